@@ -0,0 +1,105 @@
+use std::f32::consts::{PI, TAU};
+
+/// Maps the linear progress of a [`Tween`](super::Tween) (0 to 1) to the share of the change
+/// applied. `In` curves start slow, `Out` curves end slow.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    InQuad,
+    OutQuad,
+    InOutQuad,
+    InCubic,
+    OutCubic,
+    InOutCubic,
+    InOutSine,
+    /// Overshoots the target a little before settling.
+    OutBack,
+    /// Springs past the target a few times.
+    OutElastic,
+    /// A CSS style `cubic-bezier(x1, y1, x2, y2)` curve.
+    CubicBezier(f32, f32, f32, f32),
+}
+
+impl Easing {
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match *self {
+            Easing::Linear => t,
+            Easing::InQuad => t * t,
+            Easing::OutQuad => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::InOutQuad => {
+                if t < 0.5 { 2.0 * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(2) / 2.0 }
+            }
+            Easing::InCubic => t * t * t,
+            Easing::OutCubic => 1.0 - (1.0 - t).powi(3),
+            Easing::InOutCubic => {
+                if t < 0.5 { 4.0 * t * t * t } else { 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0 }
+            }
+            Easing::InOutSine => -((PI * t).cos() - 1.0) / 2.0,
+            Easing::OutBack => {
+                let c1 = 1.70158;
+                let c3 = c1 + 1.0;
+                1.0 + c3 * (t - 1.0).powi(3) + c1 * (t - 1.0).powi(2)
+            }
+            Easing::OutElastic => {
+                if t == 0.0 || t == 1.0 {
+                    return t;
+                }
+                2f32.powf(-10.0 * t) * ((t * 10.0 - 0.75) * TAU / 3.0).sin() + 1.0
+            }
+            Easing::CubicBezier(x1, y1, x2, y2) => cubic_bezier(x1, y1, x2, y2, t),
+        }
+    }
+}
+
+/// y of the curve from (0, 0) to (1, 1) with control points (x1, y1) and (x2, y2) at `x`.
+fn cubic_bezier(x1: f32, y1: f32, x2: f32, y2: f32, x: f32) -> f32 {
+    let bezier = |p1: f32, p2: f32, s: f32| {
+        let r = 1.0 - s;
+        3.0 * r * r * s * p1 + 3.0 * r * s * s * p2 + s * s * s
+    };
+    // x(s) is monotonic for x1 and x2 in 0..=1, so bisection always finds s
+    let (mut lo, mut hi) = (0.0f32, 1.0f32);
+    let mut s = x;
+    for _ in 0..24 {
+        let v = bezier(x1, x2, s);
+        if (v - x).abs() < 1e-5 {
+            break;
+        }
+        if v < x { lo = s } else { hi = s }
+        s = (lo + hi) / 2.0;
+    }
+    bezier(y1, y2, s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_easing_end_points() {
+        let all = [
+            Easing::Linear,
+            Easing::InQuad,
+            Easing::OutQuad,
+            Easing::InOutQuad,
+            Easing::InCubic,
+            Easing::OutCubic,
+            Easing::InOutCubic,
+            Easing::InOutSine,
+            Easing::OutBack,
+            Easing::OutElastic,
+            Easing::CubicBezier(0.25, 0.1, 0.25, 1.0),
+        ];
+        for e in all {
+            assert!(e.apply(0.0).abs() < 1e-4, "{:?}", e);
+            assert!((e.apply(1.0) - 1.0).abs() < 1e-4, "{:?}", e);
+        }
+        assert_eq!(0.25, Easing::InQuad.apply(0.5));
+        assert_eq!(0.5, Easing::InOutCubic.apply(0.5));
+        assert!(Easing::OutBack.apply(0.8) > 1.0);
+        // the linear bezier is the identity
+        assert!((Easing::CubicBezier(0.3, 0.3, 0.7, 0.7).apply(0.42) - 0.42).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,167 @@
+//! Time based changes of widget properties: [`Tween`]s with [`Easing`] curves, [`Spring`]s,
+//! [`Sequence`] and [`Parallel`] timelines, and [`Transition`]s for state changes like a hover
+//! fade. Everything is advanced by a time step in seconds, which the editor takes from its
+//! [`FrameClock`] and hands to [`UIElement::animate`](crate::gui_elements::UIElement::animate)
+//! every frame.
+
+pub mod easing;
+pub mod spring;
+pub mod timeline;
+pub mod tween;
+
+use std::any::Any;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use crate::gui_elements::color::Color;
+pub use easing::Easing;
+pub use spring::Spring;
+pub use timeline::{Parallel, Sequence};
+pub use tween::{Transition, Tween};
+
+/// Longest step [`FrameClock::tick`] returns, so a stalled frame does not skip animations.
+pub const MAX_FRAME_TIME: f32 = 0.1;
+
+/// A source of time, see [`FrameClock`].
+pub trait Clock {
+    /// Time since some fixed point, e.g. when the clock was made.
+    fn now(&self) -> Duration;
+}
+
+/// Wall time.
+#[derive(Debug, Clone)]
+pub struct SystemClock {
+    start: Instant,
+}
+
+impl SystemClock {
+    pub fn new() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// A clock that only moves when told to, for stepping animations in tests. Clones share the
+/// same time.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    time: Rc<Cell<Duration>>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn advance(&self, dt: Duration) {
+        self.time.set(self.time.get() + dt);
+    }
+
+    pub fn set(&self, time: Duration) {
+        self.time.set(time);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.time.get()
+    }
+}
+
+/// Measures the time between frames.
+pub struct FrameClock {
+    clock: Box<dyn Clock>,
+    last: Option<Duration>,
+}
+
+impl FrameClock {
+    pub fn new(clock: Box<dyn Clock>) -> Self {
+        Self { clock, last: None }
+    }
+
+    /// Seconds since the previous tick, 0 on the first one and at most [`MAX_FRAME_TIME`].
+    pub fn tick(&mut self) -> f32 {
+        let now = self.clock.now();
+        let dt = self.last.map(|last| now.saturating_sub(last).as_secs_f32()).unwrap_or(0.0);
+        self.last = Some(now);
+        dt.min(MAX_FRAME_TIME)
+    }
+
+    /// Makes the next tick the first again, e.g. after the window was closed.
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+}
+
+impl Default for FrameClock {
+    fn default() -> Self {
+        Self::new(Box::new(SystemClock::new()))
+    }
+}
+
+/// Values a [`Tween`] can interpolate.
+pub trait Lerp: Copy {
+    /// `self` at `t` 0, `other` at 1. Easing curves may pass `t` outside that range.
+    fn lerp(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl<const N: usize> Lerp for [f32; N] {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        std::array::from_fn(|i| self[i].lerp(&other[i], t))
+    }
+}
+
+impl Lerp for Color {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Color::lerp(self, *other, t)
+    }
+}
+
+/// Something that changes over time and ends, so it can be put in a [`Sequence`] or
+/// [`Parallel`].
+pub trait Animation: Any {
+    /// Moves forward by `dt` seconds. Returns the part of `dt` left over once the animation
+    /// has finished, 0 while it is still running.
+    fn advance(&mut self, dt: f32) -> f32;
+
+    fn is_finished(&self) -> bool;
+
+    /// Goes back to the start.
+    fn reset(&mut self);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_clock() {
+        let clock = ManualClock::new();
+        let mut frames = FrameClock::new(Box::new(clock.clone()));
+        assert_eq!(0.0, frames.tick());
+        clock.advance(Duration::from_millis(20));
+        assert!((frames.tick() - 0.02).abs() < 1e-6);
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(MAX_FRAME_TIME, frames.tick());
+        frames.reset();
+        clock.advance(Duration::from_millis(20));
+        assert_eq!(0.0, frames.tick());
+    }
+}
@@ -0,0 +1,105 @@
+use super::Animation;
+
+/// Integration step, small enough to stay stable for stiff springs.
+const STEP: f32 = 1.0 / 240.0;
+
+/// A damped spring pulling `value` towards `target`. Unlike a [`Tween`](super::Tween) it has no
+/// fixed duration and keeps its velocity when the target changes, which makes dragged or
+/// interrupted motion look natural.
+#[derive(Debug, Clone)]
+pub struct Spring {
+    pub value: f32,
+    pub velocity: f32,
+    pub target: f32,
+    pub stiffness: f32,
+    pub damping: f32,
+    pub mass: f32,
+    /// Distance and speed below which the spring snaps to the target and stops.
+    pub precision: f32,
+    start: (f32, f32),
+}
+
+impl Spring {
+    /// A spring at rest at `value` with a quick, barely overshooting response.
+    pub fn new(value: f32) -> Self {
+        Self {
+            value,
+            velocity: 0.0,
+            target: value,
+            stiffness: 170.0,
+            damping: 26.0,
+            mass: 1.0,
+            precision: 1e-3,
+            start: (value, value),
+        }
+    }
+
+    pub fn stiffness(self, stiffness: f32, damping: f32) -> Self {
+        Self { stiffness, damping, ..self }
+    }
+
+    /// Moves the target. The current velocity is kept.
+    pub fn set_target(&mut self, target: f32) {
+        self.target = target;
+    }
+
+    fn settled(&self) -> bool {
+        (self.value - self.target).abs() < self.precision && self.velocity.abs() < self.precision
+    }
+}
+
+impl Animation for Spring {
+    fn advance(&mut self, dt: f32) -> f32 {
+        if self.settled() {
+            return dt;
+        }
+        let mut left = dt;
+        while left > 0.0 {
+            let h = left.min(STEP);
+            let force = -self.stiffness * (self.value - self.target) - self.damping * self.velocity;
+            self.velocity += force / self.mass.max(1e-6) * h;
+            self.value += self.velocity * h;
+            left -= h;
+            if self.settled() {
+                self.value = self.target;
+                self.velocity = 0.0;
+                return left;
+            }
+        }
+        0.0
+    }
+
+    fn is_finished(&self) -> bool {
+        self.settled()
+    }
+
+    /// Back to the value and target it was made with, at rest.
+    fn reset(&mut self) {
+        (self.value, self.target) = self.start;
+        self.velocity = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spring_settles() {
+        let mut s = Spring::new(0.0);
+        assert!(s.is_finished());
+        s.set_target(100.0);
+        s.advance(0.1);
+        assert!(s.value > 0.0 && s.value < 100.0 && s.velocity > 0.0);
+        let mut time = 0.1;
+        while !s.is_finished() && time < 5.0 {
+            s.advance(1.0 / 60.0);
+            time += 1.0 / 60.0;
+        }
+        assert!(time < 2.0);
+        assert_eq!(100.0, s.value);
+
+        s.reset();
+        assert_eq!((0.0, 0.0), (s.value, s.target));
+    }
+}
@@ -0,0 +1,123 @@
+use std::any::Any;
+use super::Animation;
+
+/// Downcasts a boxed animation to read its value, e.g. a [`Tween`](super::Tween) in a timeline.
+fn downcast<A: Animation>(animation: &dyn Animation) -> Option<&A> {
+    (animation as &dyn Any).downcast_ref()
+}
+
+/// Runs animations one after another. Time left over when one finishes goes to the next.
+#[derive(Default)]
+pub struct Sequence {
+    items: Vec<Box<dyn Animation>>,
+    current: usize,
+}
+
+impl Sequence {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn then<A: Animation>(mut self, animation: A) -> Self {
+        self.items.push(Box::new(animation));
+        self
+    }
+
+    /// The `index`th animation, if it is an `A`.
+    pub fn get<A: Animation>(&self, index: usize) -> Option<&A> {
+        self.items.get(index).and_then(|a| downcast(a.as_ref()))
+    }
+
+    /// Index of the running animation, the length once all have finished.
+    pub fn current(&self) -> usize {
+        self.current
+    }
+}
+
+impl Animation for Sequence {
+    fn advance(&mut self, mut dt: f32) -> f32 {
+        while let Some(item) = self.items.get_mut(self.current) {
+            dt = item.advance(dt);
+            if !item.is_finished() {
+                return 0.0;
+            }
+            self.current += 1;
+        }
+        dt
+    }
+
+    fn is_finished(&self) -> bool {
+        self.current >= self.items.len()
+    }
+
+    fn reset(&mut self) {
+        self.items.iter_mut().for_each(|a| a.reset());
+        self.current = 0;
+    }
+}
+
+/// Runs animations at the same time, finishing with the longest.
+#[derive(Default)]
+pub struct Parallel {
+    items: Vec<Box<dyn Animation>>,
+}
+
+impl Parallel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with<A: Animation>(mut self, animation: A) -> Self {
+        self.items.push(Box::new(animation));
+        self
+    }
+
+    /// The `index`th animation, if it is an `A`.
+    pub fn get<A: Animation>(&self, index: usize) -> Option<&A> {
+        self.items.get(index).and_then(|a| downcast(a.as_ref()))
+    }
+}
+
+impl Animation for Parallel {
+    fn advance(&mut self, dt: f32) -> f32 {
+        // finished items hand back all of dt, so the smallest leftover is the longest runner's
+        self.items.iter_mut().map(|a| a.advance(dt)).fold(dt, f32::min)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.items.iter().all(|a| a.is_finished())
+    }
+
+    fn reset(&mut self) {
+        self.items.iter_mut().for_each(|a| a.reset());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::animation::Tween;
+
+    #[test]
+    fn test_sequence_and_parallel() {
+        let mut fade = Parallel::new()
+            .with(Tween::new(0.0, 1.0, 1.0))
+            .with(Tween::new(0.0, 10.0, 2.0));
+        let mut seq = Sequence::new().then(Tween::new(0.0, 1.0, 0.5));
+        assert_eq!(0.0, fade.advance(1.5));
+        assert_eq!(Some(1.0), fade.get::<Tween<f32>>(0).map(|t| t.value()));
+        assert_eq!(Some(7.5), fade.get::<Tween<f32>>(1).map(|t| t.value()));
+        assert!(fade.get::<Tween<[f32; 2]>>(0).is_none());
+        assert_eq!(0.5, fade.advance(1.0));
+        assert!(fade.is_finished());
+
+        fade.reset();
+        seq = seq.then(fade);
+        // the first tween's leftover 0.25s already moves the parallel part
+        assert_eq!(0.0, seq.advance(0.75));
+        assert_eq!(1, seq.current());
+        assert_eq!(Some(0.25), seq.get::<Parallel>(1).and_then(|p| p.get::<Tween<f32>>(0)).map(|t| t.value()));
+        assert_eq!(1.25, seq.advance(3.0));
+        assert!(seq.is_finished());
+    }
+}
@@ -0,0 +1,155 @@
+use super::{Animation, Easing, Lerp};
+
+/// Moves a value from `from` to `to` over `duration` seconds along an [`Easing`] curve.
+#[derive(Debug, Clone)]
+pub struct Tween<T: Lerp> {
+    pub from: T,
+    pub to: T,
+    pub duration: f32,
+    /// Seconds to wait before starting.
+    pub delay: f32,
+    pub easing: Easing,
+    elapsed: f32,
+}
+
+impl<T: Lerp> Tween<T> {
+    pub fn new(from: T, to: T, duration: f32) -> Self {
+        Self { from, to, duration, delay: 0.0, easing: Easing::Linear, elapsed: 0.0 }
+    }
+
+    pub fn easing(self, easing: Easing) -> Self {
+        Self { easing, ..self }
+    }
+
+    pub fn delay(self, delay: f32) -> Self {
+        Self { delay, ..self }
+    }
+
+    /// Progress from 0 to 1, before easing.
+    pub fn progress(&self) -> f32 {
+        if self.duration <= 0.0 {
+            return if self.elapsed >= self.delay { 1.0 } else { 0.0 };
+        }
+        ((self.elapsed - self.delay) / self.duration).clamp(0.0, 1.0)
+    }
+
+    pub fn value(&self) -> T {
+        self.from.lerp(&self.to, self.easing.apply(self.progress()))
+    }
+
+    /// Heads for `to` from the current value, starting over without delay.
+    pub fn retarget(&mut self, to: T) {
+        self.from = self.value();
+        self.to = to;
+        self.delay = 0.0;
+        self.elapsed = 0.0;
+    }
+}
+
+impl<T: Lerp + 'static> Animation for Tween<T> {
+    fn advance(&mut self, dt: f32) -> f32 {
+        let total = self.delay + self.duration.max(0.0);
+        let leftover = (self.elapsed + dt - total).max(0.0);
+        self.elapsed = (self.elapsed + dt).min(total);
+        leftover
+    }
+
+    fn is_finished(&self) -> bool {
+        self.elapsed >= self.delay + self.duration.max(0.0)
+    }
+
+    fn reset(&mut self) {
+        self.elapsed = 0.0;
+    }
+}
+
+/// A value that eases to every new target instead of jumping, e.g. a hover color or the
+/// offset of a sliding panel. Set the target from event handling and call
+/// [`Transition::update`] every frame.
+#[derive(Debug, Clone)]
+pub struct Transition<T: Lerp> {
+    tween: Tween<T>,
+}
+
+impl<T: Lerp + PartialEq + 'static> Transition<T> {
+    pub fn new(value: T, duration: f32, easing: Easing) -> Self {
+        let mut tween = Tween::new(value, value, duration).easing(easing);
+        tween.advance(duration);
+        Self { tween }
+    }
+
+    pub fn value(&self) -> T {
+        self.tween.value()
+    }
+
+    pub fn target(&self) -> T {
+        self.tween.to
+    }
+
+    /// Starts moving to `target` unless already headed there.
+    pub fn set(&mut self, target: T) {
+        if target != self.tween.to {
+            self.tween.retarget(target);
+        }
+    }
+
+    /// Goes to `value` at once.
+    pub fn jump(&mut self, value: T) {
+        self.tween.from = value;
+        self.tween.to = value;
+        self.tween.advance(self.tween.duration);
+    }
+
+    /// Advances by `dt` seconds. Returns true while still moving.
+    pub fn update(&mut self, dt: f32) -> bool {
+        self.tween.advance(dt);
+        self.is_running()
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.tween.is_finished()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tween() {
+        let mut t = Tween::new(10.0, 20.0, 1.0).delay(0.5);
+        assert_eq!(0.0, t.advance(0.5));
+        assert_eq!(10.0, t.value());
+        assert_eq!(0.0, t.advance(0.5));
+        assert_eq!(15.0, t.value());
+        assert_eq!(0.25, t.advance(0.75));
+        assert!(t.is_finished());
+        assert_eq!(20.0, t.value());
+
+        t.retarget(0.0);
+        t.advance(0.25);
+        assert_eq!(15.0, t.value());
+
+        let mut eased = Tween::new([0.0, 0.0], [4.0, 8.0], 2.0).easing(Easing::InQuad);
+        eased.advance(1.0);
+        assert_eq!([1.0, 2.0], eased.value());
+    }
+
+    #[test]
+    fn test_transition() {
+        let mut t = Transition::new(0.0, 0.2, Easing::Linear);
+        assert!(!t.is_running());
+        t.set(1.0);
+        assert!(t.update(0.1));
+        assert!((t.value() - 0.5).abs() < 1e-6);
+        // changing direction midway starts from where it is
+        t.set(0.0);
+        assert!(t.update(0.1));
+        assert!((t.value() - 0.25).abs() < 1e-6);
+        assert!(!t.update(0.1));
+        assert_eq!(0.0, t.value());
+        t.jump(1.0);
+        assert!(!t.is_running());
+        assert_eq!(1.0, t.value());
+    }
+}
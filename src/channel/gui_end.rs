@@ -0,0 +1,26 @@
+use std::sync::Arc;
+use parking_lot::{Mutex, MutexGuard};
+
+/// The editor side end of a channel, e.g. a [`Consumer`](super::Consumer) or a
+/// [`TripleReader`](super::TripleReader), shared by every widget made from it. Cloning is
+/// cheap, so a new widget can be handed the same feed each time the editor opens. Only GUI
+/// side clones ever take the lock, the audio thread holds the other end directly.
+pub struct GuiEnd<T> {
+    inner: Arc<Mutex<T>>,
+}
+
+impl<T> GuiEnd<T> {
+    pub fn new(end: T) -> Self {
+        Self { inner: Arc::new(Mutex::new(end)) }
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        self.inner.lock()
+    }
+}
+
+impl<T> Clone for GuiEnd<T> {
+    fn clone(&self) -> Self {
+        Self { inner: self.inner.clone() }
+    }
+}
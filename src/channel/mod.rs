@@ -0,0 +1,15 @@
+//! Lock-free handoff of data from the audio thread to the editor.
+//!
+//! Nothing on the audio side blocks, allocates after construction or takes a lock, so it can
+//! be used from `process`. The editor side is meant to be drained from `VstEditor::idle`,
+//! see [`VstEditor::on_idle`](crate::VstEditor::on_idle).
+
+pub mod gui_end;
+pub mod samples;
+pub mod spsc;
+pub mod triple_buffer;
+
+pub use gui_end::GuiEnd;
+pub use samples::{sample_channel, SampleSender, SampleSource};
+pub use spsc::{spsc, Consumer, Producer};
+pub use triple_buffer::{triple_buffer, TripleReader, TripleWriter};
@@ -0,0 +1,43 @@
+use super::gui_end::GuiEnd;
+use super::spsc::{spsc, Consumer, Producer};
+
+/// Creates the audio thread and GUI ends of a sample feed. `capacity` is the number of
+/// samples (or frames, for multichannel `T`) that can be queued between two editor frames.
+pub fn sample_channel<T: Copy + Send>(capacity: usize) -> (SampleSender<T>, SampleSource<T>) {
+    let (producer, consumer) = spsc(capacity);
+    (
+        SampleSender { producer },
+        SampleSource { consumer: GuiEnd::new(consumer) },
+    )
+}
+
+/// Publishes samples from the audio thread. Never blocks or allocates, samples that do not
+/// fit are dropped.
+pub struct SampleSender<T> {
+    producer: Producer<T>,
+}
+
+impl<T: Copy + Send> SampleSender<T> {
+    /// Returns the number of samples that fit.
+    pub fn push(&mut self, samples: &[T]) -> usize {
+        self.producer.push_slice(samples)
+    }
+}
+
+/// The GUI end of a sample feed.
+pub struct SampleSource<T> {
+    consumer: GuiEnd<Consumer<T>>,
+}
+
+impl<T> Clone for SampleSource<T> {
+    fn clone(&self) -> Self {
+        Self { consumer: self.consumer.clone() }
+    }
+}
+
+impl<T: Copy + Send> SampleSource<T> {
+    /// Appends every pending sample to `out`.
+    pub fn drain_into(&self, out: &mut Vec<T>) {
+        out.extend(self.consumer.lock().drain());
+    }
+}
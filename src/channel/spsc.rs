@@ -0,0 +1,221 @@
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+struct Shared<T> {
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    // next slot the producer writes
+    head: AtomicUsize,
+    // next slot the consumer reads
+    tail: AtomicUsize,
+}
+
+// A slot is only written by the producer before `head` is published past it and only read
+// by the consumer before `tail` is published past it, so no slot is ever accessed twice at once.
+unsafe impl<T: Send> Send for Shared<T> {}
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    fn next(&self, i: usize) -> usize {
+        (i + 1) % self.slots.len()
+    }
+}
+
+impl<T> Drop for Shared<T> {
+    fn drop(&mut self) {
+        let head = *self.head.get_mut();
+        let mut tail = *self.tail.get_mut();
+        while tail != head {
+            unsafe { self.slots[tail].get_mut().assume_init_drop() };
+            tail = self.next(tail);
+        }
+    }
+}
+
+/// Creates a wait-free single-producer single-consumer queue holding up to `capacity` values.
+/// All memory is allocated here, pushing and popping never allocate or block.
+pub fn spsc<T: Send>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    // one slot is kept empty to tell a full queue from an empty one
+    let slots = (0..capacity.max(1) + 1)
+        .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+        .collect::<Vec<_>>()
+        .into_boxed_slice();
+    let shared = Arc::new(Shared {
+        slots,
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+    });
+    (Producer { shared: shared.clone() }, Consumer { shared })
+}
+
+/// The writing end, normally owned by the audio thread.
+pub struct Producer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Send> Producer<T> {
+    /// Pushes `value`, handing it back if the queue is full.
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        let shared = &*self.shared;
+        let head = shared.head.load(Ordering::Relaxed);
+        let next = shared.next(head);
+        if next == shared.tail.load(Ordering::Acquire) {
+            return Err(value);
+        }
+        unsafe { (*shared.slots[head].get()).write(value) };
+        shared.head.store(next, Ordering::Release);
+        Ok(())
+    }
+
+    /// Pushes as many values from `values` as fit and returns how many were pushed.
+    pub fn push_slice(&mut self, values: &[T]) -> usize
+    where
+        T: Copy,
+    {
+        let shared = &*self.shared;
+        let tail = shared.tail.load(Ordering::Acquire);
+        let mut head = shared.head.load(Ordering::Relaxed);
+        let mut count = 0;
+        for v in values {
+            let next = shared.next(head);
+            if next == tail {
+                break;
+            }
+            unsafe { (*shared.slots[head].get()).write(*v) };
+            head = next;
+            count += 1;
+        }
+        shared.head.store(head, Ordering::Release);
+        count
+    }
+
+    /// Number of values that can currently be pushed.
+    pub fn free_len(&self) -> usize {
+        self.shared.slots.len() - 1 - len(&self.shared)
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shared.slots.len() - 1
+    }
+}
+
+/// The reading end, normally drained by the editor each frame.
+pub struct Consumer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T: Send> Consumer<T> {
+    pub fn pop(&mut self) -> Option<T> {
+        let shared = &*self.shared;
+        let tail = shared.tail.load(Ordering::Relaxed);
+        if tail == shared.head.load(Ordering::Acquire) {
+            return None;
+        }
+        let value = unsafe { (*shared.slots[tail].get()).assume_init_read() };
+        shared.tail.store(shared.next(tail), Ordering::Release);
+        Some(value)
+    }
+
+    /// Pops every value currently in the queue.
+    pub fn drain(&mut self) -> Drain<'_, T> {
+        Drain { consumer: self }
+    }
+
+    /// Pops everything and returns only the most recent value, if any.
+    pub fn latest(&mut self) -> Option<T> {
+        self.drain().last()
+    }
+
+    /// Number of values waiting to be popped.
+    pub fn len(&self) -> usize {
+        len(&self.shared)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.shared.slots.len() - 1
+    }
+}
+
+fn len<T>(shared: &Shared<T>) -> usize {
+    let head = shared.head.load(Ordering::Acquire);
+    let tail = shared.tail.load(Ordering::Acquire);
+    (head + shared.slots.len() - tail) % shared.slots.len()
+}
+
+pub struct Drain<'a, T: Send> {
+    consumer: &'a mut Consumer<T>,
+}
+
+impl<'a, T: Send> Iterator for Drain<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        self.consumer.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_push_pop() {
+        let (mut p, mut c) = spsc(3);
+        assert_eq!(Ok(()), p.push(1));
+        assert_eq!(Ok(()), p.push(2));
+        assert_eq!(Ok(()), p.push(3));
+        assert_eq!(Err(4), p.push(4));
+        assert_eq!(0, p.free_len());
+        assert_eq!(3, c.len());
+        assert_eq!(Some(1), c.pop());
+        assert_eq!(Ok(()), p.push(4));
+        assert_eq!(vec![2, 3, 4], c.drain().collect::<Vec<_>>());
+        assert!(c.is_empty());
+    }
+
+    #[test]
+    fn test_push_slice() {
+        let (mut p, mut c) = spsc(4);
+        assert_eq!(4, p.push_slice(&[1.0, 2.0, 3.0, 4.0, 5.0]));
+        assert_eq!(Some(4.0), c.latest());
+        assert_eq!(None, c.pop());
+    }
+
+    #[test]
+    fn test_drops_remaining() {
+        let value = Arc::new(());
+        let (mut p, c) = spsc(4);
+        p.push(value.clone()).unwrap();
+        p.push(value.clone()).unwrap();
+        assert_eq!(3, Arc::strong_count(&value));
+        drop(p);
+        drop(c);
+        assert_eq!(1, Arc::strong_count(&value));
+    }
+
+    #[test]
+    fn test_across_threads() {
+        let (mut p, mut c) = spsc(16);
+        let t = thread::spawn(move || {
+            let mut i = 0;
+            while i < 10_000 {
+                if p.push(i).is_ok() {
+                    i += 1;
+                }
+            }
+        });
+        let mut expected = 0;
+        while expected < 10_000 {
+            if let Some(v) = c.pop() {
+                assert_eq!(expected, v);
+                expected += 1;
+            }
+        }
+        t.join().unwrap();
+    }
+}
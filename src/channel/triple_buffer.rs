@@ -0,0 +1,134 @@
+use std::cell::UnsafeCell;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+
+// set on the back buffer index when it holds data the reader has not picked up yet
+const DIRTY: u8 = 0b100;
+const INDEX: u8 = 0b011;
+
+struct Shared<T> {
+    buffers: [UnsafeCell<T>; 3],
+    back: AtomicU8,
+}
+
+// The writer and the reader each own one buffer exclusively and only trade ownership of
+// the third through the atomic swap of `back`, so no buffer is ever shared.
+unsafe impl<T: Send> Send for Shared<T> {}
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+/// Creates a triple buffer starting with `init` in every slot.
+///
+/// Unlike a queue, a triple buffer only ever hands over the latest complete value. The writer
+/// never waits for the reader and the reader never sees a half written value, which makes it
+/// a good fit for meter readings and spectra where only the newest state matters.
+pub fn triple_buffer<T: Clone + Send>(init: T) -> (TripleWriter<T>, TripleReader<T>) {
+    let shared = Arc::new(Shared {
+        buffers: [
+            UnsafeCell::new(init.clone()),
+            UnsafeCell::new(init.clone()),
+            UnsafeCell::new(init),
+        ],
+        back: AtomicU8::new(1),
+    });
+    (
+        TripleWriter { shared: shared.clone(), index: 0 },
+        TripleReader { shared, index: 2 },
+    )
+}
+
+/// The writing end, normally owned by the audio thread.
+pub struct TripleWriter<T> {
+    shared: Arc<Shared<T>>,
+    index: u8,
+}
+
+impl<T: Send> TripleWriter<T> {
+    /// The buffer being written. Its contents are whatever was there when it was last traded,
+    /// which is not necessarily the last published value.
+    pub fn input_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.shared.buffers[self.index as usize].get() }
+    }
+
+    /// Makes the input buffer visible to the reader.
+    pub fn publish(&mut self) {
+        let old = self.shared.back.swap(self.index | DIRTY, Ordering::AcqRel);
+        self.index = old & INDEX;
+    }
+
+    /// Replaces the input buffer with `value` and publishes it.
+    pub fn write(&mut self, value: T) {
+        *self.input_mut() = value;
+        self.publish();
+    }
+}
+
+/// The reading end, normally polled by the editor each frame.
+pub struct TripleReader<T> {
+    shared: Arc<Shared<T>>,
+    index: u8,
+}
+
+impl<T: Send> TripleReader<T> {
+    /// Whether a value has been published since the last call to `read`.
+    pub fn updated(&self) -> bool {
+        self.shared.back.load(Ordering::Relaxed) & DIRTY != 0
+    }
+
+    /// Returns the most recently published value.
+    pub fn read(&mut self) -> &T {
+        if self.updated() {
+            let old = self.shared.back.swap(self.index, Ordering::AcqRel);
+            self.index = old & INDEX;
+        }
+        unsafe { &*self.shared.buffers[self.index as usize].get() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_latest_wins() {
+        let (mut w, mut r) = triple_buffer(0);
+        assert!(!r.updated());
+        assert_eq!(0, *r.read());
+        w.write(1);
+        w.write(2);
+        assert!(r.updated());
+        assert_eq!(2, *r.read());
+        assert!(!r.updated());
+        assert_eq!(2, *r.read());
+        w.write(3);
+        assert_eq!(3, *r.read());
+    }
+
+    #[test]
+    fn test_input_mut() {
+        let (mut w, mut r) = triple_buffer([0.0_f32; 4]);
+        w.input_mut()[1] = 0.5;
+        assert_eq!([0.0; 4], *r.read());
+        w.publish();
+        assert_eq!([0.0, 0.5, 0.0, 0.0], *r.read());
+    }
+
+    #[test]
+    fn test_across_threads() {
+        let (mut w, mut r) = triple_buffer([0_u32; 64]);
+        let t = thread::spawn(move || {
+            for i in 1..=10_000 {
+                w.write([i; 64]);
+            }
+        });
+        let mut last = 0;
+        while last < 10_000 {
+            let v = r.read();
+            // every element comes from the same write and writes arrive in order
+            assert!(v.iter().all(|x| *x == v[0]));
+            assert!(v[0] >= last);
+            last = v[0];
+        }
+        t.join().unwrap();
+    }
+}
@@ -0,0 +1,221 @@
+use std::error::Error;
+
+/// A point of a [`Curve`]. `tension` bends the segment that starts here.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CurvePoint {
+    pub x: f32,
+    pub y: f32,
+    /// -1 to 1, zero is a straight line.
+    pub tension: f32,
+}
+
+impl CurvePoint {
+    pub fn new(x: f32, y: f32, tension: f32) -> Self {
+        Self { x, y, tension }
+    }
+}
+
+const FORMAT_VERSION: u8 = 1;
+
+/// A function on 0..1 made of points joined by quadratic Bezier segments, e.g. a waveshaper
+/// transfer function, a velocity curve or one cycle of an LFO.
+///
+/// Points are kept sorted by `x`, the first one sits at 0 and the last one at 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve {
+    points: Vec<CurvePoint>,
+}
+
+impl Default for Curve {
+    /// A straight line from (0, 0) to (1, 1).
+    fn default() -> Self {
+        Self::new(vec![CurvePoint::new(0.0, 0.0, 0.0), CurvePoint::new(1.0, 1.0, 0.0)])
+    }
+}
+
+impl Curve {
+    /// Sorts the points, clamps them to 0..1 and pins the ends to x = 0 and x = 1.
+    pub fn new(mut points: Vec<CurvePoint>) -> Self {
+        for p in points.iter_mut() {
+            p.x = p.x.clamp(0.0, 1.0);
+            p.y = p.y.clamp(0.0, 1.0);
+            p.tension = p.tension.clamp(-1.0, 1.0);
+        }
+        points.sort_by(|a, b| a.x.total_cmp(&b.x));
+        match points.len() {
+            0 => return Self::default(),
+            1 => points.push(CurvePoint::new(1.0, points[0].y, 0.0)),
+            _ => {}
+        }
+        points[0].x = 0.0;
+        points.last_mut().unwrap().x = 1.0;
+        Self { points }
+    }
+
+    pub fn points(&self) -> &[CurvePoint] {
+        &self.points
+    }
+
+    /// Inserts a point and returns its index.
+    pub fn insert(&mut self, x: f32, y: f32) -> usize {
+        let x = x.clamp(0.0, 1.0);
+        let i = self.points.iter().position(|p| p.x > x).unwrap_or(self.points.len() - 1).max(1);
+        self.points.insert(i, CurvePoint::new(x, y.clamp(0.0, 1.0), 0.0));
+        i
+    }
+
+    /// Removes an inner point. The end points stay, returns false for them.
+    pub fn remove(&mut self, index: usize) -> bool {
+        if index == 0 || index + 1 >= self.points.len() {
+            return false;
+        }
+        self.points.remove(index);
+        true
+    }
+
+    /// Moves a point, keeping it between its neighbours. End points only move vertically.
+    pub fn move_point(&mut self, index: usize, x: f32, y: f32) {
+        let last = self.points.len() - 1;
+        let x = match index {
+            0 => 0.0,
+            i if i == last => 1.0,
+            i => x.clamp(self.points[i - 1].x, self.points[i + 1].x),
+        };
+        self.points[index].x = x;
+        self.points[index].y = y.clamp(0.0, 1.0);
+    }
+
+    /// Sets the tension of the segment starting at `index`.
+    pub fn set_tension(&mut self, index: usize, tension: f32) {
+        self.points[index].tension = tension.clamp(-1.0, 1.0);
+    }
+
+    /// The Bezier control point of the segment starting at `index`. Tension moves it along
+    /// the diagonal of the segment's bounding box, so the segment stays a function of x.
+    fn control(&self, index: usize) -> [f32; 2] {
+        let (a, b) = (self.points[index], self.points[index + 1]);
+        let t = a.tension;
+        [
+            (a.x + b.x) / 2.0 - t * (b.x - a.x) / 2.0,
+            (a.y + b.y) / 2.0 + t * (b.y - a.y) / 2.0,
+        ]
+    }
+
+    /// The point at Bezier parameter `t` of the segment starting at `index`.
+    pub fn segment_point(&self, index: usize, t: f32) -> [f32; 2] {
+        let (a, b, c) = (self.points[index], self.points[index + 1], self.control(index));
+        let u = 1.0 - t;
+        [
+            u * u * a.x + 2.0 * u * t * c[0] + t * t * b.x,
+            u * u * a.y + 2.0 * u * t * c[1] + t * t * b.y,
+        ]
+    }
+
+    /// The curve's value at `x`.
+    pub fn value(&self, x: f32) -> f32 {
+        let x = x.clamp(0.0, 1.0);
+        let i = self.points.windows(2).position(|w| x <= w[1].x).unwrap_or(self.points.len() - 2);
+        let (a, b, c) = (self.points[i], self.points[i + 1], self.control(i));
+        if b.x - a.x <= f32::EPSILON {
+            return b.y;
+        }
+        // solve x(t) = x for t, x(t) is monotonic because the control point lies between a and b
+        let qa = a.x - 2.0 * c[0] + b.x;
+        let qb = 2.0 * (c[0] - a.x);
+        let qc = a.x - x;
+        let t = if qa.abs() < 1e-6 {
+            -qc / qb
+        } else {
+            (-qb + (qb * qb - 4.0 * qa * qc).max(0.0).sqrt()) / (2.0 * qa)
+        };
+        self.segment_point(i, t.clamp(0.0, 1.0))[1]
+    }
+
+    /// `n` evenly spaced values from x = 0 to x = 1, for use on the audio thread.
+    pub fn table(&self, n: usize) -> Vec<f32> {
+        (0..n).map(|i| self.value(i as f32 / (n.max(2) - 1) as f32)).collect()
+    }
+
+    /// Packs the curve into `1 + 2 + 6 * points` bytes: a version, the point count and each
+    /// point's x, y and tension quantized to 16 bits, little endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(3 + self.points.len() * 6);
+        bytes.push(FORMAT_VERSION);
+        bytes.extend_from_slice(&(self.points.len() as u16).to_le_bytes());
+        for p in &self.points {
+            for v in [p.x, p.y, (p.tension + 1.0) / 2.0] {
+                bytes.extend_from_slice(&((v.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16).to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// Reads a curve written by [`Curve::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        match bytes.first() {
+            Some(&FORMAT_VERSION) => {}
+            Some(v) => return Err(format!("unknown curve format version {}", v).into()),
+            None => return Err("empty curve data".into()),
+        }
+        if bytes.len() < 3 {
+            return Err("truncated curve header".into());
+        }
+        let count = u16::from_le_bytes([bytes[1], bytes[2]]) as usize;
+        let body = &bytes[3..];
+        if body.len() != count * 6 {
+            return Err(format!("expected {} bytes of points, got {}", count * 6, body.len()).into());
+        }
+        let read = |i: usize| u16::from_le_bytes([body[i], body[i + 1]]) as f32 / u16::MAX as f32;
+        let points = (0..count)
+            .map(|i| CurvePoint::new(read(i * 6), read(i * 6 + 2), read(i * 6 + 4) * 2.0 - 1.0))
+            .collect();
+        Ok(Self::new(points))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value() {
+        let mut curve = Curve::default();
+        assert!((curve.value(0.25) - 0.25).abs() < 1e-5);
+        curve.set_tension(0, 1.0);
+        // the control point sits in the top left corner, the curve bulges upwards
+        assert!(curve.value(0.25) > 0.4);
+        curve.set_tension(0, -1.0);
+        assert!(curve.value(0.25) < 0.1);
+        assert_eq!(1.0, curve.value(2.0));
+    }
+
+    #[test]
+    fn test_insert_move_remove() {
+        let mut curve = Curve::default();
+        let i = curve.insert(0.5, 0.2);
+        assert_eq!(1, i);
+        assert!((curve.value(0.5) - 0.2).abs() < 1e-5);
+        curve.move_point(1, 2.0, 0.3);
+        assert_eq!(CurvePoint::new(1.0, 0.3, 0.0), curve.points()[1]);
+        assert!(!curve.remove(0));
+        assert!(curve.remove(1));
+        assert_eq!(2, curve.points().len());
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let curve = Curve::new(vec![
+            CurvePoint::new(0.0, 0.1, 0.5),
+            CurvePoint::new(0.3, 0.9, -1.0),
+            CurvePoint::new(1.0, 0.0, 0.0),
+        ]);
+        let bytes = curve.to_bytes();
+        assert_eq!(3 + 3 * 6, bytes.len());
+        let read = Curve::from_bytes(&bytes).unwrap();
+        for (a, b) in curve.points().iter().zip(read.points()) {
+            assert!((a.x - b.x).abs() < 1e-4 && (a.y - b.y).abs() < 1e-4 && (a.tension - b.tension).abs() < 1e-4);
+        }
+        assert!(Curve::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+        assert!(Curve::from_bytes(&[9]).is_err());
+    }
+}
@@ -0,0 +1,123 @@
+use std::f32::consts::PI;
+
+/// Filter shapes with coefficients from the RBJ audio EQ cookbook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterType {
+    Bell,
+    LowShelf,
+    HighShelf,
+    LowPass,
+    HighPass,
+    Notch,
+    BandPass,
+}
+
+impl FilterType {
+    /// Whether the gain affects the shape. The other types only use frequency and Q.
+    pub fn has_gain(&self) -> bool {
+        matches!(self, FilterType::Bell | FilterType::LowShelf | FilterType::HighShelf)
+    }
+}
+
+/// Coefficients of a biquad normalized so that `a0` is 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Biquad {
+    pub b0: f32,
+    pub b1: f32,
+    pub b2: f32,
+    pub a1: f32,
+    pub a2: f32,
+}
+
+impl Default for Biquad {
+    /// A filter that passes everything unchanged.
+    fn default() -> Self {
+        Self { b0: 1.0, b1: 0.0, b2: 0.0, a1: 0.0, a2: 0.0 }
+    }
+}
+
+impl Biquad {
+    /// `freq` is clamped just below Nyquist, `q` to a small positive value.
+    pub fn new(kind: FilterType, sample_rate: f32, freq: f32, q: f32, gain_db: f32) -> Self {
+        let w0 = 2.0 * PI * freq.clamp(1.0, sample_rate * 0.499) / sample_rate;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q.max(0.01));
+        let a = 10f32.powf(gain_db / 40.0);
+
+        let (b0, b1, b2, a0, a1, a2) = match kind {
+            FilterType::Bell => (1.0 + alpha * a, -2.0 * cos, 1.0 - alpha * a, 1.0 + alpha / a, -2.0 * cos, 1.0 - alpha / a),
+            FilterType::LowShelf => {
+                let s = 2.0 * a.sqrt() * alpha;
+                (
+                    a * ((a + 1.0) - (a - 1.0) * cos + s),
+                    2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                    a * ((a + 1.0) - (a - 1.0) * cos - s),
+                    (a + 1.0) + (a - 1.0) * cos + s,
+                    -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                    (a + 1.0) + (a - 1.0) * cos - s,
+                )
+            }
+            FilterType::HighShelf => {
+                let s = 2.0 * a.sqrt() * alpha;
+                (
+                    a * ((a + 1.0) + (a - 1.0) * cos + s),
+                    -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                    a * ((a + 1.0) + (a - 1.0) * cos - s),
+                    (a + 1.0) - (a - 1.0) * cos + s,
+                    2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                    (a + 1.0) - (a - 1.0) * cos - s,
+                )
+            }
+            FilterType::LowPass => ((1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+            FilterType::HighPass => ((1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+            FilterType::Notch => (1.0, -2.0 * cos, 1.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+            FilterType::BandPass => (alpha, 0.0, -alpha, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+        };
+        Self { b0: b0 / a0, b1: b1 / a0, b2: b2 / a0, a1: a1 / a0, a2: a2 / a0 }
+    }
+
+    /// Linear magnitude of the response at `freq`.
+    pub fn magnitude(&self, freq: f32, sample_rate: f32) -> f32 {
+        let w = 2.0 * PI * freq / sample_rate;
+        let (s1, c1) = w.sin_cos();
+        let (s2, c2) = (2.0 * w).sin_cos();
+        // evaluate numerator and denominator at z^-1 = e^-jw
+        let (nr, ni) = (self.b0 + self.b1 * c1 + self.b2 * c2, -(self.b1 * s1 + self.b2 * s2));
+        let (dr, di) = (1.0 + self.a1 * c1 + self.a2 * c2, -(self.a1 * s1 + self.a2 * s2));
+        ((nr * nr + ni * ni) / (dr * dr + di * di)).sqrt()
+    }
+
+    pub fn magnitude_db(&self, freq: f32, sample_rate: f32) -> f32 {
+        (20.0 * self.magnitude(freq, sample_rate).log10()).max(super::MIN_DB)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FS: f32 = 48000.0;
+
+    #[test]
+    fn test_bell_gain_at_center() {
+        let b = Biquad::new(FilterType::Bell, FS, 1000.0, 1.0, 6.0);
+        assert!((b.magnitude_db(1000.0, FS) - 6.0).abs() < 0.01);
+        assert!(b.magnitude_db(20.0, FS).abs() < 0.1);
+        assert!(Biquad::default().magnitude_db(5000.0, FS).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_shelves_and_passes() {
+        let low = Biquad::new(FilterType::LowShelf, FS, 200.0, 0.707, -12.0);
+        assert!((low.magnitude_db(10.0, FS) + 12.0).abs() < 0.1);
+        assert!(low.magnitude_db(15000.0, FS).abs() < 0.1);
+        let high = Biquad::new(FilterType::HighShelf, FS, 5000.0, 0.707, 9.0);
+        assert!((high.magnitude_db(20000.0, FS) - 9.0).abs() < 0.3);
+
+        let lp = Biquad::new(FilterType::LowPass, FS, 1000.0, std::f32::consts::FRAC_1_SQRT_2, 0.0);
+        assert!((lp.magnitude_db(1000.0, FS) + 3.01).abs() < 0.05);
+        assert!(lp.magnitude_db(50.0, FS).abs() < 0.05);
+        let notch = Biquad::new(FilterType::Notch, FS, 1000.0, 2.0, 0.0);
+        assert!(notch.magnitude_db(1000.0, FS) < -60.0);
+    }
+}
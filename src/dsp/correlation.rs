@@ -0,0 +1,69 @@
+/// Running phase correlation of a stereo signal: +1 for mono, 0 for unrelated channels and -1
+/// for one channel inverted. The sums are averaged over an exponential window.
+#[derive(Debug, Clone)]
+pub struct Correlation {
+    decay: f32,
+    lr: f32,
+    ll: f32,
+    rr: f32,
+}
+
+impl Correlation {
+    /// `time` is the integration time in seconds.
+    pub fn new(time: f32, sample_rate: f32) -> Self {
+        Self {
+            decay: (-1.0 / (time * sample_rate).max(1.0)).exp(),
+            lr: 0.0,
+            ll: 0.0,
+            rr: 0.0,
+        }
+    }
+
+    pub fn push(&mut self, pairs: &[[f32; 2]]) {
+        let (d, g) = (self.decay, 1.0 - self.decay);
+        for [l, r] in pairs {
+            self.lr = self.lr * d + l * r * g;
+            self.ll = self.ll * d + l * l * g;
+            self.rr = self.rr * d + r * r * g;
+        }
+    }
+
+    /// The current correlation. Silence reads as 0.
+    pub fn value(&self) -> f32 {
+        let energy = (self.ll * self.rr).sqrt();
+        if energy > 1e-12 {
+            (self.lr / energy).clamp(-1.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.lr = 0.0;
+        self.ll = 0.0;
+        self.rr = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(n: usize, phase: f32) -> impl Iterator<Item = f32> {
+        (0..n).map(move |i| (i as f32 * 0.05 + phase).sin())
+    }
+
+    #[test]
+    fn test_correlation() {
+        let mut c = Correlation::new(0.05, 48000.0);
+        assert_eq!(0.0, c.value());
+        c.push(&sine(4800, 0.0).map(|s| [s, s]).collect::<Vec<_>>());
+        assert!((c.value() - 1.0).abs() < 1e-3);
+        c.push(&sine(48000, 0.0).map(|s| [s, -s]).collect::<Vec<_>>());
+        assert!((c.value() + 1.0).abs() < 1e-3);
+        c.reset();
+        let quarter = std::f32::consts::FRAC_PI_2;
+        c.push(&sine(4800, 0.0).zip(sine(4800, quarter)).map(|(l, r)| [l, r]).collect::<Vec<_>>());
+        assert!(c.value().abs() < 0.05, "{}", c.value());
+    }
+}
@@ -0,0 +1,138 @@
+use std::f32::consts::PI;
+use std::ops::{Add, Mul, Sub};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Complex {
+    pub re: f32,
+    pub im: f32,
+}
+
+impl Complex {
+    pub fn new(re: f32, im: f32) -> Self {
+        Self { re, im }
+    }
+
+    pub fn norm_sqr(&self) -> f32 {
+        self.re * self.re + self.im * self.im
+    }
+
+    pub fn norm(&self) -> f32 {
+        self.norm_sqr().sqrt()
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+    fn add(self, r: Self) -> Self {
+        Self::new(self.re + r.re, self.im + r.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+    fn sub(self, r: Self) -> Self {
+        Self::new(self.re - r.re, self.im - r.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+    fn mul(self, r: Self) -> Self {
+        Self::new(self.re * r.re - self.im * r.im, self.re * r.im + self.im * r.re)
+    }
+}
+
+/// An iterative radix-2 FFT with its twiddle factors and bit reversal table precomputed.
+#[derive(Debug, Clone)]
+pub struct Fft {
+    size: usize,
+    twiddles: Vec<Complex>,
+    reversed: Vec<usize>,
+}
+
+impl Fft {
+    /// `size` must be a power of two.
+    pub fn new(size: usize) -> Self {
+        assert!(size.is_power_of_two(), "fft size {} is not a power of two", size);
+        let bits = size.trailing_zeros();
+        let twiddles = (0..size / 2)
+            .map(|k| {
+                let a = -2.0 * PI * k as f32 / size as f32;
+                Complex::new(a.cos(), a.sin())
+            })
+            .collect();
+        let reversed = (0..size)
+            .map(|i| if bits == 0 { 0 } else { i.reverse_bits() >> (usize::BITS - bits) })
+            .collect();
+        Self { size, twiddles, reversed }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Transforms `buf` in place. `buf.len()` must equal `size`.
+    pub fn process(&self, buf: &mut [Complex]) {
+        assert_eq!(self.size, buf.len());
+        for i in 0..self.size {
+            let j = self.reversed[i];
+            if i < j {
+                buf.swap(i, j);
+            }
+        }
+        let mut len = 2;
+        while len <= self.size {
+            let step = self.size / len;
+            for start in (0..self.size).step_by(len) {
+                for k in 0..len / 2 {
+                    let w = self.twiddles[k * step];
+                    let a = buf[start + k];
+                    let b = buf[start + k + len / 2] * w;
+                    buf[start + k] = a + b;
+                    buf[start + k + len / 2] = a - b;
+                }
+            }
+            len *= 2;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dft(input: &[Complex]) -> Vec<Complex> {
+        let n = input.len();
+        (0..n)
+            .map(|k| {
+                input.iter().enumerate().fold(Complex::default(), |acc, (t, x)| {
+                    let a = -2.0 * PI * (k * t) as f32 / n as f32;
+                    acc + *x * Complex::new(a.cos(), a.sin())
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_dft() {
+        let input: Vec<Complex> = (0..16)
+            .map(|i| Complex::new((i as f32 * 0.7).sin(), (i as f32 * 0.3).cos()))
+            .collect();
+        let mut out = input.clone();
+        Fft::new(16).process(&mut out);
+        for (a, b) in out.iter().zip(dft(&input)) {
+            assert!((a.re - b.re).abs() < 1e-4 && (a.im - b.im).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_sine_lands_in_bin() {
+        let n = 64;
+        let mut buf: Vec<Complex> = (0..n)
+            .map(|i| Complex::new((2.0 * PI * 5.0 * i as f32 / n as f32).sin(), 0.0))
+            .collect();
+        Fft::new(n).process(&mut buf);
+        assert!((buf[5].norm() - n as f32 / 2.0).abs() < 1e-3);
+        assert!(buf[4].norm() < 1e-3);
+    }
+}
@@ -0,0 +1,24 @@
+//! Signal processing used by the analysis widgets. All of it runs on the GUI side.
+
+pub mod biquad;
+pub mod correlation;
+pub mod fft;
+pub mod spectrum;
+
+/// Lowest level reported for silence, in dB.
+pub const MIN_DB: f32 = -144.0;
+
+/// A periodic Hann window of length `n`.
+pub fn hann(n: usize) -> Vec<f32> {
+    (0..n)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / n as f32).cos())
+        .collect()
+}
+
+pub fn power_to_db(power: f32) -> f32 {
+    if power > 0.0 {
+        (10.0 * power.log10()).max(MIN_DB)
+    } else {
+        MIN_DB
+    }
+}
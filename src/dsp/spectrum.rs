@@ -0,0 +1,135 @@
+use std::collections::VecDeque;
+use super::fft::{Complex, Fft};
+use super::{hann, power_to_db};
+
+/// Turns a stream of samples into windowed, overlapping FFT frames and keeps an exponentially
+/// averaged power spectrum of them.
+///
+/// Levels are scaled so a full scale sine reads 0 dB regardless of frame size and window.
+#[derive(Debug, Clone)]
+pub struct SpectrumAnalyzer {
+    fft: Fft,
+    window: Vec<f32>,
+    hop: usize,
+    /// How much of the previous average is kept per frame, 0 disables averaging.
+    pub averaging: f32,
+    input: VecDeque<f32>,
+    scratch: Vec<Complex>,
+    frame: Vec<f32>,
+    power: Vec<f32>,
+    scale: f32,
+}
+
+impl SpectrumAnalyzer {
+    /// `size` must be a power of two. `overlap` is the fraction of each frame shared with the
+    /// previous one, from 0 up to (not including) 1.
+    pub fn new(size: usize, overlap: f32) -> Self {
+        let window = hann(size);
+        let sum: f32 = window.iter().sum();
+        let hop = ((size as f32 * (1.0 - overlap.clamp(0.0, 0.99))) as usize).max(1);
+        Self {
+            fft: Fft::new(size),
+            window,
+            hop,
+            averaging: 0.7,
+            input: VecDeque::with_capacity(size * 2),
+            scratch: vec![Complex::default(); size],
+            frame: vec![0.0; size / 2 + 1],
+            power: vec![0.0; size / 2 + 1],
+            scale: 2.0 / sum,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.fft.size()
+    }
+
+    /// Number of bins in each frame, DC to Nyquist.
+    pub fn bins(&self) -> usize {
+        self.power.len()
+    }
+
+    /// Center frequency of `bin` in Hz.
+    pub fn bin_frequency(&self, bin: usize, sample_rate: f32) -> f32 {
+        bin as f32 * sample_rate / self.size() as f32
+    }
+
+    /// Feeds samples and calls `on_frame` with the power spectrum of every frame that
+    /// completes, before it is folded into the average. Returns the number of frames.
+    pub fn push_with<F: FnMut(&[f32])>(&mut self, samples: &[f32], mut on_frame: F) -> usize {
+        let size = self.size();
+        let mut frames = 0;
+        for s in samples {
+            self.input.push_back(*s);
+            if self.input.len() < size {
+                continue;
+            }
+            for (i, (x, w)) in self.input.iter().zip(self.window.iter()).enumerate() {
+                self.scratch[i] = Complex::new(x * w, 0.0);
+            }
+            self.fft.process(&mut self.scratch);
+            for (p, c) in self.frame.iter_mut().zip(self.scratch.iter()) {
+                *p = c.norm_sqr() * self.scale * self.scale;
+            }
+            on_frame(&self.frame);
+            for (avg, p) in self.power.iter_mut().zip(self.frame.iter()) {
+                *avg = *avg * self.averaging + p * (1.0 - self.averaging);
+            }
+            self.input.drain(..self.hop.min(self.input.len()));
+            frames += 1;
+        }
+        frames
+    }
+
+    pub fn push(&mut self, samples: &[f32]) -> usize {
+        self.push_with(samples, |_| {})
+    }
+
+    /// The averaged power spectrum, linear.
+    pub fn power(&self) -> &[f32] {
+        &self.power
+    }
+
+    /// The averaged spectrum in dB for `bin`.
+    pub fn db(&self, bin: usize) -> f32 {
+        power_to_db(self.power[bin])
+    }
+
+    pub fn reset(&mut self) {
+        self.input.clear();
+        self.power.iter_mut().for_each(|p| *p = 0.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_full_scale_sine_reads_zero_db() {
+        let size = 1024;
+        let mut a = SpectrumAnalyzer::new(size, 0.5);
+        a.averaging = 0.0;
+        // centered on bin 64
+        let samples: Vec<f32> = (0..size * 2)
+            .map(|i| (2.0 * PI * 64.0 * i as f32 / size as f32).sin())
+            .collect();
+        assert_eq!(3, a.push(&samples));
+        assert!(a.db(64).abs() < 0.1, "{}", a.db(64));
+        assert!(a.db(200) < -100.0);
+    }
+
+    #[test]
+    fn test_overlap_sets_hop() {
+        let mut a = SpectrumAnalyzer::new(256, 0.75);
+        assert_eq!(1, a.push(&[0.0; 256]));
+        assert_eq!(1, a.push(&[0.0; 64]));
+        let mut frames = 0;
+        a.push_with(&[0.0; 128], |f| {
+            assert_eq!(129, f.len());
+            frames += 1;
+        });
+        assert_eq!(2, frames);
+    }
+}
@@ -0,0 +1,332 @@
+use glyph_brush::ab_glyph::FontRef;
+use crate::channel::SampleSource;
+use crate::dsp::power_to_db;
+use crate::dsp::spectrum::SpectrumAnalyzer;
+use crate::gui_elements::shapes::{GlShapePipe, ShapeBatch};
+use crate::gui_elements::text::UIText;
+use crate::gui_elements::utils::dirty::DirtyCheck;
+use crate::gui_elements::utils::t_matrix::TMatrix;
+use crate::gui_elements::UIElement;
+use crate::gui_elements::theme::{with_alpha, Theme, Themed};
+
+/// Position of `freq` between `range.0` and `range.1` on a log axis, 0 to 1.
+pub fn freq_to_norm(freq: f32, range: (f32, f32)) -> f32 {
+    (freq / range.0).ln() / (range.1 / range.0).ln()
+}
+
+/// Inverse of [`freq_to_norm`].
+pub fn norm_to_freq(norm: f32, range: (f32, f32)) -> f32 {
+    range.0 * (range.1 / range.0).powf(norm)
+}
+
+/// Tilt in dB applied at `freq` for a slope in dB per octave, pivoting at 1 kHz.
+pub fn slope_db(freq: f32, slope: f32) -> f32 {
+    slope * (freq / 1000.0).log2()
+}
+
+/// Resamples a linear power spectrum onto `columns` log spaced columns and returns dB values.
+/// Columns wider than a bin take the loudest bin they cover, narrower ones interpolate.
+pub fn spectrum_to_columns(power: &[f32], bin_hz: f32, columns: usize, range: (f32, f32)) -> Vec<f32> {
+    let last = power.len().saturating_sub(1);
+    (0..columns)
+        .map(|c| {
+            let f0 = norm_to_freq(c as f32 / columns as f32, range) / bin_hz;
+            let f1 = norm_to_freq((c + 1) as f32 / columns as f32, range) / bin_hz;
+            let (b0, b1) = (f0.ceil() as usize, f1.floor() as usize);
+            let p = if b1 >= b0 && b0 <= last {
+                power[b0..=b1.min(last)].iter().cloned().fold(0.0, f32::max)
+            } else {
+                let center = ((f0 + f1) / 2.0).min(last as f32);
+                let i = center.floor() as usize;
+                let t = center - i as f32;
+                power[i] * (1.0 - t) + power[(i + 1).min(last)] * t
+            };
+            power_to_db(p)
+        })
+        .collect()
+}
+
+/// Averages each value with its neighbours within `radius` columns.
+pub fn smooth(values: &[f32], radius: usize) -> Vec<f32> {
+    if radius == 0 {
+        return values.to_vec();
+    }
+    (0..values.len())
+        .map(|i| {
+            let lo = i.saturating_sub(radius);
+            let hi = (i + radius).min(values.len() - 1);
+            values[lo..=hi].iter().sum::<f32>() / (hi - lo + 1) as f32
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AnalyzerColors {
+    pub background: [f32; 4],
+    pub grid: [f32; 4],
+    pub label: [f32; 4],
+    pub line: [f32; 4],
+    pub fill_top: [f32; 4],
+    pub fill_bottom: [f32; 4],
+    pub peak: [f32; 4],
+}
+
+impl AnalyzerColors {
+    pub fn from_theme(theme: &Theme) -> Self {
+        Self {
+            background: theme.panel,
+            grid: theme.grid,
+            label: theme.text_dim,
+            line: theme.secondary.normal,
+            fill_top: with_alpha(theme.secondary.normal, 0.45),
+            fill_bottom: with_alpha(theme.secondary.normal, 0.05),
+            peak: with_alpha(theme.accent.normal, 0.8),
+        }
+    }
+}
+
+impl Default for AnalyzerColors {
+    fn default() -> Self {
+        Self::from_theme(&Theme::default())
+    }
+}
+
+const LABEL_SIZE: f32 = 11.0;
+const GRID_FREQS: [f32; 10] = [20.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0, 10000.0, 20000.0];
+
+/// A spectrum analyzer drawing a smoothed log frequency curve of a [`SampleSource`].
+pub struct UIAnalyzer {
+    /// `[x, y, width, height]` in window pixels.
+    pub rect: [f32; 4],
+    pub sample_rate: f32,
+    pub freq_range: (f32, f32),
+    /// Displayed level range in dB, bottom to top.
+    pub db_range: (f32, f32),
+    /// Tilt added to the curve in dB per octave around 1 kHz, e.g. 4.5 to flatten pink noise.
+    pub slope: f32,
+    /// Width of the smoothing in octaves, zero to disable.
+    pub smoothing: f32,
+    pub peak_hold: bool,
+    /// How fast the peak trace falls, in dB per second.
+    pub peak_decay: f32,
+    /// dB spacing of the horizontal grid lines.
+    pub db_step: f32,
+    pub line_width: Themed<f32>,
+    pub colors: Themed<AnalyzerColors>,
+    /// Frame size, overlap and averaging live here.
+    pub analyzer: SpectrumAnalyzer,
+    source: SampleSource<f32>,
+    pending: Vec<f32>,
+    curve: Vec<f32>,
+    peaks: Vec<f32>,
+    /// Bumped whenever the curve or the peak trace changes.
+    revision: u64,
+    shapes: GlShapePipe,
+    batch: ShapeBatch,
+    labels: UIText,
+    dirty: DirtyCheck<([f32; 4], u64)>,
+}
+
+impl UIAnalyzer {
+    pub fn new(rect: [f32; 4], source: SampleSource<f32>, fft_size: usize) -> Result<Self, Box<dyn std::error::Error>> {
+        let font = FontRef::try_from_slice(include_bytes!("../text/fonts/source-code-pro.regular.ttf"))?;
+        let mut labels = UIText::new("", LABEL_SIZE, [1.0; 4], [0.0, 0.0], font)?;
+        labels.set_transform(TMatrix::default());
+        Ok(Self {
+            rect,
+            sample_rate: 44100.0,
+            freq_range: (20.0, 20000.0),
+            db_range: (-90.0, 6.0),
+            slope: 0.0,
+            smoothing: 1.0 / 6.0,
+            peak_hold: false,
+            peak_decay: 12.0,
+            db_step: 12.0,
+            line_width: Themed::new(1.5),
+            colors: Themed::new(AnalyzerColors::default()),
+            analyzer: SpectrumAnalyzer::new(fft_size, 0.5),
+            source,
+            pending: Vec::new(),
+            curve: Vec::new(),
+            peaks: Vec::new(),
+            revision: 0,
+            shapes: GlShapePipe::new((1, 1), &TMatrix::default())?,
+            batch: ShapeBatch::new(),
+            labels,
+            dirty: DirtyCheck::new(),
+        })
+    }
+
+    fn db_to_y(&self, db: f32) -> f32 {
+        let [_, y, _, h] = self.rect;
+        let (lo, hi) = self.db_range;
+        y + h * (1.0 - ((db - lo) / (hi - lo)).clamp(0.0, 1.0))
+    }
+
+    fn freq_to_x(&self, freq: f32) -> f32 {
+        self.rect[0] + self.rect[2] * freq_to_norm(freq, self.freq_range)
+    }
+
+    /// The current curve in dB, one value per pixel column, with slope and smoothing applied.
+    fn compute_curve(&self) -> Vec<f32> {
+        let columns = self.rect[2].max(1.0) as usize;
+        let bin_hz = self.sample_rate / self.analyzer.size() as f32;
+        let mut values = spectrum_to_columns(self.analyzer.power(), bin_hz, columns, self.freq_range);
+        for (c, v) in values.iter_mut().enumerate() {
+            let freq = norm_to_freq((c as f32 + 0.5) / columns as f32, self.freq_range);
+            *v += slope_db(freq, self.slope);
+        }
+        let octaves = (self.freq_range.1 / self.freq_range.0).log2();
+        let radius = (self.smoothing * columns as f32 / octaves / 2.0) as usize;
+        smooth(&values, radius)
+    }
+
+    fn build(&mut self) {
+        let [x, y, w, h] = self.rect;
+        let colors = self.colors;
+        let mut batch = std::mem::take(&mut self.batch);
+        batch.clear();
+        batch.rect(self.rect, colors.background);
+
+        for f in GRID_FREQS.iter().filter(|f| **f > self.freq_range.0 && **f < self.freq_range.1) {
+            batch.rect([self.freq_to_x(*f).round(), y, 1.0, h], colors.grid);
+        }
+        let mut db = (self.db_range.1 / self.db_step).floor() * self.db_step;
+        while db > self.db_range.0 {
+            batch.rect([x, self.db_to_y(db).round(), w, 1.0], colors.grid);
+            db -= self.db_step;
+        }
+
+        let step = w / self.curve.len().max(1) as f32;
+        let points: Vec<[f32; 2]> = self.curve
+            .iter()
+            .enumerate()
+            .map(|(i, v)| [x + (i as f32 + 0.5) * step, self.db_to_y(*v)])
+            .collect();
+        let floor: Vec<[f32; 2]> = points.iter().map(|p| [p[0], y + h]).collect();
+        batch.fill_between_gradient(&points, &floor, colors.fill_top, colors.fill_bottom);
+        batch.polyline(&points, *self.line_width, colors.line);
+
+        if self.peak_hold {
+            let peaks: Vec<[f32; 2]> = self.peaks
+                .iter()
+                .enumerate()
+                .map(|(i, v)| [x + (i as f32 + 0.5) * step, self.db_to_y(*v)])
+                .collect();
+            batch.polyline(&peaks, 1.0, colors.peak);
+        }
+        self.batch = batch;
+    }
+
+    fn queue_labels(&mut self) {
+        let [x, y, _, h] = self.rect;
+        let color = self.colors.label;
+        let bounds = (self.labels.font_size * 4.0, self.labels.font_size * 2.0);
+        for f in GRID_FREQS.iter().filter(|f| **f > self.freq_range.0 && **f < self.freq_range.1) {
+            let text = if *f >= 1000.0 { format!("{}k", f / 1000.0) } else { format!("{}", f) };
+            let pos = [self.freq_to_x(*f) + 3.0, y + h - self.labels.font_size - 3.0];
+            self.labels.queue_spans(&[(&text, color)], pos, bounds);
+        }
+        let mut db = (self.db_range.1 / self.db_step).floor() * self.db_step;
+        while db > self.db_range.0 {
+            let pos = [x + 3.0, self.db_to_y(db) + 2.0];
+            self.labels.queue_spans(&[(&format!("{}", db), color)], pos, bounds);
+            db -= self.db_step;
+        }
+    }
+}
+
+impl UIElement for UIAnalyzer {
+    fn render(&mut self, window_size: (i32, i32)) {
+        self.build();
+        self.shapes.update_geometry(window_size);
+        self.shapes.upload(&self.batch);
+        self.shapes.draw();
+
+        self.labels.resize(window_size);
+        self.queue_labels();
+        self.labels.draw_queued();
+        self.dirty.drawn((self.rect, self.revision));
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty.is_dirty(&(self.rect, self.revision))
+    }
+
+    fn get_window_transform() -> TMatrix {
+        TMatrix::default()
+    }
+
+    fn set_rect(&mut self, rect: [f32; 4]) {
+        self.rect = rect;
+    }
+
+    fn animate(&mut self, dt: f32) -> bool {
+        self.pending.clear();
+        self.source.drain_into(&mut self.pending);
+        self.analyzer.push(&self.pending);
+
+        let curve = self.compute_curve();
+        if curve != self.curve {
+            self.curve = curve;
+            self.revision += 1;
+        }
+        if self.peaks.len() != self.curve.len() {
+            self.peaks = self.curve.clone();
+        }
+        let mut moved = false;
+        for (p, v) in self.peaks.iter_mut().zip(self.curve.iter()) {
+            let next = (*p - self.peak_decay * dt).max(*v);
+            moved |= next != *p;
+            *p = next;
+        }
+        if moved && self.peak_hold {
+            self.revision += 1;
+        }
+        self.peak_hold && self.peaks.iter().zip(self.curve.iter()).any(|(p, v)| p > v)
+    }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        self.colors.set_theme(AnalyzerColors::from_theme(theme));
+        self.line_width.set_theme(theme.line_width);
+        theme.apply_to_labels(&mut self.labels);
+        self.dirty.invalidate();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_freq_norm_round_trip() {
+        let range = (20.0, 20000.0);
+        assert_eq!(0.0, freq_to_norm(20.0, range));
+        assert!((freq_to_norm(20000.0, range) - 1.0).abs() < 1e-6);
+        assert!((norm_to_freq(freq_to_norm(440.0, range), range) - 440.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_slope_pivots_at_1k() {
+        assert_eq!(0.0, slope_db(1000.0, 4.5));
+        assert!((slope_db(2000.0, 4.5) - 4.5).abs() < 1e-5);
+        assert!((slope_db(250.0, 4.5) + 9.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_spectrum_to_columns_takes_loudest_bin() {
+        // bins are 10 Hz apart, the two columns cover 10..100 Hz and 100..1000 Hz
+        let mut power = vec![0.0; 101];
+        power[50] = 1.0;
+        power[60] = 0.5;
+        let columns = spectrum_to_columns(&power, 10.0, 2, (10.0, 1000.0));
+        assert!(columns[0] < -100.0);
+        assert_eq!(0.0, columns[1]);
+    }
+
+    #[test]
+    fn test_smooth() {
+        assert_eq!(vec![1.0, 2.0, 3.0], smooth(&[1.0, 2.0, 3.0], 0));
+        assert_eq!(vec![1.5, 2.0, 2.5], smooth(&[1.0, 2.0, 3.0], 1));
+    }
+}
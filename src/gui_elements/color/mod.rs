@@ -0,0 +1,403 @@
+//! Colors and gradients. Widgets keep taking `[f32; 4]` arrays, which is what a [`Color`]
+//! converts into, so both can be mixed freely.
+
+use std::fmt;
+use std::str::FromStr;
+use serde::Deserialize;
+use crate::gui_elements::text::text_utils::Res;
+
+/// An sRGB color with straight (not premultiplied) alpha, all channels from 0 to 1.
+///
+/// Widget colors are sRGB values that go to GL unchanged. `FRAMEBUFFER_SRGB` stays disabled,
+/// so shader output is taken as sRGB and written as is, on screen and in offscreen targets
+/// alike. Blending happens on the sRGB values; gradients look more even when mixed in linear
+/// light, see [`Color::lerp_linear`].
+///
+/// UI and theme files write colors as hex strings (see [`Color::from_hex`]) or `(r, g, b)` /
+/// `(r, g, b, a)` tuples.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(try_from = "ColorRepr")]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub const TRANSPARENT: Self = Self::rgba(0.0, 0.0, 0.0, 0.0);
+    pub const BLACK: Self = Self::rgb(0.0, 0.0, 0.0);
+    pub const WHITE: Self = Self::rgb(1.0, 1.0, 1.0);
+
+    pub const fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Self { r, g, b, a: 1.0 }
+    }
+
+    pub const fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// From 8 bit channels.
+    pub fn rgba8(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self::rgba(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, a as f32 / 255.0)
+    }
+
+    /// Parses `#rgb`, `#rgba`, `#rrggbb` or `#rrggbbaa`. The `#` is optional.
+    pub fn from_hex(hex: &str) -> Res<Self> {
+        let digits = hex.strip_prefix('#').unwrap_or(hex);
+        let invalid = || format!("invalid hex color {:?}", hex);
+        if !digits.is_ascii() {
+            return Err(invalid().into());
+        }
+        let channel = |i: usize, len: usize| {
+            let v = u8::from_str_radix(&digits[i * len..(i + 1) * len], 16).map_err(|_| invalid())?;
+            // a single digit stands for the digit repeated, f -> ff
+            Ok::<_, String>(if len == 1 { v * 17 } else { v })
+        };
+        let (count, len) = match digits.len() {
+            3 => (3, 1),
+            4 => (4, 1),
+            6 => (3, 2),
+            8 => (4, 2),
+            _ => return Err(invalid().into()),
+        };
+        let a = if count == 4 { channel(3, len)? } else { 255 };
+        Ok(Self::rgba8(channel(0, len)?, channel(1, len)?, channel(2, len)?, a))
+    }
+
+    /// `#rrggbb`, or `#rrggbbaa` when not opaque.
+    pub fn to_hex(&self) -> String {
+        let [r, g, b, a] = self.to_rgba8();
+        if a == 255 {
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+        }
+    }
+
+    pub fn to_rgba8(&self) -> [u8; 4] {
+        let c = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        [c(self.r), c(self.g), c(self.b), c(self.a)]
+    }
+
+    /// From hue in degrees, saturation and value from 0 to 1.
+    pub fn from_hsv(h: f32, s: f32, v: f32, a: f32) -> Self {
+        let c = v * s;
+        let [r, g, b] = hue_to_rgb(h, c);
+        let m = v - c;
+        Self::rgba(r + m, g + m, b + m, a)
+    }
+
+    /// `[hue in degrees, saturation, value]`. Grays have hue 0.
+    pub fn to_hsv(&self) -> [f32; 3] {
+        let (max, min) = (self.r.max(self.g).max(self.b), self.r.min(self.g).min(self.b));
+        let s = if max > 0.0 { (max - min) / max } else { 0.0 };
+        [self.hue(max, min), s, max]
+    }
+
+    /// From hue in degrees, saturation and lightness from 0 to 1.
+    pub fn from_hsl(h: f32, s: f32, l: f32, a: f32) -> Self {
+        let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        let [r, g, b] = hue_to_rgb(h, c);
+        let m = l - c / 2.0;
+        Self::rgba(r + m, g + m, b + m, a)
+    }
+
+    /// `[hue in degrees, saturation, lightness]`. Grays have hue 0.
+    pub fn to_hsl(&self) -> [f32; 3] {
+        let (max, min) = (self.r.max(self.g).max(self.b), self.r.min(self.g).min(self.b));
+        let l = (max + min) / 2.0;
+        let d = max - min;
+        let s = if d > 0.0 { d / (1.0 - (2.0 * l - 1.0).abs()) } else { 0.0 };
+        [self.hue(max, min), s, l]
+    }
+
+    fn hue(&self, max: f32, min: f32) -> f32 {
+        let d = max - min;
+        if d <= 0.0 {
+            return 0.0;
+        }
+        let h = if max == self.r {
+            (self.g - self.b) / d
+        } else if max == self.g {
+            (self.b - self.r) / d + 2.0
+        } else {
+            (self.r - self.g) / d + 4.0
+        };
+        (h * 60.0).rem_euclid(360.0)
+    }
+
+    pub fn with_alpha(self, a: f32) -> Self {
+        Self { a, ..self }
+    }
+
+    /// Moves the HSL lightness by `amount`, negative to darken.
+    pub fn lighten(self, amount: f32) -> Self {
+        let [h, s, l] = self.to_hsl();
+        Self::from_hsl(h, s, (l + amount).clamp(0.0, 1.0), self.a)
+    }
+
+    /// The channels converted to linear light, alpha unchanged.
+    pub fn to_linear(&self) -> [f32; 4] {
+        [srgb_to_linear(self.r), srgb_to_linear(self.g), srgb_to_linear(self.b), self.a]
+    }
+
+    /// From linear light channels.
+    pub fn from_linear(c: [f32; 4]) -> Self {
+        Self::rgba(linear_to_srgb(c[0]), linear_to_srgb(c[1]), linear_to_srgb(c[2]), c[3])
+    }
+
+    /// Mixes the sRGB values, `t` 0 gives `self` and 1 gives `other`.
+    pub fn lerp(&self, other: Color, t: f32) -> Self {
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        Self::rgba(mix(self.r, other.r), mix(self.g, other.g), mix(self.b, other.b), mix(self.a, other.a))
+    }
+
+    /// Mixes in linear light, which avoids the dark band sRGB mixing gives between
+    /// saturated colors.
+    pub fn lerp_linear(&self, other: Color, t: f32) -> Self {
+        let (a, b) = (self.to_linear(), other.to_linear());
+        let mix = |i: usize| a[i] + (b[i] - a[i]) * t;
+        Self::from_linear([mix(0), mix(1), mix(2), mix(3)])
+    }
+
+    pub fn to_array(&self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
+
+/// Red, green and blue of a hue with chroma `c` before the lightness offset is added.
+fn hue_to_rgb(h: f32, c: f32) -> [f32; 3] {
+    let h = h.rem_euclid(360.0) / 60.0;
+    let x = c * (1.0 - (h % 2.0 - 1.0).abs());
+    match h as u32 {
+        0 => [c, x, 0.0],
+        1 => [x, c, 0.0],
+        2 => [0.0, c, x],
+        3 => [0.0, x, c],
+        4 => [x, 0.0, c],
+        _ => [c, 0.0, x],
+    }
+}
+
+/// Decodes one sRGB channel.
+pub fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Encodes one linear channel as sRGB.
+pub fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+impl From<[f32; 4]> for Color {
+    fn from(c: [f32; 4]) -> Self {
+        Self::rgba(c[0], c[1], c[2], c[3])
+    }
+}
+
+impl From<[f32; 3]> for Color {
+    fn from(c: [f32; 3]) -> Self {
+        Self::rgb(c[0], c[1], c[2])
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(c: Color) -> Self {
+        c.to_array()
+    }
+}
+
+/// The ways a [`Color`] can be written in a file.
+#[derive(Deserialize)]
+#[serde(untagged, expecting = "expected (r, g, b), (r, g, b, a) or \"#rrggbb\"")]
+enum ColorRepr {
+    Hex(String),
+    Rgb(f32, f32, f32),
+    Rgba(f32, f32, f32, f32),
+}
+
+impl TryFrom<ColorRepr> for Color {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(repr: ColorRepr) -> Res<Self> {
+        match repr {
+            ColorRepr::Hex(hex) => Self::from_hex(&hex),
+            ColorRepr::Rgb(r, g, b) => Ok(Self::rgb(r, g, b)),
+            ColorRepr::Rgba(r, g, b, a) => Ok(Self::rgba(r, g, b, a)),
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = Box<dyn std::error::Error>;
+
+    fn from_str(s: &str) -> Res<Self> {
+        Self::from_hex(s)
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_hex())
+    }
+}
+
+/// Where the colors of a [`Gradient`] change.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GradientShape {
+    /// Along the line from `start` to `end`, constant across it.
+    Linear { start: [f32; 2], end: [f32; 2] },
+    /// Outwards from `center`, reaching the last stop at `radius`.
+    Radial { center: [f32; 2], radius: f32 },
+}
+
+/// Colors at positions (`stops`, 0 to 1, in increasing order) over a [`GradientShape`]. Points
+/// in window pixels, like everything given to a
+/// [`ShapeBatch`](crate::gui_elements::shapes::ShapeBatch), which can fill rects with it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gradient {
+    pub shape: GradientShape,
+    pub stops: Vec<(f32, Color)>,
+    /// Mix neighbouring stops in linear light instead of sRGB.
+    pub linear_light: bool,
+}
+
+impl Gradient {
+    pub fn linear(start: [f32; 2], end: [f32; 2], stops: Vec<(f32, Color)>) -> Self {
+        Self { shape: GradientShape::Linear { start, end }, stops, linear_light: false }
+    }
+
+    pub fn radial(center: [f32; 2], radius: f32, stops: Vec<(f32, Color)>) -> Self {
+        Self { shape: GradientShape::Radial { center, radius }, stops, linear_light: false }
+    }
+
+    /// `from` at 0 to `to` at 1.
+    pub fn two(shape: GradientShape, from: Color, to: Color) -> Self {
+        Self { shape, stops: vec![(0.0, from), (1.0, to)], linear_light: false }
+    }
+
+    /// Color at position `t`. Positions before the first or after the last stop take that
+    /// stop's color.
+    pub fn sample(&self, t: f32) -> Color {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Color::TRANSPARENT,
+        };
+        if t <= first.0 {
+            return first.1;
+        }
+        for pair in self.stops.windows(2) {
+            let ((t0, c0), (t1, c1)) = (pair[0], pair[1]);
+            if t <= t1 {
+                let f = if t1 > t0 { (t - t0) / (t1 - t0) } else { 1.0 };
+                return if self.linear_light { c0.lerp_linear(c1, f) } else { c0.lerp(c1, f) };
+            }
+        }
+        last.1
+    }
+
+    /// Position of `point` along the gradient, not clamped.
+    pub fn position(&self, point: [f32; 2]) -> f32 {
+        match self.shape {
+            GradientShape::Linear { start, end } => {
+                let d = [end[0] - start[0], end[1] - start[1]];
+                let len2 = d[0] * d[0] + d[1] * d[1];
+                if len2 == 0.0 {
+                    return 0.0;
+                }
+                ((point[0] - start[0]) * d[0] + (point[1] - start[1]) * d[1]) / len2
+            }
+            GradientShape::Radial { center, radius } => {
+                let (dx, dy) = (point[0] - center[0], point[1] - center[1]);
+                if radius <= 0.0 {
+                    return 1.0;
+                }
+                (dx * dx + dy * dy).sqrt() / radius
+            }
+        }
+    }
+
+    pub fn color_at(&self, point: [f32; 2]) -> Color {
+        self.sample(self.position(point))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: [f32; 4], b: [f32; 4]) -> bool {
+        a.iter().zip(b.iter()).all(|(a, b)| (a - b).abs() < 1e-3)
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(Color::rgba8(0xff, 0x80, 0x00, 0xff), Color::from_hex("#ff8000").unwrap());
+        assert_eq!(Color::rgba8(0xff, 0x88, 0x00, 0x44), "f804".parse().unwrap());
+        assert_eq!("#ff8000", Color::from_hex("#FF8000").unwrap().to_hex());
+        assert_eq!("#11223344", Color::from_hex("#11223344").unwrap().to_hex());
+        assert!(Color::from_hex("#12345").is_err());
+        assert!(Color::from_hex("#gg0000").is_err());
+        assert!(Color::from_hex("#ü00").is_err());
+    }
+
+    #[test]
+    fn test_hsv_hsl_round_trip() {
+        let c = Color::rgb(0.2, 0.6, 0.9);
+        let [h, s, v] = c.to_hsv();
+        assert!(close(c.to_array(), Color::from_hsv(h, s, v, 1.0).to_array()));
+        let [h, s, l] = c.to_hsl();
+        assert!(close(c.to_array(), Color::from_hsl(h, s, l, 1.0).to_array()));
+
+        assert!(close([1.0, 0.0, 0.0, 1.0], Color::from_hsv(360.0, 1.0, 1.0, 1.0).to_array()));
+        assert!(close([0.0, 0.5, 0.0, 1.0], Color::from_hsl(120.0, 1.0, 0.25, 1.0).to_array()));
+        assert_eq!([0.0, 0.0, 0.5], Color::rgb(0.5, 0.5, 0.5).to_hsl());
+    }
+
+    #[test]
+    fn test_linear_conversion() {
+        assert!((srgb_to_linear(0.5) - 0.214).abs() < 1e-3);
+        for v in [0.0, 0.01, 0.5, 1.0] {
+            assert!((linear_to_srgb(srgb_to_linear(v)) - v).abs() < 1e-5);
+        }
+        // halfway between red and green is brighter when mixed in linear light
+        let (red, green) = (Color::rgb(1.0, 0.0, 0.0), Color::rgb(0.0, 1.0, 0.0));
+        assert!(close([0.5, 0.5, 0.0, 1.0], red.lerp(green, 0.5).to_array()));
+        assert!(red.lerp_linear(green, 0.5).r > 0.7);
+    }
+
+    #[test]
+    fn test_colors_reach_gl_as_srgb() {
+        // no linear conversion on the way to a shader, the framebuffer stores what was written
+        let c = Color::from_hex("#804020").unwrap();
+        let gl: [f32; 4] = c.into();
+        assert_eq!([128.0 / 255.0, 64.0 / 255.0, 32.0 / 255.0, 1.0], gl);
+        assert_eq!(c, Color::from(gl));
+    }
+
+    #[test]
+    fn test_gradient_sample() {
+        let g = Gradient::linear(
+            [0.0, 0.0],
+            [100.0, 0.0],
+            vec![(0.0, Color::BLACK), (0.5, Color::WHITE), (1.0, Color::rgb(1.0, 0.0, 0.0))],
+        );
+        assert_eq!(Color::BLACK, g.color_at([-10.0, 0.0]));
+        assert!(close([0.5, 0.5, 0.5, 1.0], g.color_at([25.0, 40.0]).to_array()));
+        assert!(close([1.0, 0.5, 0.5, 1.0], g.color_at([75.0, 0.0]).to_array()));
+        assert_eq!(Color::rgb(1.0, 0.0, 0.0), g.color_at([200.0, 0.0]));
+
+        let r = Gradient::two(GradientShape::Radial { center: [10.0, 10.0], radius: 10.0 }, Color::WHITE, Color::BLACK);
+        assert_eq!(0.5, r.position([10.0, 15.0]));
+        assert_eq!(Color::TRANSPARENT, Gradient::linear([0.0; 2], [1.0, 0.0], Vec::new()).sample(0.5));
+    }
+}
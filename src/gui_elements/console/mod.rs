@@ -0,0 +1,429 @@
+use std::collections::VecDeque;
+use glfw::{Action, Key, WindowEvent};
+use glyph_brush::ab_glyph::FontRef;
+use vst_log::{Entry, Level};
+use crate::animation::{Easing, Transition};
+use crate::gui_elements::text::UIText;
+use crate::gui_elements::theme::{with_alpha, Theme, Themed};
+
+const FONT_SIZE: f32 = 14.0;
+const LINE_HEIGHT: f32 = 17.0;
+const PADDING: f32 = 6.0;
+/// Seconds the overlay takes to slide in or out.
+const SLIDE_TIME: f32 = 0.12;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConsoleColors {
+    /// Solid fill behind the text.
+    pub background: [f32; 4],
+    pub error: [f32; 4],
+    pub warn: [f32; 4],
+    pub info: [f32; 4],
+    /// Debug and trace entries.
+    pub debug: [f32; 4],
+    pub prompt: [f32; 4],
+}
+
+impl ConsoleColors {
+    pub fn from_theme(theme: &Theme) -> Self {
+        Self {
+            background: with_alpha(theme.background, 1.0),
+            error: theme.danger,
+            warn: theme.warning,
+            info: theme.text,
+            debug: theme.text_dim,
+            prompt: theme.secondary.normal,
+        }
+    }
+
+    fn level(&self, level: Level) -> [f32; 4] {
+        match level {
+            Level::Error => self.error,
+            Level::Warn => self.warn,
+            Level::Info => self.info,
+            Level::Debug | Level::Trace => self.debug,
+        }
+    }
+}
+
+impl Default for ConsoleColors {
+    fn default() -> Self {
+        Self::from_theme(&Theme::default())
+    }
+}
+
+/// An overlay that shows the most recent `vst_log` entries inside the plugin window.
+///
+/// Toggled with `toggle_key`. While open, typing edits the filter, backspace deletes from it,
+/// escape clears it and the scroll wheel or page up / page down scroll through the history.
+/// Scrolling and clicks over the overlay do not reach the widgets underneath.
+pub struct UILogConsole {
+    pub toggle_key: Key,
+    /// Fraction of the window height the overlay covers, from the top.
+    pub height_fraction: f32,
+    pub colors: Themed<ConsoleColors>,
+    visible: bool,
+    /// How far the overlay has slid down, from 0 (hidden) to 1.
+    slide: Transition<f32>,
+    /// Where the overlay was last drawn, none while it is hidden.
+    area: Option<[f32; 4]>,
+    cursor: (f64, f64),
+    log: LogView,
+    text: UIText,
+}
+
+impl UILogConsole {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        let font = FontRef::try_from_slice(include_bytes!("../text/fonts/source-code-pro.regular.ttf"))?;
+        let mut text = UIText::new("", FONT_SIZE, [1.0; 4], [0.0, 0.0], font)?;
+        text.set_transform(Default::default());
+        Ok(Self {
+            toggle_key: Key::F12,
+            height_fraction: 0.5,
+            colors: Themed::new(ConsoleColors::default()),
+            visible: false,
+            slide: Transition::new(0.0, SLIDE_TIME, Easing::OutCubic),
+            area: None,
+            cursor: (0.0, 0.0),
+            log: LogView::new(vst_log::HISTORY_LEN),
+            text,
+        })
+    }
+
+    /// Takes the colors and font of `theme`. The console keeps its own font size so the log
+    /// stays readable with small label sizes.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.colors.set_theme(ConsoleColors::from_theme(theme));
+        self.text.set_font(theme.font_ref());
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+        self.slide.set(if visible { 1.0 } else { 0.0 });
+        self.log.scroll = 0;
+    }
+
+    /// Pulls new log entries. True if the overlay is showing and any of them pass the filter,
+    /// so it has to be drawn again.
+    pub fn is_dirty(&mut self) -> bool {
+        self.log.pull() && self.slide.value() > 0.0
+    }
+
+    /// Advances the slide in or out by `dt` seconds. Returns true while it is moving.
+    pub fn animate(&mut self, dt: f32) -> bool {
+        self.slide.update(dt)
+    }
+
+    pub fn filter(&self) -> &str {
+        &self.log.filter
+    }
+
+    pub fn set_filter(&mut self, filter: &str) {
+        self.log.set_filter(filter.to_string());
+    }
+
+    fn over_area(&self) -> bool {
+        let (x, y) = (self.cursor.0 as f32, self.cursor.1 as f32);
+        self.area.is_some_and(|[ax, ay, w, h]| x >= ax && x < ax + w && y >= ay && y < ay + h)
+    }
+
+    /// Handles a window event. Returns true if the console consumed it, which it does for
+    /// every key press while visible and for scrolling and clicks over the overlay.
+    pub fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorPos(x, y) => {
+                self.cursor = (*x, *y);
+                false
+            }
+            WindowEvent::Key(key, _, Action::Press, _) if *key == self.toggle_key => {
+                self.set_visible(!self.visible);
+                true
+            }
+            _ if !self.visible => false,
+            WindowEvent::Char(c) if !c.is_control() => {
+                let mut filter = self.log.filter.clone();
+                filter.push(*c);
+                self.log.set_filter(filter);
+                true
+            }
+            WindowEvent::Key(key, _, Action::Press | Action::Repeat, _) => {
+                match key {
+                    Key::Backspace => {
+                        let mut filter = self.log.filter.clone();
+                        filter.pop();
+                        self.log.set_filter(filter);
+                    }
+                    Key::Escape => self.set_filter(""),
+                    Key::PageUp => self.log.scroll_by(10),
+                    Key::PageDown => self.log.scroll_by(-10),
+                    Key::End => self.log.scroll = 0,
+                    // typing goes to the filter, not to the widgets underneath. Releases pass
+                    // so keys held since before the console opened are let go of.
+                    _ => {}
+                }
+                true
+            }
+            WindowEvent::Scroll(_, y) if self.over_area() => {
+                self.log.scroll_by(3 * y.signum() as isize);
+                true
+            }
+            // like key releases, button releases pass so drags begun underneath can end
+            WindowEvent::MouseButton(_, Action::Press, _) => self.over_area(),
+            _ => false,
+        }
+    }
+
+    /// Draws the overlay on top of whatever has already been rendered.
+    pub fn render(&mut self, window_size: (i32, i32)) {
+        self.log.pull();
+        let slide = self.slide.value();
+        if slide <= 0.0 {
+            self.area = None;
+            return;
+        }
+
+        let (w, h) = (window_size.0 as f32, window_size.1 as f32);
+        let height = (h * self.height_fraction).max(LINE_HEIGHT * 2.0 + PADDING * 2.0);
+        let top = -height * (1.0 - slide);
+        self.area = Some([0.0, top, w, height]);
+        fill_rect([0.0, top, w, height], self.colors.background, window_size);
+
+        // one row is left for the prompt
+        let rows = ((height - PADDING * 2.0) / LINE_HEIGHT) as usize;
+        let rows = rows.saturating_sub(1);
+        self.log.clamp_scroll(rows);
+        let prompt = self.log.prompt();
+        let colors = *self.colors;
+        let mut spans: Vec<(&str, [f32; 4])> = self.log
+            .visible(rows)
+            .map(|line| (line.display.as_str(), colors.level(line.level)))
+            .collect();
+        spans.push((&prompt, colors.prompt));
+
+        self.text.resize(window_size);
+        self.text.render_spans(&spans, [PADDING, top + PADDING], (w - PADDING * 2.0, height - PADDING));
+    }
+
+}
+
+/// A pulled log entry, formatted once for display.
+struct Line {
+    level: Level,
+    text: String,
+    /// `[LEVEL] text` and a newline, as drawn.
+    display: String,
+}
+
+impl Line {
+    fn new(entry: Entry) -> Self {
+        let display = format!("[{}] {}\n", entry.level.as_str(), entry.text);
+        Self { level: entry.level, text: entry.text, display }
+    }
+
+    /// Case-insensitive substring match over the text, or the level name. `filter` must be
+    /// lowercase already.
+    fn matches(&self, filter: &str) -> bool {
+        filter.is_empty() || self.text.to_lowercase().contains(filter) || self.level.as_str().to_lowercase() == filter
+    }
+}
+
+/// The entries the console has pulled from the log and which of them it shows. Lines are
+/// formatted and matched against the filter once, when they arrive or the filter changes.
+struct LogView {
+    capacity: usize,
+    lines: VecDeque<Line>,
+    /// Lines evicted so far, which makes `lines[i]` line number `evicted + i`.
+    evicted: usize,
+    /// Numbers of the lines that pass the filter, oldest first.
+    matching: VecDeque<usize>,
+    next_id: u64,
+    filter: String,
+    /// Lines scrolled up from the newest matching entry.
+    scroll: usize,
+}
+
+impl LogView {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            lines: VecDeque::with_capacity(capacity),
+            evicted: 0,
+            matching: VecDeque::new(),
+            next_id: 0,
+            filter: String::new(),
+            scroll: 0,
+        }
+    }
+
+    /// Pulls the entries logged since the last call. True if any of them pass the filter.
+    fn pull(&mut self) -> bool {
+        self.push(vst_log::history_since(self.next_id))
+    }
+
+    /// Appends entries newer than the ones kept, dropping the oldest beyond the capacity.
+    /// True if any of them pass the filter.
+    fn push(&mut self, entries: Vec<Entry>) -> bool {
+        let filter = self.filter.to_lowercase();
+        let mut shown = false;
+        for e in entries {
+            if e.id < self.next_id {
+                continue;
+            }
+            self.next_id = e.id + 1;
+            if self.lines.len() == self.capacity {
+                self.lines.pop_front();
+                if self.matching.front() == Some(&self.evicted) {
+                    self.matching.pop_front();
+                }
+                self.evicted += 1;
+            }
+            let line = Line::new(e);
+            if line.matches(&filter) {
+                self.matching.push_back(self.evicted + self.lines.len());
+                shown = true;
+            }
+            self.lines.push_back(line);
+        }
+        shown
+    }
+
+    /// Replaces the filter and scrolls back to the newest line.
+    fn set_filter(&mut self, filter: String) {
+        let lower = filter.to_lowercase();
+        self.matching = self.lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.matches(&lower))
+            .map(|(i, _)| self.evicted + i)
+            .collect();
+        self.filter = filter;
+        self.scroll = 0;
+    }
+
+    /// Scrolls up by `lines`, or down for negative ones. Clamped to the lines there are
+    /// when the view is next shown.
+    fn scroll_by(&mut self, lines: isize) {
+        self.scroll = self.scroll.saturating_add_signed(lines);
+    }
+
+    /// Limits the scroll so the oldest matching entry stops at the top of `rows`.
+    fn clamp_scroll(&mut self, rows: usize) {
+        self.scroll = self.scroll.min(self.matching.len().saturating_sub(rows));
+    }
+
+    /// Up to `rows` matching lines ending `scroll` lines above the newest.
+    fn visible(&self, rows: usize) -> impl Iterator<Item = &Line> {
+        let end = self.matching.len().saturating_sub(self.scroll);
+        self.matching
+            .range(end.saturating_sub(rows)..end)
+            .map(|n| &self.lines[n - self.evicted])
+    }
+
+    fn prompt(&self) -> String {
+        if self.scroll > 0 {
+            format!("filter: {}_  (+{} newer)", self.filter, self.scroll)
+        } else {
+            format!("filter: {}_", self.filter)
+        }
+    }
+}
+
+/// Fills a rect given in window pixels (origin top left) by clearing inside a scissor box.
+/// The clear color is restored afterwards. Blending does not apply.
+fn fill_rect(rect: [f32; 4], color: [f32; 4], window_size: (i32, i32)) {
+    unsafe {
+        let mut prev = [0.0_f32; 4];
+        gl::GetFloatv(gl::COLOR_CLEAR_VALUE, prev.as_mut_ptr());
+        gl::Enable(gl::SCISSOR_TEST);
+        gl::Scissor(
+            rect[0] as _,
+            (window_size.1 as f32 - rect[1] - rect[3]) as _,
+            rect[2] as _,
+            rect[3] as _,
+        );
+        gl::ClearColor(color[0], color[1], color[2], color[3]);
+        gl::Clear(gl::COLOR_BUFFER_BIT);
+        gl::Disable(gl::SCISSOR_TEST);
+        gl::ClearColor(prev[0], prev[1], prev[2], prev[3]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: u64, level: Level, text: &str) -> Entry {
+        Entry { id, level, text: text.to_string() }
+    }
+
+    impl LogView {
+        /// What `render` does to pick the lines, as their text.
+        fn show(&mut self, rows: usize) -> Vec<String> {
+            self.clamp_scroll(rows);
+            self.visible(rows).map(|line| line.text.clone()).collect()
+        }
+    }
+
+    #[test]
+    fn test_filter_matches_text_or_level() {
+        let mut view = LogView::new(8);
+        view.push(vec![entry(0, Level::Info, "Opened editor"), entry(1, Level::Error, "no GL"), entry(2, Level::Debug, "error count 0")]);
+        view.set_filter("ERROR".to_string());
+        assert_eq!(vec!["no GL", "error count 0"], view.show(10));
+        view.set_filter("editor".to_string());
+        assert_eq!(vec!["Opened editor"], view.show(10));
+    }
+
+    #[test]
+    fn test_scroll_is_clamped() {
+        let mut view = LogView::new(16);
+        view.push((0..10).map(|i| entry(i, Level::Info, &i.to_string())).collect());
+        assert_eq!(vec!["7", "8", "9"], view.show(3));
+
+        view.scroll_by(2);
+        assert_eq!(vec!["5", "6", "7"], view.show(3));
+        assert_eq!("filter: _  (+2 newer)", view.prompt());
+        // scrolling past the oldest entry stops with it at the top
+        view.scroll_by(100);
+        assert_eq!(vec!["0", "1", "2"], view.show(3));
+        assert_eq!(7, view.scroll);
+        view.scroll_by(-100);
+        assert_eq!(vec!["7", "8", "9"], view.show(3));
+        // fewer lines than rows
+        view.set_filter("3".to_string());
+        view.scroll_by(5);
+        assert_eq!(vec!["3"], view.show(3));
+        assert_eq!(0, view.scroll);
+    }
+
+    #[test]
+    fn test_push_pages_and_evicts() {
+        let mut view = LogView::new(3);
+        assert!(view.push(vec![entry(0, Level::Info, "a"), entry(1, Level::Info, "b")]));
+        assert!(!view.push(Vec::new()));
+        // entries already pulled are skipped, ids evicted from the log's history are not
+        // waited for
+        assert!(view.push(vec![entry(1, Level::Info, "b"), entry(5, Level::Info, "f"), entry(6, Level::Info, "g")]));
+        assert_eq!(7, view.next_id);
+        assert_eq!(vec!["b", "f", "g"], view.show(10));
+    }
+
+    #[test]
+    fn test_filter_follows_new_and_evicted_lines() {
+        let mut view = LogView::new(3);
+        view.set_filter("gl".to_string());
+        assert!(view.push(vec![entry(0, Level::Info, "GL 3.3"), entry(1, Level::Info, "opened")]));
+        // lines the filter hides need no redraw
+        assert!(!view.push(vec![entry(2, Level::Info, "resized")]));
+        assert_eq!(vec!["GL 3.3"], view.show(10));
+        // the evicted match goes with its line
+        assert!(view.push(vec![entry(3, Level::Error, "no gl context")]));
+        assert_eq!(vec!["no gl context"], view.show(10));
+        view.set_filter(String::new());
+        assert_eq!(vec!["opened", "resized", "no gl context"], view.show(10));
+    }
+}
@@ -0,0 +1,336 @@
+use std::sync::Arc;
+use glfw::{Action, Key, MouseButton, WindowEvent};
+use parking_lot::Mutex;
+use crate::curve::Curve;
+use crate::gui_elements::shapes::{GlShapePipe, ShapeBatch};
+use crate::gui_elements::utils::click::ClickTracker;
+use crate::gui_elements::utils::dirty::DirtyCheck;
+use crate::gui_elements::utils::t_matrix::TMatrix;
+use crate::gui_elements::UIElement;
+use crate::gui_elements::theme::{with_alpha, Theme, Themed};
+
+/// Rounds `value` (0 to 1) to the nearest of `divisions` steps.
+pub fn snap(value: f32, divisions: usize) -> f32 {
+    if divisions == 0 {
+        value
+    } else {
+        (value * divisions as f32).round() / divisions as f32
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CurveColors {
+    pub background: [f32; 4],
+    pub grid: [f32; 4],
+    pub line: [f32; 4],
+    pub fill: [f32; 4],
+    pub point: [f32; 4],
+    pub handle: [f32; 4],
+    pub highlight: [f32; 4],
+}
+
+impl CurveColors {
+    pub fn from_theme(theme: &Theme) -> Self {
+        Self {
+            background: theme.panel,
+            grid: theme.grid,
+            line: theme.accent.normal,
+            fill: with_alpha(theme.accent.normal, 0.12),
+            point: theme.control.normal,
+            handle: theme.accent.normal,
+            highlight: theme.control.hover,
+        }
+    }
+}
+
+impl Default for CurveColors {
+    fn default() -> Self {
+        Self::from_theme(&Theme::default())
+    }
+}
+
+/// See [`UICurveEditor::on_change`].
+pub type ChangeHandler = Box<dyn FnMut(&Curve)>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Target {
+    Point(usize),
+    /// The tension handle of the segment starting at this point.
+    Tension(usize),
+}
+
+/// Edits a [`Curve`] shared with the plugin. Drag points to move them and the handle in the
+/// middle of a segment to bend it. Double-click adds a point (or straightens a segment when
+/// done on its handle), right-click deletes one. Points snap to the grid unless Shift is held.
+pub struct UICurveEditor {
+    /// `[x, y, width, height]` in window pixels.
+    pub rect: [f32; 4],
+    /// Grid divisions along x and y, also used for snapping. Zero turns an axis off.
+    pub grid: (usize, usize),
+    pub snap: bool,
+    pub point_radius: Themed<f32>,
+    pub line_width: Themed<f32>,
+    pub colors: Themed<CurveColors>,
+    curve: Arc<Mutex<Curve>>,
+    on_change: Option<ChangeHandler>,
+    cursor: (f64, f64),
+    shift: bool,
+    clicks: ClickTracker,
+    drag: Option<(Target, f32, f32)>,
+    /// Rect, curve and highlighted target last drawn.
+    dirty: DirtyCheck<([f32; 4], Curve, Option<Target>)>,
+    shapes: GlShapePipe,
+    batch: ShapeBatch,
+}
+
+impl UICurveEditor {
+    pub fn new(rect: [f32; 4], curve: Arc<Mutex<Curve>>) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            rect,
+            grid: (8, 8),
+            snap: true,
+            point_radius: Themed::new(5.0),
+            line_width: Themed::new(2.0),
+            colors: Themed::new(CurveColors::default()),
+            curve,
+            on_change: None,
+            cursor: (0.0, 0.0),
+            shift: false,
+            clicks: ClickTracker::new(),
+            drag: None,
+            dirty: DirtyCheck::new(),
+            shapes: GlShapePipe::new((1, 1), &TMatrix::default())?,
+            batch: ShapeBatch::new(),
+        })
+    }
+
+    /// Called after every edit, e.g. to rebuild a lookup table for the audio thread.
+    pub fn on_change(&mut self, f: impl FnMut(&Curve) + 'static) {
+        self.on_change = Some(Box::new(f));
+    }
+
+    fn to_screen(&self, p: [f32; 2]) -> [f32; 2] {
+        let [x, y, w, h] = self.rect;
+        [x + p[0] * w, y + (1.0 - p[1]) * h]
+    }
+
+    fn cursor_value(&self) -> [f32; 2] {
+        let [x, y, w, h] = self.rect;
+        let v = [
+            ((self.cursor.0 as f32 - x) / w).clamp(0.0, 1.0),
+            (1.0 - (self.cursor.1 as f32 - y) / h).clamp(0.0, 1.0),
+        ];
+        if self.snap && !self.shift {
+            [snap(v[0], self.grid.0), snap(v[1], self.grid.1)]
+        } else {
+            v
+        }
+    }
+
+    fn target_position(&self, curve: &Curve, target: Target) -> [f32; 2] {
+        match target {
+            Target::Point(i) => {
+                let p = curve.points()[i];
+                self.to_screen([p.x, p.y])
+            }
+            Target::Tension(i) => self.to_screen(curve.segment_point(i, 0.5)),
+        }
+    }
+
+    /// Points win over handles.
+    fn target_at_cursor(&self, curve: &Curve) -> Option<Target> {
+        let (cx, cy) = (self.cursor.0 as f32, self.cursor.1 as f32);
+        let reach = *self.point_radius + 3.0;
+        let n = curve.points().len();
+        (0..n)
+            .rev()
+            .map(Target::Point)
+            .chain((0..n - 1).rev().map(Target::Tension))
+            .find(|t| {
+                let [x, y] = self.target_position(curve, *t);
+                (x - cx).powi(2) + (y - cy).powi(2) <= reach * reach
+            })
+    }
+
+    fn changed(&mut self) {
+        if let Some(f) = self.on_change.as_mut() {
+            f(&self.curve.lock());
+        }
+    }
+
+    fn press(&mut self) -> bool {
+        let inside = {
+            let [x, y, w, h] = self.rect;
+            let (cx, cy) = (self.cursor.0 as f32, self.cursor.1 as f32);
+            cx >= x && cx <= x + w && cy >= y && cy <= y + h
+        };
+        let double = self.clicks.press(self.cursor);
+        let target = self.target_at_cursor(&self.curve.lock());
+        match (target, double) {
+            (Some(Target::Tension(i)), true) => {
+                self.curve.lock().set_tension(i, 0.0);
+                self.changed();
+            }
+            (Some(target), _) => {
+                let tension = match target {
+                    Target::Tension(i) => self.curve.lock().points()[i].tension,
+                    Target::Point(_) => 0.0,
+                };
+                self.drag = Some((target, self.cursor.1 as f32, tension));
+            }
+            (None, true) if inside => {
+                let [x, y] = self.cursor_value();
+                let i = self.curve.lock().insert(x, y);
+                self.drag = Some((Target::Point(i), self.cursor.1 as f32, 0.0));
+                self.changed();
+            }
+            (None, _) => return inside,
+        }
+        true
+    }
+
+    fn drag_to_cursor(&mut self, target: Target, start_y: f32, start_tension: f32) {
+        let mut curve = self.curve.lock();
+        match target {
+            Target::Point(i) => {
+                let [x, y] = self.cursor_value();
+                curve.move_point(i, x, y);
+            }
+            Target::Tension(i) => {
+                // the handle follows the mouse, which needs the opposite tension on falling segments
+                let (a, b) = (curve.points()[i], curve.points()[i + 1]);
+                let direction = if b.y >= a.y { 1.0 } else { -1.0 };
+                let delta = (start_y - self.cursor.1 as f32) / (self.rect[3] / 2.0);
+                curve.set_tension(i, start_tension + direction * delta);
+            }
+        }
+        drop(curve);
+        self.changed();
+    }
+
+    /// The dragged target, or else the one under the cursor.
+    fn active_target(&self, curve: &Curve) -> Option<Target> {
+        self.drag.map(|d| d.0).or_else(|| self.target_at_cursor(curve))
+    }
+
+    fn build(&mut self) {
+        let [x, y, w, h] = self.rect;
+        let colors = self.colors;
+        let curve = self.curve.lock().clone();
+        let mut batch = std::mem::take(&mut self.batch);
+        batch.clear();
+        batch.rect(self.rect, colors.background);
+        for i in 1..self.grid.0 {
+            batch.rect([(x + w * i as f32 / self.grid.0 as f32).round(), y, 1.0, h], colors.grid);
+        }
+        for i in 1..self.grid.1 {
+            batch.rect([x, (y + h * i as f32 / self.grid.1 as f32).round(), w, 1.0], colors.grid);
+        }
+
+        let columns = w.max(1.0) as usize;
+        let points: Vec<[f32; 2]> = (0..=columns)
+            .map(|c| {
+                let v = c as f32 / columns as f32;
+                self.to_screen([v, curve.value(v)])
+            })
+            .collect();
+        let floor: Vec<[f32; 2]> = points.iter().map(|p| [p[0], y + h]).collect();
+        batch.fill_between(&points, &floor, colors.fill);
+        batch.polyline(&points, *self.line_width, colors.line);
+
+        let active = self.active_target(&curve);
+        for i in 0..curve.points().len() - 1 {
+            let p = self.target_position(&curve, Target::Tension(i));
+            if active == Some(Target::Tension(i)) {
+                batch.circle(p, *self.point_radius, colors.highlight);
+            }
+            batch.circle(p, *self.point_radius - 1.5, colors.handle);
+            batch.circle(p, *self.point_radius - 3.0, colors.background);
+        }
+        for i in 0..curve.points().len() {
+            let p = self.target_position(&curve, Target::Point(i));
+            if active == Some(Target::Point(i)) {
+                batch.circle(p, *self.point_radius + 2.0, colors.highlight);
+            }
+            batch.circle(p, *self.point_radius, colors.point);
+        }
+        self.batch = batch;
+        self.dirty.drawn((self.rect, curve, active));
+    }
+}
+
+impl UIElement for UICurveEditor {
+    fn render(&mut self, window_size: (i32, i32)) {
+        self.build();
+        self.shapes.update_geometry(window_size);
+        self.shapes.upload(&self.batch);
+        self.shapes.draw();
+    }
+
+    fn get_window_transform() -> TMatrix {
+        TMatrix::default()
+    }
+
+    fn set_rect(&mut self, rect: [f32; 4]) {
+        self.rect = rect;
+    }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        self.colors.set_theme(CurveColors::from_theme(theme));
+        self.point_radius.set_theme(theme.point_radius);
+        self.line_width.set_theme(theme.line_width);
+        self.dirty.invalidate();
+    }
+
+    fn is_dirty(&self) -> bool {
+        let curve = self.curve.lock();
+        let active = self.active_target(&curve);
+        self.dirty.is_dirty(&(self.rect, curve.clone(), active))
+    }
+
+    fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorPos(x, y) => {
+                self.cursor = (*x, *y);
+                match self.drag {
+                    Some((target, start_y, start_tension)) => {
+                        self.drag_to_cursor(target, start_y, start_tension);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            WindowEvent::Key(Key::LeftShift | Key::RightShift, _, action, _) => {
+                self.shift = *action != Action::Release;
+                false
+            }
+            WindowEvent::MouseButton(MouseButton::Button1, Action::Press, _) => self.press(),
+            WindowEvent::MouseButton(MouseButton::Button1, Action::Release, _) => self.drag.take().is_some(),
+            WindowEvent::MouseButton(MouseButton::Button2, Action::Press, _) => {
+                let target = self.target_at_cursor(&self.curve.lock());
+                match target {
+                    Some(Target::Point(i)) => {
+                        if self.curve.lock().remove(i) {
+                            self.changed();
+                        }
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snap() {
+        assert_eq!(0.25, snap(0.27, 4));
+        assert_eq!(1.0, snap(0.9, 4));
+        assert_eq!(0.27, snap(0.27, 0));
+    }
+}
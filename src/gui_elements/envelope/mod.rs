@@ -0,0 +1,584 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use glfw::{Action, MouseButton, WindowEvent};
+use glyph_brush::ab_glyph::FontRef;
+use crate::gui_elements::shapes::{GlShapePipe, ShapeBatch};
+use crate::gui_elements::text::UIText;
+use crate::gui_elements::utils::dirty::DirtyCheck;
+use crate::gui_elements::utils::t_matrix::TMatrix;
+use crate::gui_elements::UIElement;
+use crate::params::{Binding, Gesture, ParamHost, Taper};
+use crate::gui_elements::theme::{with_alpha, Theme, Themed};
+
+/// Bends `t` (0 to 1) into a curve. Zero is linear, positive tension starts slow and ends
+/// fast, negative the other way round. Tension is clamped to -1..1.
+pub fn shape(t: f32, tension: f32) -> f32 {
+    let k = tension.clamp(-1.0, 1.0) * 8.0;
+    if k.abs() < 1e-3 {
+        t
+    } else {
+        ((k * t).exp() - 1.0) / (k.exp() - 1.0)
+    }
+}
+
+/// Where a stage ends, either fixed or a parameter from 0 to 1.
+#[derive(Debug, Clone, Copy)]
+pub enum StageLevel {
+    Fixed(f32),
+    Param(Binding),
+}
+
+impl StageLevel {
+    fn get(&self, params: &dyn ParamHost) -> f32 {
+        match self {
+            StageLevel::Fixed(level) => *level,
+            StageLevel::Param(binding) => binding.get(params),
+        }
+    }
+}
+
+/// One segment of an envelope, moving from the previous stage's level to `level` in `time`.
+#[derive(Debug, Clone, Copy)]
+pub struct EnvelopeStage {
+    /// Duration in seconds.
+    pub time: Binding,
+    pub level: StageLevel,
+    /// Curve tension from -1 to 1, see [`shape`]. `None` draws the stage linear.
+    pub tension: Option<Binding>,
+}
+
+impl EnvelopeStage {
+    pub fn new(time: Binding, level: StageLevel, tension: Option<Binding>) -> Self {
+        Self { time, level, tension }
+    }
+}
+
+/// Stages of an envelope starting at level 0. The envelope holds after `sustain_after` until
+/// the note is released and the remaining stages run.
+#[derive(Debug, Clone)]
+pub struct Envelope {
+    pub stages: Vec<EnvelopeStage>,
+    pub sustain_after: Option<usize>,
+}
+
+impl Envelope {
+    /// The classic four stage layout. Attack rises to 1 and release falls to 0, tensions can
+    /// be set on the stages afterwards.
+    pub fn adsr(attack: Binding, decay: Binding, sustain: Binding, release: Binding) -> Self {
+        Self {
+            stages: vec![
+                EnvelopeStage::new(attack, StageLevel::Fixed(1.0), None),
+                EnvelopeStage::new(decay, StageLevel::Param(sustain), None),
+                EnvelopeStage::new(release, StageLevel::Fixed(0.0), None),
+            ],
+            sustain_after: Some(1),
+        }
+    }
+}
+
+/// Where the voice is in its envelope.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EnvelopePosition {
+    /// Stage index and seconds since the stage started.
+    Stage(usize, f32),
+    Sustain,
+}
+
+const IDLE: u32 = u32::MAX;
+const SUSTAIN: u32 = u32::MAX - 1;
+
+/// Lets the audio thread publish the envelope position of the voice to follow. Setting and
+/// reading are single atomic operations, so it can be shared freely by cloning.
+#[derive(Debug, Clone)]
+pub struct EnvelopePlayhead {
+    state: Arc<AtomicU64>,
+}
+
+impl Default for EnvelopePlayhead {
+    fn default() -> Self {
+        Self { state: Arc::new(AtomicU64::new((IDLE as u64) << 32)) }
+    }
+}
+
+impl EnvelopePlayhead {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, position: EnvelopePosition) {
+        let (stage, time) = match position {
+            EnvelopePosition::Stage(stage, time) => (stage.min(SUSTAIN as usize - 1) as u32, time),
+            EnvelopePosition::Sustain => (SUSTAIN, 0.0),
+        };
+        self.state.store(((stage as u64) << 32) | time.to_bits() as u64, Ordering::Relaxed);
+    }
+
+    /// Hides the playhead, e.g. when no voice is playing.
+    pub fn clear(&self) {
+        self.state.store((IDLE as u64) << 32, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> Option<EnvelopePosition> {
+        let state = self.state.load(Ordering::Relaxed);
+        match (state >> 32) as u32 {
+            IDLE => None,
+            SUSTAIN => Some(EnvelopePosition::Sustain),
+            stage => Some(EnvelopePosition::Stage(stage as usize, f32::from_bits(state as u32))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct EnvelopeColors {
+    pub background: [f32; 4],
+    pub grid: [f32; 4],
+    pub label: [f32; 4],
+    pub line: [f32; 4],
+    pub fill: [f32; 4],
+    pub point: [f32; 4],
+    pub handle: [f32; 4],
+    pub highlight: [f32; 4],
+    pub playhead: [f32; 4],
+}
+
+impl EnvelopeColors {
+    pub fn from_theme(theme: &Theme) -> Self {
+        Self {
+            background: theme.panel,
+            grid: theme.grid,
+            label: theme.text_dim,
+            line: theme.accent.normal,
+            fill: with_alpha(theme.accent.normal, 0.15),
+            point: theme.control.normal,
+            handle: theme.accent.normal,
+            highlight: theme.control.hover,
+            playhead: theme.secondary.normal,
+        }
+    }
+}
+
+impl Default for EnvelopeColors {
+    fn default() -> Self {
+        Self::from_theme(&Theme::default())
+    }
+}
+
+/// The drawn extent of one stage in window pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Segment {
+    x0: f32,
+    x1: f32,
+    level0: f32,
+    level1: f32,
+    tension: f32,
+}
+
+impl Segment {
+    fn level_at(&self, t: f32) -> f32 {
+        self.level0 + (self.level1 - self.level0) * shape(t, self.tension)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Target {
+    Point(usize),
+    Tension(usize),
+}
+
+/// Rect, stages, playhead and highlighted target, everything the picture depends on besides
+/// style.
+type EnvelopeView = ([f32; 4], Vec<Segment>, Option<EnvelopePosition>, Option<Target>);
+
+const LABEL_SIZE: f32 = 11.0;
+const CURVE_STEPS: usize = 32;
+
+/// An envelope editor. Breakpoints set a stage's time (horizontal) and level (vertical, if the
+/// level is a parameter), the handle in the middle of a stage sets its tension. Every drag is
+/// one edit gesture for the host.
+pub struct UIEnvelopeEditor {
+    /// `[x, y, width, height]` in window pixels.
+    pub rect: [f32; 4],
+    /// Seconds covered by the width of the widget, not counting the sustain hold.
+    pub visible_time: f32,
+    /// Width of the flat sustain part, in pixels.
+    pub sustain_width: f32,
+    /// Seconds between vertical grid lines.
+    pub time_step: f32,
+    pub point_radius: Themed<f32>,
+    pub line_width: Themed<f32>,
+    pub colors: Themed<EnvelopeColors>,
+    envelope: Envelope,
+    params: Arc<dyn ParamHost>,
+    playhead: Option<EnvelopePlayhead>,
+    cursor: (f64, f64),
+    drag: Option<(Target, f32, f32)>,
+    gesture: Gesture,
+    dirty: DirtyCheck<EnvelopeView>,
+    shapes: GlShapePipe,
+    batch: ShapeBatch,
+    labels: UIText,
+}
+
+impl UIEnvelopeEditor {
+    pub fn new(rect: [f32; 4], envelope: Envelope, params: Arc<dyn ParamHost>) -> Result<Self, Box<dyn std::error::Error>> {
+        let font = FontRef::try_from_slice(include_bytes!("../text/fonts/source-code-pro.regular.ttf"))?;
+        let mut labels = UIText::new("", LABEL_SIZE, [1.0; 4], [0.0, 0.0], font)?;
+        labels.set_transform(TMatrix::default());
+        Ok(Self {
+            rect,
+            visible_time: 4.0,
+            sustain_width: 40.0,
+            time_step: 0.5,
+            point_radius: Themed::new(5.0),
+            line_width: Themed::new(2.0),
+            colors: Themed::new(EnvelopeColors::default()),
+            envelope,
+            params,
+            playhead: None,
+            cursor: (0.0, 0.0),
+            drag: None,
+            gesture: Gesture::default(),
+            dirty: DirtyCheck::new(),
+            shapes: GlShapePipe::new((1, 1), &TMatrix::default())?,
+            batch: ShapeBatch::new(),
+            labels,
+        })
+    }
+
+    /// Follows `playhead` with a dot on the curve.
+    pub fn set_playhead(&mut self, playhead: Option<EnvelopePlayhead>) {
+        self.playhead = playhead;
+    }
+
+    pub fn envelope(&self) -> &Envelope {
+        &self.envelope
+    }
+
+    fn pixels_per_second(&self) -> f32 {
+        (self.rect[2] - self.sustain_width).max(1.0) / self.visible_time
+    }
+
+    fn level_to_y(&self, level: f32) -> f32 {
+        self.rect[1] + self.rect[3] * (1.0 - level.clamp(0.0, 1.0))
+    }
+
+    fn segments(&self) -> Vec<Segment> {
+        let params = &*self.params;
+        let pps = self.pixels_per_second();
+        let mut x = self.rect[0];
+        let mut level = 0.0;
+        let mut segments = Vec::with_capacity(self.envelope.stages.len());
+        for (i, stage) in self.envelope.stages.iter().enumerate() {
+            let x1 = x + stage.time.get(params).max(0.0) * pps;
+            let level1 = stage.level.get(params);
+            let tension = stage.tension.map(|t| t.get(params)).unwrap_or(0.0);
+            segments.push(Segment { x0: x, x1, level0: level, level1, tension });
+            x = x1;
+            level = level1;
+            if self.envelope.sustain_after == Some(i) {
+                x += self.sustain_width;
+            }
+        }
+        segments
+    }
+
+    fn target_position(&self, target: Target, segments: &[Segment]) -> [f32; 2] {
+        match target {
+            Target::Point(i) => [segments[i].x1, self.level_to_y(segments[i].level1)],
+            Target::Tension(i) => {
+                let s = &segments[i];
+                [(s.x0 + s.x1) / 2.0, self.level_to_y(s.level_at(0.5))]
+            }
+        }
+    }
+
+    /// Breakpoints win over tension handles, later stages over earlier ones.
+    fn target_at_cursor(&self, segments: &[Segment]) -> Option<Target> {
+        let (cx, cy) = (self.cursor.0 as f32, self.cursor.1 as f32);
+        let reach = *self.point_radius + 3.0;
+        let hit = |target| {
+            let [x, y] = self.target_position(target, segments);
+            (x - cx).powi(2) + (y - cy).powi(2) <= reach * reach
+        };
+        let stages = &self.envelope.stages;
+        (0..stages.len())
+            .rev()
+            .map(Target::Point)
+            .chain((0..stages.len()).rev().filter(|i| stages[*i].tension.is_some()).map(Target::Tension))
+            .find(|t| hit(*t))
+    }
+
+    fn bindings(&self, target: Target) -> Vec<Binding> {
+        let stage = &self.envelope.stages[target_index(target)];
+        match (target, stage.level) {
+            (Target::Point(_), StageLevel::Param(level)) => vec![stage.time, level],
+            (Target::Point(_), StageLevel::Fixed(_)) => vec![stage.time],
+            (Target::Tension(_), _) => stage.tension.into_iter().collect(),
+        }
+    }
+
+    fn begin_drag(&mut self, target: Target) {
+        let indices: Vec<i32> = self.bindings(target).iter().map(|b| b.index).collect();
+        if !self.gesture.begin(&*self.params, &indices) {
+            return;
+        }
+        let start_tension = match target {
+            Target::Tension(i) => self.envelope.stages[i].tension.map(|t| t.get(&*self.params)).unwrap_or(0.0),
+            Target::Point(_) => 0.0,
+        };
+        self.drag = Some((target, self.cursor.1 as f32, start_tension));
+    }
+
+    fn end_drag(&mut self) {
+        self.drag = None;
+        self.gesture.end(&*self.params);
+    }
+
+    fn drag_to_cursor(&self, target: Target, start_y: f32, start_tension: f32) {
+        let params = &*self.params;
+        let segments = self.segments();
+        let (cx, cy) = (self.cursor.0 as f32, self.cursor.1 as f32);
+        match target {
+            Target::Point(i) => {
+                let stage = &self.envelope.stages[i];
+                stage.time.set(params, ((cx - segments[i].x0) / self.pixels_per_second()).max(0.0));
+                if let StageLevel::Param(level) = stage.level {
+                    level.set(params, 1.0 - (cy - self.rect[1]) / self.rect[3]);
+                }
+            }
+            Target::Tension(i) => {
+                // the handle follows the mouse: moving it up bends a rising stage upwards,
+                // which needs less tension
+                let s = &segments[i];
+                let direction = if s.level1 >= s.level0 { -1.0 } else { 1.0 };
+                let tension = start_tension + direction * (start_y - cy) / (self.rect[3] / 2.0);
+                if let Some(t) = self.envelope.stages[i].tension {
+                    t.set(params, tension.clamp(-1.0, 1.0));
+                }
+            }
+        }
+    }
+
+    fn playhead_position(&self, segments: &[Segment]) -> Option<[f32; 2]> {
+        let pps = self.pixels_per_second();
+        match self.playhead.as_ref()?.get()? {
+            EnvelopePosition::Sustain => {
+                let s = &segments[self.envelope.sustain_after?];
+                Some([s.x1 + self.sustain_width / 2.0, self.level_to_y(s.level1)])
+            }
+            EnvelopePosition::Stage(i, time) => {
+                let s = segments.get(i)?;
+                let width = s.x1 - s.x0;
+                let t = if width > 0.0 { (time * pps / width).clamp(0.0, 1.0) } else { 1.0 };
+                Some([s.x0 + width * t, self.level_to_y(s.level_at(t))])
+            }
+        }
+    }
+
+    /// The dragged target, or else the one under the cursor.
+    fn active_target(&self, segments: &[Segment]) -> Option<Target> {
+        self.drag.map(|d| d.0).or_else(|| self.target_at_cursor(segments))
+    }
+
+    fn build(&mut self, segments: &[Segment]) {
+        let [x, y, w, h] = self.rect;
+        let colors = self.colors;
+        let mut batch = std::mem::take(&mut self.batch);
+        batch.clear();
+        batch.rect(self.rect, colors.background);
+
+        let pps = self.pixels_per_second();
+        let mut t = self.time_step;
+        while t < self.visible_time {
+            batch.rect([(x + t * pps).round(), y, 1.0, h], colors.grid);
+            t += self.time_step;
+        }
+        for level in [0.25, 0.5, 0.75] {
+            batch.rect([x, self.level_to_y(level).round(), w, 1.0], colors.grid);
+        }
+
+        let mut points = vec![[x, self.level_to_y(0.0)]];
+        for (i, s) in segments.iter().enumerate() {
+            points.extend((1..=CURVE_STEPS).map(|i| {
+                let t = i as f32 / CURVE_STEPS as f32;
+                [s.x0 + (s.x1 - s.x0) * t, self.level_to_y(s.level_at(t))]
+            }));
+            if self.envelope.sustain_after == Some(i) {
+                points.push([s.x1 + self.sustain_width, self.level_to_y(s.level1)]);
+            }
+        }
+        let floor: Vec<[f32; 2]> = points.iter().map(|p| [p[0], y + h]).collect();
+        batch.fill_between(&points, &floor, colors.fill);
+        batch.polyline(&points, *self.line_width, colors.line);
+
+        let active = self.active_target(segments);
+        for (i, stage) in self.envelope.stages.iter().enumerate() {
+            if stage.tension.is_some() {
+                let p = self.target_position(Target::Tension(i), segments);
+                if active == Some(Target::Tension(i)) {
+                    batch.circle(p, *self.point_radius, colors.highlight);
+                }
+                batch.circle(p, *self.point_radius - 1.5, colors.handle);
+                batch.circle(p, *self.point_radius - 3.0, colors.background);
+            }
+            let p = self.target_position(Target::Point(i), segments);
+            if active == Some(Target::Point(i)) {
+                batch.circle(p, *self.point_radius + 2.0, colors.highlight);
+            }
+            batch.circle(p, *self.point_radius, colors.point);
+        }
+
+        if let Some(p) = self.playhead_position(segments) {
+            batch.circle(p, *self.point_radius - 1.0, colors.playhead);
+        }
+        self.batch = batch;
+    }
+
+    fn queue_labels(&mut self, segments: &[Segment]) {
+        let target = match self.drag.map(|d| d.0).or_else(|| self.target_at_cursor(segments)) {
+            Some(t) => t,
+            None => return,
+        };
+        let params = &*self.params;
+        let stage = &self.envelope.stages[target_index(target)];
+        let text = match target {
+            Target::Point(_) => {
+                let mut text = format_time(stage.time.get(params));
+                if let StageLevel::Param(level) = stage.level {
+                    text += &format!(" {:.2}", level.get(params));
+                }
+                text
+            }
+            Target::Tension(_) => format!("{:+.2}", stage.tension.map(|t| t.get(params)).unwrap_or(0.0)),
+        };
+        let [px, py] = self.target_position(target, segments);
+        let pos = [
+            (px + *self.point_radius + 4.0).min(self.rect[0] + self.rect[2] - self.labels.font_size * 8.0),
+            (py - self.labels.font_size - *self.point_radius).max(self.rect[1]),
+        ];
+        self.labels.queue_spans(&[(&text, self.colors.label)], pos, (self.labels.font_size * 12.0, self.labels.font_size * 2.0));
+    }
+}
+
+fn target_index(target: Target) -> usize {
+    match target {
+        Target::Point(i) | Target::Tension(i) => i,
+    }
+}
+
+fn format_time(seconds: f32) -> String {
+    if seconds < 1.0 {
+        format!("{:.0} ms", seconds * 1000.0)
+    } else {
+        format!("{:.2} s", seconds)
+    }
+}
+
+/// A binding for tension parameters stored as 0..1 with 0.5 meaning linear.
+pub fn tension_binding(index: i32) -> Binding {
+    Binding::new(index, Taper::Linear { min: -1.0, max: 1.0 })
+}
+
+impl UIElement for UIEnvelopeEditor {
+    fn render(&mut self, window_size: (i32, i32)) {
+        let segments = self.segments();
+        self.build(&segments);
+        self.shapes.update_geometry(window_size);
+        self.shapes.upload(&self.batch);
+        self.shapes.draw();
+
+        self.labels.resize(window_size);
+        self.queue_labels(&segments);
+        self.labels.draw_queued();
+
+        let (active, playhead) = (self.active_target(&segments), self.playhead.as_ref().and_then(|p| p.get()));
+        self.dirty.drawn((self.rect, segments, playhead, active));
+    }
+
+    fn get_window_transform() -> TMatrix {
+        TMatrix::default()
+    }
+
+    fn set_rect(&mut self, rect: [f32; 4]) {
+        self.rect = rect;
+    }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        self.colors.set_theme(EnvelopeColors::from_theme(theme));
+        self.point_radius.set_theme(theme.point_radius);
+        self.line_width.set_theme(theme.line_width);
+        theme.apply_to_labels(&mut self.labels);
+        self.dirty.invalidate();
+    }
+
+    fn is_dirty(&self) -> bool {
+        let segments = self.segments();
+        let (active, playhead) = (self.active_target(&segments), self.playhead.as_ref().and_then(|p| p.get()));
+        self.dirty.is_dirty(&(self.rect, segments, playhead, active))
+    }
+
+    fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorPos(x, y) => {
+                self.cursor = (*x, *y);
+                match self.drag {
+                    Some((target, start_y, start_tension)) => {
+                        self.drag_to_cursor(target, start_y, start_tension);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            // a second press during a drag must not begin another gesture
+            WindowEvent::MouseButton(MouseButton::Button1, Action::Press, _) if self.drag.is_none() => {
+                match self.target_at_cursor(&self.segments()) {
+                    Some(target) => {
+                        self.begin_drag(target);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            WindowEvent::MouseButton(MouseButton::Button1, Action::Release, _) => {
+                let dragging = self.drag.is_some();
+                self.end_drag();
+                dragging
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Drop for UIEnvelopeEditor {
+    fn drop(&mut self) {
+        self.end_drag();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shape() {
+        assert_eq!(0.5, shape(0.5, 0.0));
+        for tension in [-1.0, -0.3, 0.4, 1.0] {
+            assert!(shape(0.0, tension).abs() < 1e-6);
+            assert!((shape(1.0, tension) - 1.0).abs() < 1e-6);
+        }
+        assert!(shape(0.5, 0.5) < 0.5);
+        assert!(shape(0.5, -0.5) > 0.5);
+    }
+
+    #[test]
+    fn test_playhead_round_trip() {
+        let playhead = EnvelopePlayhead::new();
+        assert_eq!(None, playhead.get());
+        playhead.clone().set(EnvelopePosition::Stage(2, 0.125));
+        assert_eq!(Some(EnvelopePosition::Stage(2, 0.125)), playhead.get());
+        playhead.set(EnvelopePosition::Sustain);
+        assert_eq!(Some(EnvelopePosition::Sustain), playhead.get());
+        playhead.clear();
+        assert_eq!(None, playhead.get());
+    }
+}
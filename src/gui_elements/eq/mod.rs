@@ -0,0 +1,440 @@
+use std::sync::Arc;
+use crate::animation::{Easing, Transition};
+use glfw::{Action, MouseButton, WindowEvent};
+use glyph_brush::ab_glyph::FontRef;
+use crate::dsp::biquad::{Biquad, FilterType};
+use crate::gui_elements::analyzer::{freq_to_norm, norm_to_freq};
+use crate::gui_elements::shapes::{GlShapePipe, ShapeBatch};
+use crate::gui_elements::text::UIText;
+use crate::gui_elements::utils::dirty::DirtyCheck;
+use crate::gui_elements::utils::t_matrix::TMatrix;
+use crate::gui_elements::UIElement;
+use crate::params::{Binding, Gesture, ParamHost};
+use crate::gui_elements::theme::{with_alpha, Theme, Themed};
+
+/// One EQ band whose frequency, gain and Q are plugin parameters.
+#[derive(Debug, Clone, Copy)]
+pub struct EqBand {
+    pub kind: FilterType,
+    /// Frequency in Hz, usually with a log taper.
+    pub freq: Binding,
+    /// Gain in dB. Ignored by types without gain.
+    pub gain: Binding,
+    pub q: Binding,
+    /// Used for the node and the band's own curve.
+    pub color: [f32; 4],
+}
+
+impl EqBand {
+    pub fn new(kind: FilterType, freq: Binding, gain: Binding, q: Binding, color: [f32; 4]) -> Self {
+        Self { kind, freq, gain, q, color }
+    }
+
+    /// Coefficients for the current parameter values.
+    pub fn biquad(&self, params: &dyn ParamHost, sample_rate: f32) -> Biquad {
+        let gain = if self.kind.has_gain() { self.gain.get(params) } else { 0.0 };
+        Biquad::new(self.kind, sample_rate, self.freq.get(params), self.q.get(params), gain)
+    }
+}
+
+/// Response of filters in series at `freq`, in dB.
+pub fn response_db(filters: &[Biquad], freq: f32, sample_rate: f32) -> f32 {
+    filters.iter().map(|f| f.magnitude_db(freq, sample_rate)).sum()
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct EqColors {
+    pub background: [f32; 4],
+    pub grid: [f32; 4],
+    pub zero_line: [f32; 4],
+    pub label: [f32; 4],
+    pub curve: [f32; 4],
+    pub fill: [f32; 4],
+    /// Ring drawn around the node under the cursor.
+    pub highlight: [f32; 4],
+}
+
+impl EqColors {
+    pub fn from_theme(theme: &Theme) -> Self {
+        Self {
+            background: theme.panel,
+            grid: theme.grid,
+            zero_line: theme.grid_strong,
+            label: theme.text_dim,
+            curve: theme.text,
+            fill: with_alpha(theme.text, 0.12),
+            highlight: theme.control.hover,
+        }
+    }
+}
+
+impl Default for EqColors {
+    fn default() -> Self {
+        Self::from_theme(&Theme::default())
+    }
+}
+
+/// Rect, band parameter values and the active node, everything the picture depends on
+/// besides style.
+type EqView = ([f32; 4], Vec<[f32; 3]>, Option<usize>);
+
+const LABEL_SIZE: f32 = 11.0;
+/// Seconds the highlight ring takes to fade in or out.
+const HIGHLIGHT_FADE: f32 = 0.15;
+const GRID_FREQS: [f32; 10] = [20.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0, 10000.0, 20000.0];
+
+/// An EQ curve editor. Dragging a node sets the band's frequency and gain, the mouse wheel over
+/// a node changes its Q. Each drag and each wheel step is sent to the host as one edit gesture.
+///
+/// The curves are recomputed from the parameters every frame, so host automation shows up too.
+pub struct UIEqEditor {
+    /// `[x, y, width, height]` in window pixels.
+    pub rect: [f32; 4],
+    pub sample_rate: f32,
+    pub freq_range: (f32, f32),
+    /// Displayed gain range in dB, bottom to top.
+    pub db_range: (f32, f32),
+    /// dB spacing of the horizontal grid lines.
+    pub db_step: f32,
+    /// Also draw every band's own response in its color.
+    pub show_band_curves: bool,
+    /// Factor applied to Q per wheel step.
+    pub q_step: f32,
+    pub node_radius: Themed<f32>,
+    pub line_width: Themed<f32>,
+    pub colors: Themed<EqColors>,
+    bands: Vec<EqBand>,
+    params: Arc<dyn ParamHost>,
+    cursor: (f64, f64),
+    drag: Option<usize>,
+    gesture: Gesture,
+    /// Node with the highlight ring and how far the ring has faded in.
+    highlighted: Option<usize>,
+    highlight: Transition<f32>,
+    dirty: DirtyCheck<EqView>,
+    shapes: GlShapePipe,
+    batch: ShapeBatch,
+    labels: UIText,
+}
+
+impl UIEqEditor {
+    pub fn new(rect: [f32; 4], bands: Vec<EqBand>, params: Arc<dyn ParamHost>) -> Result<Self, Box<dyn std::error::Error>> {
+        let font = FontRef::try_from_slice(include_bytes!("../text/fonts/source-code-pro.regular.ttf"))?;
+        let mut labels = UIText::new("", LABEL_SIZE, [1.0; 4], [0.0, 0.0], font)?;
+        labels.set_transform(TMatrix::default());
+        Ok(Self {
+            rect,
+            sample_rate: 44100.0,
+            freq_range: (20.0, 20000.0),
+            db_range: (-24.0, 24.0),
+            db_step: 6.0,
+            show_band_curves: true,
+            q_step: 1.1,
+            node_radius: Themed::new(6.0),
+            line_width: Themed::new(2.0),
+            colors: Themed::new(EqColors::default()),
+            bands,
+            params,
+            cursor: (0.0, 0.0),
+            drag: None,
+            gesture: Gesture::default(),
+            highlighted: None,
+            highlight: Transition::new(0.0, HIGHLIGHT_FADE, Easing::OutQuad),
+            dirty: DirtyCheck::new(),
+            shapes: GlShapePipe::new((1, 1), &TMatrix::default())?,
+            batch: ShapeBatch::new(),
+            labels,
+        })
+    }
+
+    pub fn bands(&self) -> &[EqBand] {
+        &self.bands
+    }
+
+    /// Changes a band's type or color. The parameters stay bound as before.
+    pub fn band_mut(&mut self, index: usize) -> Option<&mut EqBand> {
+        self.dirty.invalidate();
+        self.bands.get_mut(index)
+    }
+
+    fn db_to_y(&self, db: f32) -> f32 {
+        let [_, y, _, h] = self.rect;
+        let (lo, hi) = self.db_range;
+        y + h * (1.0 - ((db - lo) / (hi - lo)).clamp(0.0, 1.0))
+    }
+
+    fn y_to_db(&self, y: f32) -> f32 {
+        let (lo, hi) = self.db_range;
+        lo + (hi - lo) * (1.0 - ((y - self.rect[1]) / self.rect[3]).clamp(0.0, 1.0))
+    }
+
+    fn freq_to_x(&self, freq: f32) -> f32 {
+        self.rect[0] + self.rect[2] * freq_to_norm(freq, self.freq_range)
+    }
+
+    fn x_to_freq(&self, x: f32) -> f32 {
+        norm_to_freq(((x - self.rect[0]) / self.rect[2]).clamp(0.0, 1.0), self.freq_range)
+    }
+
+    /// Bands without gain sit on the 0 dB line.
+    fn node_position(&self, band: &EqBand) -> [f32; 2] {
+        let params = &*self.params;
+        let db = if band.kind.has_gain() { band.gain.get(params) } else { 0.0 };
+        [self.freq_to_x(band.freq.get(params)), self.db_to_y(db)]
+    }
+
+    /// The band whose node is under the cursor. Later bands are drawn on top and win.
+    fn node_at_cursor(&self) -> Option<usize> {
+        let (cx, cy) = (self.cursor.0 as f32, self.cursor.1 as f32);
+        let reach = *self.node_radius + 3.0;
+        self.bands.iter().enumerate().rev().find_map(|(i, band)| {
+            let [x, y] = self.node_position(band);
+            ((x - cx).powi(2) + (y - cy).powi(2) <= reach * reach).then_some(i)
+        })
+    }
+
+    fn drag_to_cursor(&self, index: usize) {
+        let band = &self.bands[index];
+        let params = &*self.params;
+        band.freq.set(params, self.x_to_freq(self.cursor.0 as f32));
+        if band.kind.has_gain() {
+            band.gain.set(params, self.y_to_db(self.cursor.1 as f32));
+        }
+    }
+
+    fn begin_drag(&mut self, index: usize) {
+        let band = self.bands[index];
+        let indices: &[i32] = if band.kind.has_gain() { &[band.freq.index, band.gain.index] } else { &[band.freq.index] };
+        if self.gesture.begin(&*self.params, indices) {
+            self.drag = Some(index);
+        }
+    }
+
+    fn end_drag(&mut self) {
+        self.drag = None;
+        self.gesture.end(&*self.params);
+    }
+
+    fn scroll_q(&self, index: usize, steps: f32) {
+        let q = self.bands[index].q;
+        let params = &*self.params;
+        params.begin_edit(q.index);
+        q.set(params, q.get(params) * self.q_step.powf(steps));
+        params.end_edit(q.index);
+    }
+
+    fn points(&self, curve: &[f32]) -> Vec<[f32; 2]> {
+        let step = self.rect[2] / curve.len().max(1) as f32;
+        curve
+            .iter()
+            .enumerate()
+            .map(|(i, db)| [self.rect[0] + (i as f32 + 0.5) * step, self.db_to_y(*db)])
+            .collect()
+    }
+
+    fn view(&self) -> EqView {
+        let params = &*self.params;
+        let values = self.bands.iter().map(|b| [b.freq.get(params), b.gain.get(params), b.q.get(params)]).collect();
+        (self.rect, values, self.drag.or_else(|| self.node_at_cursor()))
+    }
+
+    fn build(&mut self) {
+        let [x, y, w, h] = self.rect;
+        let colors = self.colors;
+        let mut batch = std::mem::take(&mut self.batch);
+        batch.clear();
+        batch.rect(self.rect, colors.background);
+
+        for f in GRID_FREQS.iter().filter(|f| **f > self.freq_range.0 && **f < self.freq_range.1) {
+            batch.rect([self.freq_to_x(*f).round(), y, 1.0, h], colors.grid);
+        }
+        let mut db = (self.db_range.1 / self.db_step).floor() * self.db_step;
+        while db > self.db_range.0 {
+            let color = if db == 0.0 { colors.zero_line } else { colors.grid };
+            batch.rect([x, self.db_to_y(db).round(), w, 1.0], color);
+            db -= self.db_step;
+        }
+
+        let filters: Vec<Biquad> = self.bands.iter().map(|b| b.biquad(&*self.params, self.sample_rate)).collect();
+        let columns = w.max(1.0) as usize;
+        let freqs: Vec<f32> = (0..columns)
+            .map(|c| norm_to_freq((c as f32 + 0.5) / columns as f32, self.freq_range))
+            .collect();
+        let zero: Vec<[f32; 2]> = self.points(&vec![0.0; columns]);
+
+        if self.show_band_curves {
+            for (band, filter) in self.bands.iter().zip(filters.iter()) {
+                let curve: Vec<f32> = freqs.iter().map(|f| filter.magnitude_db(*f, self.sample_rate)).collect();
+                let points = self.points(&curve);
+                let fill = [band.color[0], band.color[1], band.color[2], band.color[3] * 0.15];
+                batch.fill_between(&points, &zero, fill);
+                batch.polyline(&points, 1.0, band.color);
+            }
+        }
+
+        let combined: Vec<f32> = freqs.iter().map(|f| response_db(&filters, *f, self.sample_rate)).collect();
+        let points = self.points(&combined);
+        batch.fill_between(&points, &zero, colors.fill);
+        batch.polyline(&points, *self.line_width, colors.curve);
+
+        let fade = self.highlight.value();
+        for (i, band) in self.bands.iter().enumerate() {
+            let p = self.node_position(band);
+            if self.highlighted == Some(i) && fade > 0.0 {
+                batch.circle(p, *self.node_radius + 2.0, with_alpha(colors.highlight, colors.highlight[3] * fade));
+            }
+            batch.circle(p, *self.node_radius, band.color);
+        }
+        self.batch = batch;
+    }
+
+    fn queue_labels(&mut self) {
+        let [x, y, _, h] = self.rect;
+        let color = self.colors.label;
+        let bounds = (self.labels.font_size * 4.0, self.labels.font_size * 2.0);
+        for f in GRID_FREQS.iter().filter(|f| **f > self.freq_range.0 && **f < self.freq_range.1) {
+            let pos = [self.freq_to_x(*f) + 3.0, y + h - self.labels.font_size - 3.0];
+            self.labels.queue_spans(&[(&format_freq(*f), color)], pos, bounds);
+        }
+        let mut db = (self.db_range.1 / self.db_step).floor() * self.db_step;
+        while db > self.db_range.0 {
+            let pos = [x + 3.0, self.db_to_y(db) + 2.0];
+            self.labels.queue_spans(&[(&format!("{}", db), color)], pos, bounds);
+            db -= self.db_step;
+        }
+
+        if let Some(band) = self.drag.or_else(|| self.node_at_cursor()).map(|i| self.bands[i]) {
+            let params = &*self.params;
+            let mut text = format!("{}Hz", format_freq(band.freq.get(params)));
+            if band.kind.has_gain() {
+                text += &format!(" {:+.1}dB", band.gain.get(params));
+            }
+            text += &format!(" Q{:.2}", band.q.get(params));
+            let [nx, ny] = self.node_position(&band);
+            let pos = [nx + *self.node_radius + 4.0, ny - self.labels.font_size - *self.node_radius];
+            self.labels.queue_spans(&[(&text, band.color)], pos, (self.labels.font_size * 16.0, self.labels.font_size * 2.0));
+        }
+    }
+}
+
+fn format_freq(freq: f32) -> String {
+    if freq >= 1000.0 {
+        format!("{}k", (freq / 100.0).round() / 10.0)
+    } else {
+        format!("{}", freq.round())
+    }
+}
+
+impl UIElement for UIEqEditor {
+    fn render(&mut self, window_size: (i32, i32)) {
+        self.build();
+        self.shapes.update_geometry(window_size);
+        self.shapes.upload(&self.batch);
+        self.shapes.draw();
+
+        self.labels.resize(window_size);
+        self.queue_labels();
+        self.labels.draw_queued();
+        self.dirty.drawn(self.view());
+    }
+
+    fn get_window_transform() -> TMatrix {
+        TMatrix::default()
+    }
+
+    fn set_rect(&mut self, rect: [f32; 4]) {
+        self.rect = rect;
+    }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        self.colors.set_theme(EqColors::from_theme(theme));
+        self.node_radius.set_theme(theme.point_radius + 1.0);
+        self.line_width.set_theme(theme.line_width);
+        theme.apply_to_labels(&mut self.labels);
+        self.dirty.invalidate();
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty.is_dirty(&self.view())
+    }
+
+    fn animate(&mut self, dt: f32) -> bool {
+        match self.drag.or_else(|| self.node_at_cursor()) {
+            Some(i) => {
+                if self.highlighted != Some(i) {
+                    // moving straight to another node starts its ring from nothing
+                    self.highlighted = Some(i);
+                    self.highlight.jump(0.0);
+                }
+                self.highlight.set(1.0);
+            }
+            None => self.highlight.set(0.0),
+        }
+        self.highlight.update(dt)
+    }
+
+    fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorPos(x, y) => {
+                self.cursor = (*x, *y);
+                match self.drag {
+                    Some(i) => {
+                        self.drag_to_cursor(i);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            // a second press during a drag must not begin another gesture
+            WindowEvent::MouseButton(MouseButton::Button1, Action::Press, _) if self.drag.is_none() => match self.node_at_cursor() {
+                Some(i) => {
+                    self.begin_drag(i);
+                    true
+                }
+                None => false,
+            },
+            WindowEvent::MouseButton(MouseButton::Button1, Action::Release, _) => {
+                let dragging = self.drag.is_some();
+                self.end_drag();
+                dragging
+            }
+            WindowEvent::Scroll(_, dy) => match self.drag.or_else(|| self.node_at_cursor()) {
+                Some(i) => {
+                    self.scroll_q(i, *dy as f32);
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+impl Drop for UIEqEditor {
+    /// Closes a drag still in progress so the host is not left inside a gesture.
+    fn drop(&mut self) {
+        self.end_drag();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response_sums_bands() {
+        let fs = 48000.0;
+        let a = Biquad::new(FilterType::Bell, fs, 1000.0, 1.0, 6.0);
+        let b = Biquad::new(FilterType::HighShelf, fs, 4000.0, 0.707, -3.0);
+        let expected = a.magnitude_db(2500.0, fs) + b.magnitude_db(2500.0, fs);
+        assert!((response_db(&[a, b], 2500.0, fs) - expected).abs() < 1e-5);
+        assert_eq!(0.0, response_db(&[], 2500.0, fs));
+    }
+
+    #[test]
+    fn test_format_freq() {
+        assert_eq!("250", format_freq(250.2));
+        assert_eq!("1.5k", format_freq(1520.0));
+        assert_eq!("20k", format_freq(20000.0));
+    }
+}
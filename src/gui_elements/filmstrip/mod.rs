@@ -0,0 +1,377 @@
+use std::sync::Arc;
+use glfw::{Action, Key, MouseButton, WindowEvent};
+use crate::gui_elements::image::Image;
+use crate::gui_elements::text::text_utils::Res;
+use crate::gui_elements::utils::blit::GlBlitPipe;
+use crate::gui_elements::utils::click::ClickTracker;
+use crate::gui_elements::utils::dirty::DirtyCheck;
+use crate::gui_elements::utils::t_matrix::TMatrix;
+use crate::gui_elements::utils::texture::GlTexture;
+use crate::gui_elements::UIElement;
+use crate::gui_elements::theme::{Theme, Themed};
+use crate::params::ParamHost;
+
+/// The frame showing a normalized `value` in a strip of `frames` frames.
+pub fn frame_index(value: f32, frames: usize) -> usize {
+    (value.clamp(0.0, 1.0) * frames.saturating_sub(1) as f32).round() as usize
+}
+
+/// One resolution of a filmstrip. Tall strips are split over several textures so no texture
+/// exceeds the driver's size limit.
+struct StripVariant {
+    scale: f32,
+    frames_per_texture: usize,
+    textures: Vec<GlTexture>,
+}
+
+/// A vertical strip of equally sized frames, e.g. every rotation of a knob, loaded at 1x and
+/// optionally 2x for high density displays.
+pub struct Filmstrip {
+    frames: usize,
+    /// Frame size at 1x, in pixels.
+    frame_size: (u32, u32),
+    variants: Vec<StripVariant>,
+}
+
+impl Filmstrip {
+    /// `x2` must be exactly twice the size of `x1`.
+    pub fn new(frames: usize, x1: &Image, x2: Option<&Image>) -> Res<Self> {
+        if frames == 0 || !x1.height().is_multiple_of(frames as u32) {
+            return Err(format!("{} px tall strip does not split into {} frames", x1.height(), frames).into());
+        }
+        let mut variants = vec![Self::variant(1.0, frames, x1)];
+        if let Some(x2) = x2 {
+            if (x2.width(), x2.height()) != (x1.width() * 2, x1.height() * 2) {
+                return Err(format!(
+                    "2x strip is {}x{}, expected {}x{}",
+                    x2.width(), x2.height(), x1.width() * 2, x1.height() * 2
+                ).into());
+            }
+            variants.push(Self::variant(2.0, frames, x2));
+        }
+        Ok(Self { frames, frame_size: (x1.width(), x1.height() / frames as u32), variants })
+    }
+
+    fn variant(scale: f32, frames: usize, image: &Image) -> StripVariant {
+        let mut max_size = 0;
+        unsafe {
+            gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_size);
+        }
+        let frame_height = image.height() / frames as u32;
+        let frames_per_texture = ((max_size.max(1) as u32 / frame_height.max(1)) as usize).clamp(1, frames);
+        let textures = (0..frames)
+            .step_by(frames_per_texture)
+            .map(|first| {
+                let count = frames_per_texture.min(frames - first) as u32;
+                image.sub_image([0, first as u32 * frame_height, image.width(), count * frame_height]).to_texture()
+            })
+            .collect();
+        StripVariant { scale, frames_per_texture, textures }
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Size of one frame at 1x.
+    pub fn frame_size(&self) -> (u32, u32) {
+        self.frame_size
+    }
+
+    /// Draws `frame` into `rect` multiplied by `tint`, picking the sharpest variant that is not
+    /// wasted at `scale`.
+    pub fn draw(&self, pipe: &GlBlitPipe, frame: usize, rect: [f32; 4], scale: f32, tint: [f32; 4], window_size: (i32, i32)) {
+        let variant = self
+            .variants
+            .iter()
+            .find(|v| v.scale >= scale)
+            .unwrap_or_else(|| self.variants.last().unwrap());
+        let frame = frame.min(self.frames - 1);
+        let texture = &variant.textures[frame / variant.frames_per_texture];
+        let rows = texture.size().1 as f32 / (self.frame_size.1 as f32 * variant.scale);
+        let row = (frame % variant.frames_per_texture) as f32;
+        pipe.draw_region(texture, [0.0, row / rows, 1.0, (row + 1.0) / rows], rect, tint, window_size);
+    }
+}
+
+/// A knob drawn from a filmstrip and bound to a parameter. Drag vertically to turn it (hold
+/// Shift for fine control), use the wheel for steps and double-click to reset it. Filmstrips
+/// are artwork, the theme only reaches them through [`Theme::artwork_tint`].
+pub struct UIFilmstripKnob {
+    /// `[x, y, width, height]` in window pixels.
+    pub rect: [f32; 4],
+    /// Display scale, 2 picks the 2x strip if there is one.
+    pub scale: f32,
+    /// Multiplied into the strip.
+    pub tint: Themed<[f32; 4]>,
+    /// Pixels of vertical drag for the whole range.
+    pub drag_pixels: f32,
+    /// Drag speed while Shift is held.
+    pub fine_factor: f32,
+    pub wheel_step: f32,
+    /// Value restored by double-click, none to disable.
+    pub default_value: Option<f32>,
+    strip: Filmstrip,
+    index: i32,
+    params: Arc<dyn ParamHost>,
+    blit: GlBlitPipe,
+    cursor: (f64, f64),
+    shift: bool,
+    clicks: ClickTracker,
+    /// Cursor height and value at the last drag step.
+    drag: Option<(f32, f32)>,
+    dirty: DirtyCheck<([f32; 4], usize, [f32; 4])>,
+}
+
+impl UIFilmstripKnob {
+    /// Binds the knob to the normalized parameter `index`. `rect` starts at `position` with the
+    /// strip's 1x frame size.
+    pub fn new(position: [f32; 2], strip: Filmstrip, index: i32, params: Arc<dyn ParamHost>) -> Res<Self> {
+        let (w, h) = strip.frame_size();
+        Ok(Self {
+            rect: [position[0], position[1], w as f32, h as f32],
+            scale: 1.0,
+            tint: Themed::new([1.0; 4]),
+            drag_pixels: 200.0,
+            fine_factor: 0.1,
+            wheel_step: 0.02,
+            default_value: None,
+            strip,
+            index,
+            params,
+            blit: GlBlitPipe::new()?,
+            cursor: (0.0, 0.0),
+            shift: false,
+            clicks: ClickTracker::new(),
+            drag: None,
+            dirty: DirtyCheck::new(),
+        })
+    }
+
+    fn frame(&self) -> usize {
+        frame_index(self.params.get(self.index), self.strip.frames())
+    }
+
+    fn contains_cursor(&self) -> bool {
+        let [x, y, w, h] = self.rect;
+        let (cx, cy) = (self.cursor.0 as f32, self.cursor.1 as f32);
+        cx >= x && cx < x + w && cy >= y && cy < y + h
+    }
+}
+
+impl UIElement for UIFilmstripKnob {
+    fn render(&mut self, window_size: (i32, i32)) {
+        let frame = self.frame();
+        self.strip.draw(&self.blit, frame, self.rect, self.scale, *self.tint, window_size);
+        self.dirty.drawn((self.rect, frame, *self.tint));
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty.is_dirty(&(self.rect, self.frame(), *self.tint))
+    }
+
+    fn get_window_transform() -> TMatrix {
+        TMatrix::default()
+    }
+
+    fn set_rect(&mut self, rect: [f32; 4]) {
+        self.rect = rect;
+    }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        self.tint.set_theme(theme.artwork_tint);
+    }
+
+    fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorPos(x, y) => {
+                self.cursor = (*x, *y);
+                let (last_y, value) = match self.drag {
+                    Some(drag) => drag,
+                    None => return false,
+                };
+                // relative steps so switching Shift mid drag does not jump
+                let speed = if self.shift { self.fine_factor } else { 1.0 };
+                let value = (value + (last_y - *y as f32) / self.drag_pixels * speed).clamp(0.0, 1.0);
+                self.params.set(self.index, value);
+                self.drag = Some((*y as f32, value));
+                true
+            }
+            WindowEvent::Key(Key::LeftShift | Key::RightShift, _, action, _) => {
+                self.shift = *action != Action::Release;
+                false
+            }
+            // a second press during a drag must not begin another gesture
+            WindowEvent::MouseButton(MouseButton::Button1, Action::Press, _) if self.drag.is_none() && self.contains_cursor() => {
+                self.params.begin_edit(self.index);
+                match (self.clicks.press(self.cursor), self.default_value) {
+                    (true, Some(default)) => {
+                        self.params.set(self.index, default);
+                        self.params.end_edit(self.index);
+                    }
+                    _ => self.drag = Some((self.cursor.1 as f32, self.params.get(self.index))),
+                }
+                true
+            }
+            WindowEvent::MouseButton(MouseButton::Button1, Action::Release, _) => match self.drag.take() {
+                Some(_) => {
+                    self.params.end_edit(self.index);
+                    true
+                }
+                None => false,
+            },
+            WindowEvent::Scroll(_, dy) if self.contains_cursor() => {
+                self.params.begin_edit(self.index);
+                let value = self.params.get(self.index) + *dy as f32 * self.wheel_step;
+                self.params.set(self.index, value.clamp(0.0, 1.0));
+                self.params.end_edit(self.index);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Drop for UIFilmstripKnob {
+    fn drop(&mut self) {
+        if self.drag.take().is_some() {
+            self.params.end_edit(self.index);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ButtonMode {
+    /// Each click flips the parameter between 0 and 1.
+    Toggle,
+    /// The parameter is 1 while the button is held.
+    Momentary,
+}
+
+/// A button drawn from a filmstrip whose first frame is off and last frame is on, bound to a
+/// parameter.
+pub struct UIFilmstripButton {
+    /// `[x, y, width, height]` in window pixels.
+    pub rect: [f32; 4],
+    pub scale: f32,
+    /// Multiplied into the strip.
+    pub tint: Themed<[f32; 4]>,
+    pub mode: ButtonMode,
+    strip: Filmstrip,
+    index: i32,
+    params: Arc<dyn ParamHost>,
+    blit: GlBlitPipe,
+    cursor: (f64, f64),
+    held: bool,
+    dirty: DirtyCheck<([f32; 4], bool, [f32; 4])>,
+}
+
+impl UIFilmstripButton {
+    pub fn new(position: [f32; 2], strip: Filmstrip, index: i32, params: Arc<dyn ParamHost>) -> Res<Self> {
+        let (w, h) = strip.frame_size();
+        Ok(Self {
+            rect: [position[0], position[1], w as f32, h as f32],
+            scale: 1.0,
+            tint: Themed::new([1.0; 4]),
+            mode: ButtonMode::Toggle,
+            strip,
+            index,
+            params,
+            blit: GlBlitPipe::new()?,
+            cursor: (0.0, 0.0),
+            held: false,
+            dirty: DirtyCheck::new(),
+        })
+    }
+
+    fn is_on(&self) -> bool {
+        self.params.get(self.index) >= 0.5
+    }
+
+    fn release(&mut self) {
+        if self.held {
+            self.held = false;
+            self.params.set(self.index, 0.0);
+            self.params.end_edit(self.index);
+        }
+    }
+}
+
+impl UIElement for UIFilmstripButton {
+    fn render(&mut self, window_size: (i32, i32)) {
+        let on = self.is_on();
+        let frame = if on { self.strip.frames() - 1 } else { 0 };
+        self.strip.draw(&self.blit, frame, self.rect, self.scale, *self.tint, window_size);
+        self.dirty.drawn((self.rect, on, *self.tint));
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty.is_dirty(&(self.rect, self.is_on(), *self.tint))
+    }
+
+    fn get_window_transform() -> TMatrix {
+        TMatrix::default()
+    }
+
+    fn set_rect(&mut self, rect: [f32; 4]) {
+        self.rect = rect;
+    }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        self.tint.set_theme(theme.artwork_tint);
+    }
+
+    fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorPos(x, y) => {
+                self.cursor = (*x, *y);
+                false
+            }
+            WindowEvent::MouseButton(MouseButton::Button1, Action::Press, _) if !self.held => {
+                let [x, y, w, h] = self.rect;
+                let (cx, cy) = (self.cursor.0 as f32, self.cursor.1 as f32);
+                if !(cx >= x && cx < x + w && cy >= y && cy < y + h) {
+                    return false;
+                }
+                self.params.begin_edit(self.index);
+                match self.mode {
+                    ButtonMode::Toggle => {
+                        let on = self.is_on();
+                        self.params.set(self.index, if on { 0.0 } else { 1.0 });
+                        self.params.end_edit(self.index);
+                    }
+                    ButtonMode::Momentary => {
+                        self.params.set(self.index, 1.0);
+                        self.held = true;
+                    }
+                }
+                true
+            }
+            WindowEvent::MouseButton(MouseButton::Button1, Action::Release, _) if self.held => {
+                self.release();
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Drop for UIFilmstripButton {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_index() {
+        assert_eq!(0, frame_index(0.0, 64));
+        assert_eq!(63, frame_index(1.0, 64));
+        assert_eq!(32, frame_index(0.5, 64));
+        assert_eq!(63, frame_index(2.0, 64));
+        assert_eq!(0, frame_index(0.7, 1));
+    }
+}
@@ -0,0 +1,70 @@
+/// A row of the atlas holding images up to `height` tall, filled left to right.
+#[derive(Debug, Clone)]
+struct Shelf {
+    y: u32,
+    height: u32,
+    used: u32,
+}
+
+/// Places rectangles in a fixed size texture using shelves. Simple and good enough for the
+/// handful of icons and widget parts a plugin editor has. Nothing is ever freed.
+#[derive(Debug, Clone)]
+pub struct AtlasPacker {
+    width: u32,
+    height: u32,
+    /// Empty pixels kept around every rectangle so filtering does not pick up neighbours.
+    padding: u32,
+    shelves: Vec<Shelf>,
+}
+
+impl AtlasPacker {
+    pub fn new(width: u32, height: u32, padding: u32) -> Self {
+        Self { width, height, padding, shelves: Vec::new() }
+    }
+
+    /// Finds room for a `width` x `height` rectangle and returns its top left corner, or
+    /// `None` if the atlas is full.
+    pub fn pack(&mut self, width: u32, height: u32) -> Option<[u32; 2]> {
+        let (w, h) = (width + self.padding * 2, height + self.padding * 2);
+        if w > self.width || h > self.height {
+            return None;
+        }
+        // the lowest shelf that fits wastes the least height
+        let best = self
+            .shelves
+            .iter_mut()
+            .filter(|s| s.height >= h && s.used + w <= self.width)
+            .min_by_key(|s| s.height);
+        let shelf = match best {
+            Some(shelf) => shelf,
+            None => {
+                let y = self.shelves.last().map(|s| s.y + s.height).unwrap_or(0);
+                if y + h > self.height {
+                    return None;
+                }
+                self.shelves.push(Shelf { y, height: h, used: 0 });
+                self.shelves.last_mut().unwrap()
+            }
+        };
+        let pos = [shelf.used + self.padding, shelf.y + self.padding];
+        shelf.used += w;
+        Some(pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_shelves() {
+        let mut a = AtlasPacker::new(64, 32, 1);
+        assert_eq!(Some([1, 1]), a.pack(30, 10));
+        assert_eq!(Some([33, 1]), a.pack(20, 8));
+        // does not fit next to the others, opens a second shelf
+        assert_eq!(Some([1, 13]), a.pack(20, 10));
+        assert_eq!(Some([23, 13]), a.pack(10, 5));
+        assert_eq!(None, a.pack(40, 10));
+        assert_eq!(None, a.pack(70, 1));
+    }
+}
@@ -0,0 +1,205 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use parking_lot::Mutex;
+use crate::gui_elements::image::atlas::AtlasPacker;
+use crate::gui_elements::image::pipe::{GlImagePipe, ImageBatch};
+use crate::gui_elements::image::Image;
+use crate::gui_elements::text::text_utils::Res;
+use crate::gui_elements::utils::texture::{GlTexture, TextureFormat};
+
+/// Size of one atlas page.
+pub const ATLAS_SIZE: u32 = 1024;
+/// Images wider or taller than this get a texture of their own instead of atlas space.
+pub const ATLAS_MAX_IMAGE: u32 = 256;
+const ATLAS_PADDING: u32 = 1;
+
+/// Where the pixels of an image come from.
+#[derive(Debug, Clone)]
+pub enum ImageSource {
+    /// PNG bytes compiled into the plugin, e.g. with `include_bytes!`.
+    Embedded(&'static [u8]),
+    /// A PNG file, read on first use.
+    File(PathBuf),
+    /// Pixels that are already decoded.
+    Rgba(Arc<Image>),
+}
+
+struct LibraryEntry {
+    source: ImageSource,
+    image: Option<Arc<Image>>,
+}
+
+/// Images registered by ID. Decoded images are cached, so the library should live as long
+/// as the editor and be shared with the [`TextureManager`] made on every open.
+#[derive(Default)]
+pub struct ImageLibrary {
+    entries: Mutex<HashMap<String, LibraryEntry>>,
+}
+
+impl ImageLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `source` under `id`, replacing an earlier image with the same ID.
+    pub fn add<S: Into<String>>(&self, id: S, source: ImageSource) {
+        let image = match &source {
+            ImageSource::Rgba(image) => Some(image.clone()),
+            _ => None,
+        };
+        self.entries.lock().insert(id.into(), LibraryEntry { source, image });
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.entries.lock().contains_key(id)
+    }
+
+    /// Decodes the image on first use.
+    pub fn load(&self, id: &str) -> Res<Arc<Image>> {
+        let mut entries = self.entries.lock();
+        let entry = entries.get_mut(id).ok_or_else(|| format!("no image with id \"{}\"", id))?;
+        if let Some(image) = &entry.image {
+            return Ok(image.clone());
+        }
+        let image = Arc::new(match &entry.source {
+            ImageSource::Embedded(bytes) => Image::from_png(bytes)?,
+            ImageSource::File(path) => Image::load_png(path)?,
+            ImageSource::Rgba(image) => (**image).clone(),
+        });
+        entry.image = Some(image.clone());
+        Ok(image)
+    }
+}
+
+/// Where an image ended up on the GPU.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextureRegion {
+    texture: usize,
+    /// `[left, top, right, bottom]` texture coordinates.
+    pub uv: [f32; 4],
+    /// Image size in pixels.
+    pub size: (u32, u32),
+}
+
+struct AtlasPage {
+    texture: usize,
+    packer: AtlasPacker,
+}
+
+struct TextureCache {
+    textures: Vec<GlTexture>,
+    pages: Vec<AtlasPage>,
+    regions: HashMap<String, TextureRegion>,
+    pipe: GlImagePipe,
+    batch: ImageBatch,
+}
+
+/// Uploads images from an [`ImageLibrary`] on first use and draws them as tinted or
+/// nine-sliced quads. Small images share atlas pages. All textures belong to the GL context
+/// that was current when the manager was created and are freed when it is dropped, so make a
+/// new manager every time the editor opens and share it between widgets with an `Rc`.
+pub struct TextureManager {
+    library: Arc<ImageLibrary>,
+    cache: RefCell<TextureCache>,
+}
+
+impl TextureManager {
+    pub fn new(library: Arc<ImageLibrary>) -> Res<Self> {
+        Ok(Self {
+            library,
+            cache: RefCell::new(TextureCache {
+                textures: Vec::new(),
+                pages: Vec::new(),
+                regions: HashMap::new(),
+                pipe: GlImagePipe::new()?,
+                batch: ImageBatch::new(),
+            }),
+        })
+    }
+
+    pub fn library(&self) -> &Arc<ImageLibrary> {
+        &self.library
+    }
+
+    /// Uploads the image if it is not on the GPU yet.
+    pub fn region(&self, id: &str) -> Res<TextureRegion> {
+        if let Some(region) = self.cache.borrow().regions.get(id) {
+            return Ok(*region);
+        }
+        let image = self.library.load(id)?;
+        let mut cache = self.cache.borrow_mut();
+        let region = cache.upload(&image);
+        cache.regions.insert(id.to_string(), region);
+        Ok(region)
+    }
+
+    /// Draws the whole image stretched over `rect` (`[x, y, width, height]` in window pixels),
+    /// multiplied by `tint`.
+    pub fn draw(&self, id: &str, rect: [f32; 4], tint: [f32; 4], window_size: (i32, i32)) -> Res<()> {
+        let region = self.region(id)?;
+        self.draw_batch(region, window_size, |batch| batch.quad(rect, region.uv, tint));
+        Ok(())
+    }
+
+    /// Draws the image as a nine-slice, keeping the `insets` (`[left, top, right, bottom]` in
+    /// image pixels) at their size and stretching the rest.
+    pub fn draw_nine_slice(&self, id: &str, rect: [f32; 4], insets: [f32; 4], tint: [f32; 4], window_size: (i32, i32)) -> Res<()> {
+        let region = self.region(id)?;
+        self.draw_batch(region, window_size, |batch| batch.nine_slice(rect, region.size, region.uv, insets, tint));
+        Ok(())
+    }
+
+    fn draw_batch<F: FnOnce(&mut ImageBatch)>(&self, region: TextureRegion, window_size: (i32, i32), f: F) {
+        let mut cache = self.cache.borrow_mut();
+        let TextureCache { textures, pipe, batch, .. } = &mut *cache;
+        batch.clear();
+        f(batch);
+        pipe.draw(&textures[region.texture], batch, window_size);
+    }
+}
+
+impl TextureCache {
+    fn upload(&mut self, image: &Image) -> TextureRegion {
+        let (w, h) = (image.width(), image.height());
+        if w > ATLAS_MAX_IMAGE || h > ATLAS_MAX_IMAGE {
+            self.textures.push(image.to_texture());
+            return TextureRegion { texture: self.textures.len() - 1, uv: [0.0, 0.0, 1.0, 1.0], size: (w, h) };
+        }
+        let found = self.pages.iter_mut().find_map(|p| p.packer.pack(w, h).map(|pos| (p.texture, pos)));
+        let (texture, [x, y]) = match found {
+            Some(found) => found,
+            None => {
+                // cleared, so the padding around every image is transparent
+                let empty = vec![0; (ATLAS_SIZE * ATLAS_SIZE * 4) as usize];
+                self.textures.push(GlTexture::new((ATLAS_SIZE, ATLAS_SIZE), TextureFormat::Rgba, Some(&empty)));
+                let mut packer = AtlasPacker::new(ATLAS_SIZE, ATLAS_SIZE, ATLAS_PADDING);
+                let pos = packer.pack(w, h).expect("image fits an empty atlas page");
+                self.pages.push(AtlasPage { texture: self.textures.len() - 1, packer });
+                (self.textures.len() - 1, pos)
+            }
+        };
+        self.textures[texture].upload_region([x, y, w, h], image.pixels());
+        let size = ATLAS_SIZE as f32;
+        let uv = [x as f32 / size, y as f32 / size, (x + w) as f32 / size, (y + h) as f32 / size];
+        TextureRegion { texture, uv, size: (w, h) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_library_cache() {
+        let library = ImageLibrary::new();
+        let image = Arc::new(Image::from_rgba(1, 1, vec![1, 2, 3, 4]).unwrap());
+        library.add("dot", ImageSource::Rgba(image.clone()));
+        library.add("missing", ImageSource::File(PathBuf::from("does/not/exist.png")));
+        assert!(Arc::ptr_eq(&image, &library.load("dot").unwrap()));
+        assert!(library.load("missing").is_err());
+        assert!(library.load("unknown").is_err());
+        assert!(library.contains("missing") && !library.contains("unknown"));
+    }
+}
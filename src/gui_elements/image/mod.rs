@@ -0,0 +1,182 @@
+pub mod atlas;
+pub mod manager;
+pub mod pipe;
+
+use std::path::Path;
+use std::rc::Rc;
+use glfw::WindowEvent;
+use crate::gui_elements::text::text_utils::Res;
+use crate::gui_elements::utils::dirty::DirtyCheck;
+use crate::gui_elements::utils::t_matrix::TMatrix;
+use crate::gui_elements::utils::texture::{GlTexture, TextureFormat};
+use crate::gui_elements::UIElement;
+use crate::gui_elements::theme::{Theme, Themed};
+use manager::TextureManager;
+
+/// An RGBA8 image in CPU memory with straight (not premultiplied) alpha, row 0 at the top.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    /// Wraps raw RGBA pixels. `pixels` must hold `width * height * 4` bytes.
+    pub fn from_rgba(width: u32, height: u32, pixels: Vec<u8>) -> Res<Self> {
+        if pixels.len() != width as usize * height as usize * 4 {
+            return Err(format!("{}x{} RGBA image needs {} bytes, got {}", width, height, width * height * 4, pixels.len()).into());
+        }
+        Ok(Self { width, height, pixels })
+    }
+
+    /// Decodes a PNG of any color type and bit depth, e.g. from `include_bytes!`.
+    pub fn from_png(bytes: &[u8]) -> Res<Self> {
+        let mut decoder = png::Decoder::new(bytes);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+        let data = &buf[..info.buffer_size()];
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => data.to_vec(),
+            png::ColorType::Rgb => data.chunks(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+            png::ColorType::GrayscaleAlpha => data.chunks(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+            png::ColorType::Grayscale => data.iter().flat_map(|v| [*v, *v, *v, 255]).collect(),
+            png::ColorType::Indexed => return Err("indexed PNG was not expanded".into()),
+        };
+        Self::from_rgba(info.width, info.height, pixels)
+    }
+
+    pub fn load_png<P: AsRef<Path>>(path: P) -> Res<Self> {
+        let bytes = std::fs::read(path.as_ref()).map_err(|e| format!("{}: {}", path.as_ref().display(), e))?;
+        Self::from_png(&bytes)
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Copies out the pixels in `rect` (`[x, y, width, height]`).
+    pub fn sub_image(&self, rect: [u32; 4]) -> Self {
+        let [x, y, w, h] = rect;
+        assert!(x + w <= self.width && y + h <= self.height);
+        let stride = self.width as usize * 4;
+        let mut pixels = Vec::with_capacity(w as usize * h as usize * 4);
+        for row in y..y + h {
+            let start = row as usize * stride + x as usize * 4;
+            pixels.extend_from_slice(&self.pixels[start..start + w as usize * 4]);
+        }
+        Self { width: w, height: h, pixels }
+    }
+
+    /// Uploads the image. Texture row 0 is the top row of the image.
+    pub fn to_texture(&self) -> GlTexture {
+        GlTexture::new((self.width, self.height), TextureFormat::Rgba, Some(&self.pixels))
+    }
+}
+
+/// Image id, rect, tint and insets, everything a [`UIImage`] looks like.
+type ImageView = (String, [f32; 4], [f32; 4], Option<[f32; 4]>);
+
+/// Draws an image from a [`TextureManager`], stretched or nine-sliced over `rect`. Images
+/// are artwork, the theme only reaches them through [`Theme::artwork_tint`].
+pub struct UIImage {
+    /// `[x, y, width, height]` in window pixels.
+    pub rect: [f32; 4],
+    pub id: String,
+    /// Multiplied into the image.
+    pub tint: Themed<[f32; 4]>,
+    /// Nine-slice border widths (`[left, top, right, bottom]` in image pixels), none to stretch.
+    pub insets: Option<[f32; 4]>,
+    textures: Rc<TextureManager>,
+    dirty: DirtyCheck<ImageView>,
+}
+
+impl UIImage {
+    pub fn new<S: Into<String>>(rect: [f32; 4], id: S, textures: Rc<TextureManager>) -> Res<Self> {
+        let id = id.into();
+        // fail early on unknown or broken images rather than on every frame
+        textures.region(&id)?;
+        Ok(Self { rect, id, tint: Themed::new([1.0; 4]), insets: None, textures, dirty: DirtyCheck::new() })
+    }
+
+    fn view(&self) -> ImageView {
+        (self.id.clone(), self.rect, *self.tint, self.insets)
+    }
+}
+
+impl UIElement for UIImage {
+    fn render(&mut self, window_size: (i32, i32)) {
+        let drawn = match self.insets {
+            Some(insets) => self.textures.draw_nine_slice(&self.id, self.rect, insets, *self.tint, window_size),
+            None => self.textures.draw(&self.id, self.rect, *self.tint, window_size),
+        };
+        if let Err(e) = drawn {
+            vst_log::log(format!("image {}: {}", self.id, e));
+        }
+        self.dirty.drawn(self.view());
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty.is_dirty(&self.view())
+    }
+
+    fn get_window_transform() -> TMatrix {
+        TMatrix::default()
+    }
+
+    fn set_rect(&mut self, rect: [f32; 4]) {
+        self.rect = rect;
+    }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        self.tint.set_theme(theme.artwork_tint);
+    }
+
+    fn handle_event(&mut self, _event: &WindowEvent) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(width: u32, height: u32, color: png::ColorType, data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut encoder = png::Encoder::new(&mut out, width, height);
+        encoder.set_color(color);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(data).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_png_color_types() {
+        let rgb = Image::from_png(&encode(2, 1, png::ColorType::Rgb, &[1, 2, 3, 4, 5, 6])).unwrap();
+        assert_eq!(&[1, 2, 3, 255, 4, 5, 6, 255], rgb.pixels());
+        let gray = Image::from_png(&encode(1, 2, png::ColorType::GrayscaleAlpha, &[7, 8, 9, 10])).unwrap();
+        assert_eq!((1, 2), (gray.width(), gray.height()));
+        assert_eq!(&[7, 7, 7, 8, 9, 9, 9, 10], gray.pixels());
+        assert!(Image::from_png(&[0, 1, 2]).is_err());
+    }
+
+    #[test]
+    fn test_sub_image() {
+        let pixels: Vec<u8> = (0..4 * 3 * 4).map(|i| i as u8).collect();
+        let image = Image::from_rgba(4, 3, pixels).unwrap();
+        let sub = image.sub_image([1, 1, 2, 2]);
+        assert_eq!(&[20, 21, 22, 23], &sub.pixels()[..4]);
+        assert_eq!(&[36, 37, 38, 39], &sub.pixels()[8..12]);
+        assert!(Image::from_rgba(2, 2, vec![0; 3]).is_err());
+    }
+}
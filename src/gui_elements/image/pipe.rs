@@ -0,0 +1,190 @@
+use gl::types::*;
+use std::{ffi::CString, mem};
+use crate::gui_elements::text::text_utils::{ortho, Res};
+use crate::gui_elements::utils::gl::{compile_shader, link_program, gl_err_to_str, GlObject};
+use crate::gui_elements::utils::texture::GlTexture;
+use crate::gl_log_error;
+
+/// `[x, y, u, v, r, g, b, a]`
+pub type ImageVertex = [GLfloat; 8];
+
+/// Splits `rect` into the nine parts of a nine-slice image. `size` is the source image size in
+/// pixels, `uv` its `[left, top, right, bottom]` texture coordinates and `insets` the
+/// `[left, top, right, bottom]` border widths in image pixels. Borders keep their size, the
+/// edges stretch along one axis and the center along both. Borders shrink evenly if `rect` is
+/// too small for them. Returns `(rect, uv)` pairs, empty parts are left out.
+pub fn nine_slice(rect: [f32; 4], size: (u32, u32), uv: [f32; 4], insets: [f32; 4]) -> Vec<([f32; 4], [f32; 4])> {
+    let [x, y, w, h] = rect;
+    let [l, t, r, b] = insets;
+    let (iw, ih) = (size.0.max(1) as f32, size.1.max(1) as f32);
+    let sx = if l + r > w { w / (l + r) } else { 1.0 };
+    let sy = if t + b > h { h / (t + b) } else { 1.0 };
+    let xs = [x, x + l * sx, x + w - r * sx, x + w];
+    let ys = [y, y + t * sy, y + h - b * sy, y + h];
+    let [u0, v0, u1, v1] = uv;
+    let us = [u0, u0 + (u1 - u0) * l / iw, u1 - (u1 - u0) * r / iw, u1];
+    let vs = [v0, v0 + (v1 - v0) * t / ih, v1 - (v1 - v0) * b / ih, v1];
+    let mut parts = Vec::with_capacity(9);
+    for row in 0..3 {
+        for col in 0..3 {
+            let part = [xs[col], ys[row], xs[col + 1] - xs[col], ys[row + 1] - ys[row]];
+            if part[2] > 0.0 && part[3] > 0.0 {
+                parts.push((part, [us[col], vs[row], us[col + 1], vs[row + 1]]));
+            }
+        }
+    }
+    parts
+}
+
+/// Textured, tinted quads in window pixel coordinates that all sample the same texture.
+#[derive(Debug, Default, Clone)]
+pub struct ImageBatch {
+    vertices: Vec<ImageVertex>,
+}
+
+impl ImageBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    pub fn vertices(&self) -> &[ImageVertex] {
+        &self.vertices
+    }
+
+    /// `uv` is `[left, top, right, bottom]`, the texture is multiplied by `tint`.
+    pub fn quad(&mut self, rect: [f32; 4], uv: [f32; 4], tint: [f32; 4]) {
+        let [x, y, w, h] = rect;
+        let [u0, v0, u1, v1] = uv;
+        let [r, g, b, a] = tint;
+        let tl = [x, y, u0, v0, r, g, b, a];
+        let tr = [x + w, y, u1, v0, r, g, b, a];
+        let br = [x + w, y + h, u1, v1, r, g, b, a];
+        let bl = [x, y + h, u0, v1, r, g, b, a];
+        self.vertices.extend_from_slice(&[tl, tr, br, tl, br, bl]);
+    }
+
+    /// See [`nine_slice`].
+    pub fn nine_slice(&mut self, rect: [f32; 4], size: (u32, u32), uv: [f32; 4], insets: [f32; 4], tint: [f32; 4]) {
+        for (part, part_uv) in nine_slice(rect, size, uv, insets) {
+            self.quad(part, part_uv, tint);
+        }
+    }
+}
+
+/// Draws an [`ImageBatch`] with one texture.
+pub struct GlImagePipe {
+    shaders: [GLuint; 2],
+    program: GLuint,
+    vao: GLuint,
+    vbo: GLuint,
+    proj_uniform: GLint,
+    vertex_buffer_len: usize,
+}
+
+impl GlImagePipe {
+    pub fn new() -> Res<Self> {
+        let vs = compile_shader(include_str!("shaders/image.vs"), gl::VERTEX_SHADER)?;
+        let fs = compile_shader(include_str!("shaders/image.fs"), gl::FRAGMENT_SHADER)?;
+        let program = link_program(vs, fs)?;
+
+        let vao;
+        let vbo;
+        let proj_uniform = unsafe {
+            vao = GlObject::VertexArray.gen();
+            gl::BindVertexArray(vao);
+            vbo = GlObject::Buffer.gen();
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+
+            gl::UseProgram(program);
+            gl::BindFragDataLocation(program, 0, CString::new("out_color")?.as_ptr());
+
+            let proj_uniform = gl::GetUniformLocation(program, CString::new("proj")?.as_ptr());
+            if proj_uniform < 0 {
+                return Err(format!("GetUniformLocation(\"proj\") -> {}", proj_uniform).into());
+            }
+            gl::Uniform1i(gl::GetUniformLocation(program, CString::new("image")?.as_ptr()), 0);
+
+            let mut offset = 0;
+            for (v_field, float_count) in &[("pos", 2), ("uv", 2), ("tint", 4)] {
+                let attr = gl::GetAttribLocation(program, CString::new(*v_field)?.as_ptr());
+                if attr < 0 {
+                    return Err(format!("{} GetAttribLocation -> {}", v_field, attr).into());
+                }
+                gl::VertexAttribPointer(
+                    attr as _,
+                    *float_count,
+                    gl::FLOAT,
+                    gl::FALSE as _,
+                    mem::size_of::<ImageVertex>() as _,
+                    offset as _,
+                );
+                gl::EnableVertexAttribArray(attr as _);
+                offset += float_count * 4;
+            }
+            gl_log_error!();
+            proj_uniform
+        };
+
+        Ok(Self { shaders: [vs, fs], program, vao, vbo, proj_uniform, vertex_buffer_len: 0 })
+    }
+
+    pub fn draw(&mut self, texture: &GlTexture, batch: &ImageBatch, window_size: (i32, i32)) {
+        let vertices = batch.vertices();
+        if vertices.is_empty() {
+            return;
+        }
+        let proj = ortho(0.0, window_size.0 as f32, 0.0, window_size.1 as f32, 1.0, -1.0);
+        let bytes = mem::size_of_val(vertices) as GLsizeiptr;
+        unsafe {
+            gl::UseProgram(self.program);
+            gl::UniformMatrix4fv(self.proj_uniform, 1, 0, proj.as_ptr());
+            texture.bind(0);
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            if self.vertex_buffer_len < vertices.len() {
+                gl::BufferData(gl::ARRAY_BUFFER, bytes, vertices.as_ptr() as _, gl::DYNAMIC_DRAW);
+                self.vertex_buffer_len = vertices.len();
+            } else {
+                gl::BufferSubData(gl::ARRAY_BUFFER, 0, bytes, vertices.as_ptr() as _);
+            }
+            gl::DrawArrays(gl::TRIANGLES, 0, vertices.len() as _);
+            gl_log_error!();
+        }
+    }
+}
+
+impl Drop for GlImagePipe {
+    fn drop(&mut self) {
+        GlObject::Program.delete(self.program);
+        self.shaders.iter().for_each(|&s| GlObject::Shader.delete(s));
+        GlObject::Buffer.delete(self.vbo);
+        GlObject::VertexArray.delete(self.vao);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nine_slice() {
+        let parts = nine_slice([0.0, 0.0, 100.0, 50.0], (20, 20), [0.0, 0.0, 1.0, 1.0], [5.0, 5.0, 5.0, 5.0]);
+        assert_eq!(9, parts.len());
+        assert_eq!(([0.0, 0.0, 5.0, 5.0], [0.0, 0.0, 0.25, 0.25]), parts[0]);
+        assert_eq!(([5.0, 5.0, 90.0, 40.0], [0.25, 0.25, 0.75, 0.75]), parts[4]);
+        assert_eq!(([95.0, 45.0, 5.0, 5.0], [0.75, 0.75, 1.0, 1.0]), parts[8]);
+
+        // too narrow for both borders, no middle column
+        let parts = nine_slice([0.0, 0.0, 6.0, 50.0], (20, 20), [0.0, 0.0, 1.0, 1.0], [5.0, 5.0, 5.0, 5.0]);
+        assert_eq!(6, parts.len());
+        assert_eq!(3.0, parts[0].0[2]);
+    }
+}
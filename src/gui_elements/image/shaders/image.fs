@@ -0,0 +1,12 @@
+#version 150
+
+uniform sampler2D image;
+
+in vec2 f_uv;
+in vec4 f_tint;
+
+out vec4 out_color;
+
+void main() {
+    out_color = texture(image, f_uv) * f_tint;
+}
@@ -0,0 +1,23 @@
+#version 150
+
+const mat4 INVERT_Y_AXIS = mat4(
+    vec4(1.0, 0.0, 0.0, 0.0),
+    vec4(0.0, -1.0, 0.0, 0.0),
+    vec4(0.0, 0.0, 1.0, 0.0),
+    vec4(0.0, 0.0, 0.0, 1.0)
+);
+
+uniform mat4 proj;
+
+in vec2 pos;
+in vec2 uv;
+in vec4 tint;
+
+out vec2 f_uv;
+out vec4 f_tint;
+
+void main() {
+    f_uv = uv;
+    f_tint = tint;
+    gl_Position = INVERT_Y_AXIS * proj * vec4(pos, 0.0, 1.0);
+}
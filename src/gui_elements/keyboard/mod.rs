@@ -0,0 +1,479 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use glfw::{Action, Key, MouseButton, WindowEvent};
+use glyph_brush::ab_glyph::FontRef;
+use crate::channel::spsc::{spsc, Consumer, Producer};
+use crate::channel::GuiEnd;
+use crate::gui_elements::shapes::{GlShapePipe, ShapeBatch};
+use crate::gui_elements::text::UIText;
+use crate::gui_elements::utils::dirty::DirtyCheck;
+use crate::gui_elements::utils::t_matrix::TMatrix;
+use crate::gui_elements::UIElement;
+use crate::gui_elements::theme::{Theme, Themed};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoteEvent {
+    On { note: u8, velocity: u8 },
+    Off { note: u8 },
+}
+
+/// The highest MIDI note.
+pub const MAX_NOTE: u8 = 127;
+
+impl NoteEvent {
+    pub fn note(&self) -> u8 {
+        match *self {
+            NoteEvent::On { note, .. } | NoteEvent::Off { note } => note,
+        }
+    }
+
+    /// The raw MIDI message on `channel` (0 to 15). Notes above 127 are clamped.
+    pub fn to_midi(&self, channel: u8) -> [u8; 3] {
+        match *self {
+            NoteEvent::On { note, velocity } => [0x90 | (channel & 0x0f), note.min(MAX_NOTE), velocity.clamp(1, 127)],
+            NoteEvent::Off { note } => [0x80 | (channel & 0x0f), note.min(MAX_NOTE), 0],
+        }
+    }
+}
+
+/// Creates a queue of notes played on the editor for the plugin to pick up in `process`.
+pub fn note_channel(capacity: usize) -> (NoteSender, NoteReceiver) {
+    let (producer, consumer) = spsc(capacity);
+    (NoteSender { producer: GuiEnd::new(producer) }, NoteReceiver { consumer })
+}
+
+/// The GUI end, handed to a new keyboard on every editor open.
+#[derive(Clone)]
+pub struct NoteSender {
+    producer: GuiEnd<Producer<NoteEvent>>,
+}
+
+impl NoteSender {
+    /// Returns false if the event was dropped because the queue is full or the note is above
+    /// 127.
+    pub fn send(&self, event: NoteEvent) -> bool {
+        event.note() <= MAX_NOTE && self.producer.lock().push(event).is_ok()
+    }
+}
+
+/// The audio thread end. Never blocks.
+pub struct NoteReceiver {
+    consumer: Consumer<NoteEvent>,
+}
+
+impl NoteReceiver {
+    pub fn pop(&mut self) -> Option<NoteEvent> {
+        self.consumer.pop()
+    }
+}
+
+/// Which of the 128 MIDI notes are sounding, set by the audio thread and shown on the keyboard.
+/// Every note is one atomic bit, so it can be shared freely by cloning.
+#[derive(Debug, Clone, Default)]
+pub struct NoteState {
+    bits: Arc<[AtomicU64; 2]>,
+}
+
+impl NoteState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, note: u8, on: bool) {
+        let (word, bit) = ((note as usize >> 6) & 1, 1u64 << (note & 63));
+        if on {
+            self.bits[word].fetch_or(bit, Ordering::Relaxed);
+        } else {
+            self.bits[word].fetch_and(!bit, Ordering::Relaxed);
+        }
+    }
+
+    pub fn is_on(&self, note: u8) -> bool {
+        let (word, bit) = ((note as usize >> 6) & 1, 1u64 << (note & 63));
+        self.bits[word].load(Ordering::Relaxed) & bit != 0
+    }
+
+    pub fn clear(&self) {
+        self.bits.iter().for_each(|w| w.store(0, Ordering::Relaxed));
+    }
+
+    /// All 128 notes as bits, note 0 lowest.
+    pub fn bits(&self) -> u128 {
+        self.bits[0].load(Ordering::Relaxed) as u128 | (self.bits[1].load(Ordering::Relaxed) as u128) << 64
+    }
+}
+
+pub fn is_black(note: u8) -> bool {
+    matches!(note % 12, 1 | 3 | 6 | 8 | 10)
+}
+
+/// Note name with octave, counting middle C (60) as C4.
+pub fn note_name(note: u8) -> String {
+    const NAMES: [&str; 12] = ["C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B"];
+    format!("{}{}", NAMES[note as usize % 12], note as i32 / 12 - 1)
+}
+
+/// Semitone offset of a computer key from the base note, laid out like a piano on the home
+/// and top letter rows.
+pub fn qwerty_offset(key: Key) -> Option<u8> {
+    let offset = match key {
+        Key::A => 0,
+        Key::W => 1,
+        Key::S => 2,
+        Key::E => 3,
+        Key::D => 4,
+        Key::F => 5,
+        Key::T => 6,
+        Key::G => 7,
+        Key::Y => 8,
+        Key::H => 9,
+        Key::U => 10,
+        Key::J => 11,
+        Key::K => 12,
+        Key::O => 13,
+        Key::L => 14,
+        Key::P => 15,
+        Key::Semicolon => 16,
+        _ => return None,
+    };
+    Some(offset)
+}
+
+/// Key rectangles for the notes `low..=high`, white keys first so black keys draw on top.
+/// `low` and `high` should be white keys, `high` is cut off at the last MIDI note.
+pub fn key_layout(range: (u8, u8), rect: [f32; 4], black_height: f32) -> Vec<(u8, [f32; 4])> {
+    let range = (range.0, range.1.min(MAX_NOTE));
+    let [x, y, w, h] = rect;
+    let whites = (range.0..=range.1).filter(|n| !is_black(*n)).count().max(1);
+    let white_w = w / whites as f32;
+    let black_w = white_w * 0.6;
+    let mut keys = Vec::new();
+    let mut black = Vec::new();
+    let mut i = 0;
+    for note in range.0..=range.1 {
+        if is_black(note) {
+            black.push((note, [x + i as f32 * white_w - black_w / 2.0, y, black_w, h * black_height]));
+        } else {
+            keys.push((note, [x + i as f32 * white_w, y, white_w, h]));
+            i += 1;
+        }
+    }
+    keys.extend(black);
+    keys
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct KeyboardColors {
+    pub white: [f32; 4],
+    pub black: [f32; 4],
+    pub border: [f32; 4],
+    pub label: [f32; 4],
+    /// Keys held on the editor.
+    pub pressed: [f32; 4],
+    /// Notes the plugin reports as sounding.
+    pub playing: [f32; 4],
+}
+
+impl KeyboardColors {
+    pub fn from_theme(theme: &Theme) -> Self {
+        Self {
+            white: theme.text,
+            black: theme.surface,
+            border: theme.grid_strong,
+            label: theme.text_dim,
+            pressed: theme.accent.active,
+            playing: theme.secondary.normal,
+        }
+    }
+}
+
+impl Default for KeyboardColors {
+    fn default() -> Self {
+        Self::from_theme(&Theme::default())
+    }
+}
+
+const LABEL_SIZE: f32 = 10.0;
+
+/// A piano keyboard. Clicking plays a note with a velocity taken from how far down the key was
+/// hit, dragging glides across keys. With `qwerty` on, the letter rows play from `A` upwards and
+/// `Z` / `X` shift the octave.
+pub struct UIKeyboard {
+    /// `[x, y, width, height]` in window pixels.
+    pub rect: [f32; 4],
+    /// Lowest and highest note, both should be white keys.
+    pub range: (u8, u8),
+    /// Height of the black keys relative to the white ones.
+    pub black_height: f32,
+    pub qwerty: bool,
+    /// Note played by `A` on the computer keyboard.
+    pub qwerty_base: u8,
+    /// Velocity of notes played on the computer keyboard.
+    pub qwerty_velocity: u8,
+    pub colors: Themed<KeyboardColors>,
+    sender: NoteSender,
+    playing: Option<NoteState>,
+    cursor: (f64, f64),
+    mouse_note: Option<u8>,
+    held_keys: HashMap<Key, u8>,
+    /// Rect, held and playing notes last drawn.
+    dirty: DirtyCheck<([f32; 4], u128, u128)>,
+    shapes: GlShapePipe,
+    batch: ShapeBatch,
+    labels: UIText,
+}
+
+impl UIKeyboard {
+    pub fn new(rect: [f32; 4], sender: NoteSender) -> Result<Self, Box<dyn std::error::Error>> {
+        let font = FontRef::try_from_slice(include_bytes!("../text/fonts/source-code-pro.regular.ttf"))?;
+        let mut labels = UIText::new("", LABEL_SIZE, [1.0; 4], [0.0, 0.0], font)?;
+        labels.set_transform(TMatrix::default());
+        Ok(Self {
+            rect,
+            range: (36, 96),
+            black_height: 0.62,
+            qwerty: true,
+            qwerty_base: 60,
+            qwerty_velocity: 100,
+            colors: Themed::new(KeyboardColors::default()),
+            sender,
+            playing: None,
+            cursor: (0.0, 0.0),
+            mouse_note: None,
+            held_keys: HashMap::new(),
+            dirty: DirtyCheck::new(),
+            shapes: GlShapePipe::new((1, 1), &TMatrix::default())?,
+            batch: ShapeBatch::new(),
+            labels,
+        })
+    }
+
+    /// Highlights the notes in `state` as playing.
+    pub fn set_playing(&mut self, state: Option<NoteState>) {
+        self.playing = state;
+    }
+
+    /// The note under the cursor and the velocity for hitting it there.
+    fn note_at_cursor(&self) -> Option<(u8, u8)> {
+        let (cx, cy) = (self.cursor.0 as f32, self.cursor.1 as f32);
+        // black keys come last and lie on top
+        key_layout(self.range, self.rect, self.black_height)
+            .into_iter()
+            .rev()
+            .find(|(_, [x, y, w, h])| cx >= *x && cx < x + w && cy >= *y && cy < y + h)
+            .map(|(note, [_, y, _, h])| {
+                let depth = ((cy - y) / h).clamp(0.0, 1.0);
+                (note, (1.0 + depth * 126.0).round() as u8)
+            })
+    }
+
+    fn note_on(&self, note: u8, velocity: u8) {
+        self.sender.send(NoteEvent::On { note, velocity });
+    }
+
+    fn note_off(&self, note: u8) {
+        self.sender.send(NoteEvent::Off { note });
+    }
+
+    fn is_held(&self, note: u8) -> bool {
+        self.mouse_note == Some(note) || self.held_keys.values().any(|n| *n == note)
+    }
+
+    fn view(&self) -> ([f32; 4], u128, u128) {
+        let held = self.mouse_note.into_iter().chain(self.held_keys.values().copied()).fold(0u128, |bits, n| bits | 1 << n);
+        (self.rect, held, self.playing.as_ref().map(NoteState::bits).unwrap_or(0))
+    }
+
+    /// The note a computer key plays, none above the last MIDI note.
+    fn qwerty_note(&self, key: Key) -> Option<u8> {
+        qwerty_offset(key).and_then(|o| self.qwerty_base.checked_add(o)).filter(|n| *n <= MAX_NOTE)
+    }
+
+    fn handle_key(&mut self, key: Key, action: Action) -> bool {
+        match (key, action) {
+            (Key::Z, Action::Press) => {
+                self.qwerty_base = self.qwerty_base.saturating_sub(12);
+                true
+            }
+            (Key::X, Action::Press) => {
+                self.qwerty_base = (self.qwerty_base + 12).min(127 - 16);
+                true
+            }
+            (_, Action::Press) => match self.qwerty_note(key) {
+                Some(note) => {
+                    self.held_keys.insert(key, note);
+                    self.note_on(note, self.qwerty_velocity);
+                    true
+                }
+                None => false,
+            },
+            // key repeat must not retrigger
+            (_, Action::Repeat) => qwerty_offset(key).is_some(),
+            (_, Action::Release) => match self.held_keys.remove(&key) {
+                Some(note) => {
+                    self.note_off(note);
+                    true
+                }
+                None => false,
+            },
+        }
+    }
+
+    fn build(&mut self) {
+        let colors = self.colors;
+        let mut batch = std::mem::take(&mut self.batch);
+        batch.clear();
+        for (note, rect) in key_layout(self.range, self.rect, self.black_height) {
+            let color = if self.is_held(note) {
+                colors.pressed
+            } else if self.playing.as_ref().map(|p| p.is_on(note)).unwrap_or(false) {
+                colors.playing
+            } else if is_black(note) {
+                colors.black
+            } else {
+                colors.white
+            };
+            batch.rect(rect, color);
+            batch.rect_outline(rect, 1.0, colors.border);
+        }
+        self.batch = batch;
+    }
+
+    fn queue_labels(&mut self) {
+        let color = self.colors.label;
+        for (note, [x, y, w, h]) in key_layout(self.range, self.rect, self.black_height) {
+            if note % 12 == 0 {
+                let pos = [x + 2.0, y + h - self.labels.font_size - 3.0];
+                self.labels.queue_spans(&[(&note_name(note), color)], pos, (w.max(self.labels.font_size * 3.0), self.labels.font_size * 2.0));
+            }
+        }
+    }
+}
+
+impl UIElement for UIKeyboard {
+    fn render(&mut self, window_size: (i32, i32)) {
+        self.build();
+        self.shapes.update_geometry(window_size);
+        self.shapes.upload(&self.batch);
+        self.shapes.draw();
+
+        self.labels.resize(window_size);
+        self.queue_labels();
+        self.labels.draw_queued();
+        self.dirty.drawn(self.view());
+    }
+
+    fn get_window_transform() -> TMatrix {
+        TMatrix::default()
+    }
+
+    fn set_rect(&mut self, rect: [f32; 4]) {
+        self.rect = rect;
+    }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        self.colors.set_theme(KeyboardColors::from_theme(theme));
+        theme.apply_to_labels(&mut self.labels);
+        self.dirty.invalidate();
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty.is_dirty(&self.view())
+    }
+
+    fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorPos(x, y) => {
+                self.cursor = (*x, *y);
+                let held = match self.mouse_note {
+                    Some(held) => held,
+                    None => return false,
+                };
+                match self.note_at_cursor() {
+                    Some((note, velocity)) if note != held => {
+                        self.note_off(held);
+                        self.note_on(note, velocity);
+                        self.mouse_note = Some(note);
+                    }
+                    _ => {}
+                }
+                true
+            }
+            WindowEvent::MouseButton(MouseButton::Button1, Action::Press, _) => match self.note_at_cursor() {
+                Some((note, velocity)) => {
+                    self.note_on(note, velocity);
+                    self.mouse_note = Some(note);
+                    true
+                }
+                None => false,
+            },
+            WindowEvent::MouseButton(MouseButton::Button1, Action::Release, _) => match self.mouse_note.take() {
+                Some(note) => {
+                    self.note_off(note);
+                    true
+                }
+                None => false,
+            },
+            WindowEvent::Key(key, _, action, _) if self.qwerty => self.handle_key(*key, *action),
+            _ => false,
+        }
+    }
+}
+
+impl Drop for UIKeyboard {
+    /// Releases everything still held so no note hangs when the editor closes.
+    fn drop(&mut self) {
+        if let Some(note) = self.mouse_note.take() {
+            self.note_off(note);
+        }
+        for (_, note) in self.held_keys.drain() {
+            self.sender.send(NoteEvent::Off { note });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_note_names() {
+        assert_eq!("C4", note_name(60));
+        assert_eq!("A#-1", note_name(10));
+        assert!(is_black(61) && !is_black(64) && !is_black(65));
+    }
+
+    #[test]
+    fn test_key_layout() {
+        // one octave C to C has 8 white and 5 black keys
+        let keys = key_layout((60, 72), [0.0, 0.0, 80.0, 50.0], 0.5);
+        assert_eq!(13, keys.len());
+        assert!(keys[..8].iter().all(|(n, _)| !is_black(*n)));
+        assert_eq!((60, [0.0, 0.0, 10.0, 50.0]), keys[0]);
+        assert_eq!((61, [7.0, 0.0, 6.0, 25.0]), keys[8]);
+        assert_eq!(127, key_layout((120, 255), [0.0, 0.0, 80.0, 50.0], 0.5).iter().map(|k| k.0).max().unwrap());
+    }
+
+    #[test]
+    fn test_channel_and_state() {
+        let (sender, mut receiver) = note_channel(4);
+        assert!(sender.clone().send(NoteEvent::On { note: 60, velocity: 0 }));
+        let event = receiver.pop().unwrap();
+        assert_eq!([0x92, 60, 1], event.to_midi(2));
+        assert_eq!(None, receiver.pop());
+        // notes past 127 are dropped rather than wrapped around to low notes
+        assert!(!sender.send(NoteEvent::On { note: 128 + 60, velocity: 100 }));
+        assert_eq!(None, receiver.pop());
+        assert_eq!([0x80, 127, 0], NoteEvent::Off { note: 200 }.to_midi(0));
+
+        let state = NoteState::new();
+        state.set(100, true);
+        state.set(3, true);
+        assert!(state.is_on(100) && state.is_on(3) && !state.is_on(64 + 3));
+        assert_eq!(1 << 100 | 1 << 3, state.bits());
+        state.set(100, false);
+        assert!(!state.is_on(100));
+        assert_eq!(Some(16), qwerty_offset(Key::Semicolon));
+    }
+}
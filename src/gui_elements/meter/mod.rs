@@ -0,0 +1,508 @@
+use glfw::{Action, MouseButton, WindowEvent};
+use glyph_brush::ab_glyph::FontRef;
+use crate::channel::{spsc, Consumer, GuiEnd, Producer};
+use crate::gui_elements::shapes::{GlShapePipe, ShapeBatch};
+use crate::gui_elements::text::UIText;
+use crate::gui_elements::utils::dirty::DirtyCheck;
+use crate::gui_elements::utils::t_matrix::TMatrix;
+use crate::gui_elements::UIElement;
+use crate::gui_elements::theme::{Theme, Themed};
+
+pub use crate::dsp::MIN_DB;
+
+/// The level snaps to its target once it is this close, in dB, so the ballistics settle.
+const SETTLE_DB: f32 = 0.01;
+
+pub fn gain_to_db(gain: f32) -> f32 {
+    if gain > 0.0 {
+        (20.0 * gain.log10()).max(MIN_DB)
+    } else {
+        MIN_DB
+    }
+}
+
+/// Creates the audio thread and GUI ends of a meter feed. `capacity` is the number of
+/// readings that can be queued between two editor frames.
+pub fn meter_channel(capacity: usize) -> (MeterSender, MeterSource) {
+    let (producer, consumer) = spsc(capacity);
+    (
+        MeterSender { producer },
+        MeterSource { consumer: GuiEnd::new(consumer) },
+    )
+}
+
+/// Publishes levels from the audio thread. Never blocks or allocates, readings are dropped
+/// if the editor falls behind.
+pub struct MeterSender {
+    producer: Producer<f32>,
+}
+
+impl MeterSender {
+    pub fn push_db(&mut self, db: f32) {
+        let _ = self.producer.push(db);
+    }
+
+    /// Publishes the peak of a block of samples.
+    pub fn push_samples(&mut self, samples: &[f32]) {
+        let peak = samples.iter().fold(0.0_f32, |m, s| m.max(s.abs()));
+        self.push_db(gain_to_db(peak));
+    }
+}
+
+/// The GUI end of a meter feed.
+#[derive(Clone)]
+pub struct MeterSource {
+    consumer: GuiEnd<Consumer<f32>>,
+}
+
+impl MeterSource {
+    /// Drains every pending reading and returns the loudest.
+    pub fn take_max(&self) -> Option<f32> {
+        self.consumer.lock().drain().reduce(f32::max)
+    }
+}
+
+/// How fast the displayed level follows the input. Both times are one pole time constants
+/// in seconds applied in the dB domain, zero means instant.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ballistics {
+    pub attack_time: f32,
+    pub release_time: f32,
+}
+
+impl Ballistics {
+    /// Classic VU, roughly 300 ms to reach 99% in both directions.
+    pub fn vu() -> Self {
+        Self { attack_time: 0.065, release_time: 0.065 }
+    }
+
+    /// Quasi-peak programme meter, about 10 ms integration with a slow fall.
+    pub fn ppm() -> Self {
+        Self { attack_time: 0.002, release_time: 0.65 }
+    }
+
+    /// Sample peak with instant attack.
+    pub fn digital_peak() -> Self {
+        Self { attack_time: 0.0, release_time: 0.3 }
+    }
+}
+
+impl Default for Ballistics {
+    fn default() -> Self {
+        Self::digital_peak()
+    }
+}
+
+/// The time dependent part of a meter, kept separate from drawing so it can be stepped
+/// without a GL context.
+#[derive(Debug, Clone)]
+pub struct MeterState {
+    pub ballistics: Ballistics,
+    /// How long the peak marker stays put before decaying, in seconds.
+    pub peak_hold_time: f32,
+    /// How fast the peak marker falls once the hold time is up, in dB per second.
+    pub peak_decay: f32,
+    /// Readings at or above this latch the clip indicator.
+    pub clip_threshold: f32,
+    target: f32,
+    level: f32,
+    peak: f32,
+    peak_age: f32,
+    clipped: bool,
+}
+
+impl MeterState {
+    pub fn new(ballistics: Ballistics) -> Self {
+        Self {
+            ballistics,
+            peak_hold_time: 1.5,
+            peak_decay: 20.0,
+            clip_threshold: 0.0,
+            target: MIN_DB,
+            level: MIN_DB,
+            peak: MIN_DB,
+            peak_age: 0.0,
+            clipped: false,
+        }
+    }
+
+    /// Advances by `dt` seconds. `input` is the loudest reading since the last update,
+    /// `None` if nothing arrived.
+    pub fn update(&mut self, input: Option<f32>, dt: f32) {
+        let target = input.unwrap_or(MIN_DB).max(MIN_DB);
+        if target >= self.clip_threshold && input.is_some() {
+            self.clipped = true;
+        }
+        self.target = target;
+
+        let time = if target > self.level { self.ballistics.attack_time } else { self.ballistics.release_time };
+        if time <= 0.0 || (target - self.level).abs() < SETTLE_DB {
+            self.level = target;
+        } else {
+            self.level += (target - self.level) * (1.0 - (-dt / time).exp());
+        }
+
+        if self.level >= self.peak {
+            self.peak = self.level;
+            self.peak_age = 0.0;
+        } else {
+            self.peak_age += dt;
+            if self.peak_age > self.peak_hold_time {
+                self.peak = (self.peak - self.peak_decay * dt).max(self.level);
+            }
+        }
+    }
+
+    /// True once the level has reached the last input and the peak marker has fallen back
+    /// onto it, i.e. further updates with the same input change nothing.
+    pub fn is_settled(&self) -> bool {
+        self.level == self.target && self.peak <= self.level
+    }
+
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+
+    pub fn peak(&self) -> f32 {
+        self.peak
+    }
+
+    pub fn clipped(&self) -> bool {
+        self.clipped
+    }
+
+    pub fn reset_clip(&mut self) {
+        self.clipped = false;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    Vertical,
+    Horizontal,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MeterColors {
+    pub background: [f32; 4],
+    /// Below `warn_db`.
+    pub low: [f32; 4],
+    /// Between `warn_db` and `hot_db`.
+    pub mid: [f32; 4],
+    /// Above `hot_db`.
+    pub high: [f32; 4],
+    pub peak: [f32; 4],
+    pub clip_on: [f32; 4],
+    pub clip_off: [f32; 4],
+    pub tick: [f32; 4],
+}
+
+impl MeterColors {
+    pub fn from_theme(theme: &Theme) -> Self {
+        Self {
+            background: theme.panel,
+            low: theme.good,
+            mid: theme.warning,
+            high: theme.danger,
+            peak: theme.text,
+            clip_on: theme.danger,
+            clip_off: [theme.danger[0] * 0.25, theme.danger[1] * 0.25, theme.danger[2] * 0.25, theme.danger[3]],
+            tick: theme.text_dim,
+        }
+    }
+}
+
+impl Default for MeterColors {
+    fn default() -> Self {
+        Self::from_theme(&Theme::default())
+    }
+}
+
+const CLIP_SIZE: f32 = 8.0;
+const GAP: f32 = 2.0;
+const LABEL_SIZE: f32 = 11.0;
+
+/// A level meter with peak hold and a clip indicator that resets on click.
+pub struct UILevelMeter {
+    /// `[x, y, width, height]` in window pixels, including the scale.
+    pub rect: [f32; 4],
+    pub orientation: Orientation,
+    /// Displayed range in dB, bottom to top.
+    pub range: (f32, f32),
+    pub warn_db: f32,
+    pub hot_db: f32,
+    /// dB values that get a tick and a label.
+    pub ticks: Vec<f32>,
+    /// Space reserved for the scale next to the bar, zero to hide it.
+    pub scale_size: f32,
+    pub colors: Themed<MeterColors>,
+    pub state: MeterState,
+    source: MeterSource,
+    cursor: (f64, f64),
+    shapes: GlShapePipe,
+    batch: ShapeBatch,
+    labels: UIText,
+    dirty: DirtyCheck<([f32; 4], f32, f32, bool)>,
+}
+
+impl UILevelMeter {
+    pub fn new(rect: [f32; 4], orientation: Orientation, source: MeterSource) -> Result<Self, Box<dyn std::error::Error>> {
+        let font = FontRef::try_from_slice(include_bytes!("../text/fonts/source-code-pro.regular.ttf"))?;
+        let mut labels = UIText::new("", LABEL_SIZE, [1.0; 4], [0.0, 0.0], font)?;
+        labels.set_transform(TMatrix::default());
+        Ok(Self {
+            rect,
+            orientation,
+            range: (-60.0, 6.0),
+            warn_db: -18.0,
+            hot_db: -6.0,
+            ticks: vec![6.0, 0.0, -6.0, -12.0, -18.0, -24.0, -36.0, -48.0, -60.0],
+            scale_size: 26.0,
+            colors: Themed::new(MeterColors::default()),
+            state: MeterState::new(Ballistics::default()),
+            source,
+            cursor: (0.0, 0.0),
+            shapes: GlShapePipe::new((1, 1), &TMatrix::default())?,
+            batch: ShapeBatch::new(),
+            labels,
+            dirty: DirtyCheck::new(),
+        })
+    }
+
+    fn view(&self) -> ([f32; 4], f32, f32, bool) {
+        (self.rect, self.state.level(), self.state.peak(), self.state.clipped())
+    }
+
+    /// Maps a dB value to 0..1 along the bar.
+    fn normalize(&self, db: f32) -> f32 {
+        let (lo, hi) = self.range;
+        ((db - lo) / (hi - lo)).clamp(0.0, 1.0)
+    }
+
+    /// Returns the bar rect and the clip indicator rect.
+    fn layout(&self) -> ([f32; 4], [f32; 4]) {
+        let [x, y, w, h] = self.rect;
+        match self.orientation {
+            Orientation::Vertical => {
+                let bar_w = (w - self.scale_size).max(1.0);
+                (
+                    [x, y + CLIP_SIZE + GAP, bar_w, h - CLIP_SIZE - GAP],
+                    [x, y, bar_w, CLIP_SIZE],
+                )
+            }
+            Orientation::Horizontal => {
+                let bar_h = (h - self.scale_size).max(1.0);
+                (
+                    [x, y, w - CLIP_SIZE - GAP, bar_h],
+                    [x + w - CLIP_SIZE, y, CLIP_SIZE, bar_h],
+                )
+            }
+        }
+    }
+
+    /// The segment of the bar between two normalized positions.
+    fn segment(&self, bar: [f32; 4], from: f32, to: f32) -> [f32; 4] {
+        let [x, y, w, h] = bar;
+        match self.orientation {
+            Orientation::Vertical => [x, y + h * (1.0 - to), w, h * (to - from)],
+            Orientation::Horizontal => [x + w * from, y, w * (to - from), h],
+        }
+    }
+
+    fn build(&mut self) {
+        let (bar, clip) = self.layout();
+        let colors = self.colors;
+        let mut batch = std::mem::take(&mut self.batch);
+        batch.clear();
+
+        batch.rect(bar, colors.background);
+        let level = self.normalize(self.state.level());
+        let warn = self.normalize(self.warn_db);
+        let hot = self.normalize(self.hot_db);
+        for (from, to, color) in [(0.0, warn, colors.low), (warn, hot, colors.mid), (hot, 1.0, colors.high)] {
+            let to = to.min(level);
+            if to > from {
+                batch.rect(self.segment(bar, from, to), color);
+            }
+        }
+
+        let peak = self.normalize(self.state.peak());
+        if peak > 0.0 {
+            let marker = self.segment(bar, peak, peak);
+            match self.orientation {
+                Orientation::Vertical => batch.rect([marker[0], marker[1] - 1.0, marker[2], 2.0], colors.peak),
+                Orientation::Horizontal => batch.rect([marker[0] - 1.0, marker[1], 2.0, marker[3]], colors.peak),
+            }
+        }
+
+        batch.rect(clip, if self.state.clipped() { colors.clip_on } else { colors.clip_off });
+
+        if self.scale_size > 0.0 {
+            for db in self.ticks.iter().map(|t| self.normalize(*t)) {
+                let t = self.segment(bar, db, db);
+                match self.orientation {
+                    Orientation::Vertical => batch.rect([t[0] + t[2], t[1], 4.0, 1.0], colors.tick),
+                    Orientation::Horizontal => batch.rect([t[0], t[1] + t[3], 1.0, 4.0], colors.tick),
+                }
+            }
+        }
+        self.batch = batch;
+    }
+
+    fn queue_labels(&mut self) {
+        let (bar, _) = self.layout();
+        let bounds = (self.scale_size + self.labels.font_size * 2.0, self.labels.font_size * 2.0);
+        for db in self.ticks.clone() {
+            let t = self.segment(bar, self.normalize(db), self.normalize(db));
+            let pos = match self.orientation {
+                Orientation::Vertical => [t[0] + t[2] + 6.0, t[1] - self.labels.font_size / 2.0],
+                Orientation::Horizontal => [t[0] - self.labels.font_size / 2.0, t[1] + t[3] + 5.0],
+            };
+            self.labels.queue_spans(&[(&db.to_string(), self.colors.tick)], pos, bounds);
+        }
+    }
+}
+
+impl UIElement for UILevelMeter {
+    fn render(&mut self, window_size: (i32, i32)) {
+        self.build();
+        self.shapes.update_geometry(window_size);
+        self.shapes.upload(&self.batch);
+        self.shapes.draw();
+
+        if self.scale_size > 0.0 {
+            self.labels.resize(window_size);
+            self.queue_labels();
+            self.labels.draw_queued();
+        }
+        self.dirty.drawn(self.view());
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty.is_dirty(&self.view())
+    }
+
+    fn get_window_transform() -> TMatrix {
+        TMatrix::default()
+    }
+
+    fn set_rect(&mut self, rect: [f32; 4]) {
+        self.rect = rect;
+    }
+
+    fn animate(&mut self, dt: f32) -> bool {
+        self.state.update(self.source.take_max(), dt);
+        !self.state.is_settled()
+    }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        self.colors.set_theme(MeterColors::from_theme(theme));
+        theme.apply_to_labels(&mut self.labels);
+        self.dirty.invalidate();
+    }
+
+    fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::CursorPos(x, y) => {
+                self.cursor = (*x, *y);
+                false
+            }
+            WindowEvent::MouseButton(MouseButton::Button1, Action::Press, _) => {
+                let (_, clip) = self.layout();
+                let (x, y) = (self.cursor.0 as f32, self.cursor.1 as f32);
+                if x >= clip[0] && x <= clip[0] + clip[2] && y >= clip[1] && y <= clip[1] + clip[3] {
+                    self.state.reset_clip();
+                    true
+                } else {
+                    false
+                }
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gain_to_db() {
+        assert_eq!(0.0, gain_to_db(1.0));
+        assert_eq!(MIN_DB, gain_to_db(0.0));
+        assert!((gain_to_db(0.5) + 6.0206).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_digital_peak_attack_is_instant() {
+        let mut s = MeterState::new(Ballistics::digital_peak());
+        s.update(Some(-3.0), 0.016);
+        assert_eq!(-3.0, s.level());
+        assert_eq!(-3.0, s.peak());
+        s.update(None, 0.3);
+        assert!(s.level() < -3.0);
+        assert_eq!(-3.0, s.peak());
+    }
+
+    #[test]
+    fn test_vu_integrates() {
+        let mut s = MeterState::new(Ballistics::vu());
+        s.update(Some(0.0), 0.01);
+        assert!(s.level() < -10.0);
+        for _ in 0..30 {
+            s.update(Some(0.0), 0.01);
+        }
+        assert!(s.level() > -2.0);
+    }
+
+    #[test]
+    fn test_peak_hold_then_decay() {
+        let mut s = MeterState::new(Ballistics::digital_peak());
+        s.peak_hold_time = 1.0;
+        s.peak_decay = 10.0;
+        s.update(Some(-6.0), 0.0);
+        s.update(None, 0.5);
+        assert_eq!(-6.0, s.peak());
+        s.update(None, 0.6);
+        assert!((s.peak() + 12.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_settles_after_silence() {
+        let mut s = MeterState::new(Ballistics::ppm());
+        s.peak_hold_time = 0.5;
+        assert!(s.is_settled());
+        s.update(Some(-6.0), 0.01);
+        assert!(!s.is_settled());
+        let mut frames = 0;
+        while !s.is_settled() {
+            s.update(None, 0.02);
+            frames += 1;
+            assert!(frames < 1000, "never settled");
+        }
+        assert_eq!(MIN_DB, s.level());
+        assert_eq!(MIN_DB, s.peak());
+    }
+
+    #[test]
+    fn test_clip_latches_until_reset() {
+        let mut s = MeterState::new(Ballistics::digital_peak());
+        s.update(Some(-0.1), 0.01);
+        assert!(!s.clipped());
+        s.update(Some(0.5), 0.01);
+        s.update(None, 1.0);
+        assert!(s.clipped());
+        s.reset_clip();
+        assert!(!s.clipped());
+    }
+
+    #[test]
+    fn test_source_takes_max() {
+        let (mut tx, rx) = meter_channel(8);
+        tx.push_db(-20.0);
+        tx.push_samples(&[0.1, -0.5, 0.25]);
+        tx.push_db(-30.0);
+        assert!((rx.take_max().unwrap() + 6.0206).abs() < 1e-3);
+        assert_eq!(None, rx.take_max());
+    }
+}
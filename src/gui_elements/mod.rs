@@ -1,11 +1,56 @@
+use glfw::WindowEvent;
+use theme::Theme;
 use utils::t_matrix::TMatrix;
 
+pub mod analyzer;
+pub mod color;
+pub mod console;
+pub mod curve;
+pub mod envelope;
+pub mod eq;
+pub mod filmstrip;
+pub mod image;
+pub mod keyboard;
+pub mod meter;
+pub mod scope;
+pub mod sequencer;
+pub mod shapes;
+pub mod spectrogram;
+pub mod stereo;
 pub mod text;
+pub mod theme;
 pub mod triangle;
 pub mod utils;
 
 
 pub trait UIElement {
     fn render(&mut self, window_size: (i32, i32));
-    fn get_window_transform() -> TMatrix;
+    fn get_window_transform() -> TMatrix where Self: Sized;
+
+    /// Handles a window event. Returns true if the element consumed it.
+    fn handle_event(&mut self, _event: &WindowEvent) -> bool {
+        false
+    }
+
+    /// Moves the element to `rect` (`[x, y, width, height]` in window pixels), called by the
+    /// layout whenever it changes. Elements that place themselves can ignore it.
+    fn set_rect(&mut self, _rect: [f32; 4]) {}
+
+    /// Restyles the element from `theme`, called when it is created and whenever the theme
+    /// changes.
+    fn set_theme(&mut self, _theme: &Theme) {}
+
+    /// Advances the element's animations by `dt` seconds, called once per frame before
+    /// rendering. Returns true while any of them is still running, which asks the editor for
+    /// another frame.
+    fn animate(&mut self, _dt: f32) -> bool {
+        false
+    }
+
+    /// True if the element would look different from its last render, e.g. because a
+    /// parameter changed, the cursor moved onto a handle or new audio data arrived. The editor
+    /// skips frames in which nothing is dirty or animating.
+    fn is_dirty(&self) -> bool {
+        false
+    }
 }
\ No newline at end of file
@@ -0,0 +1,435 @@
+use crate::channel::{spsc, triple_buffer, Consumer, GuiEnd, Producer, TripleReader, TripleWriter};
+use crate::gui_elements::shapes::{GlShapePipe, ShapeBatch};
+use crate::gui_elements::utils::dirty::DirtyCheck;
+use crate::gui_elements::utils::t_matrix::TMatrix;
+use crate::gui_elements::UIElement;
+use crate::transport::Transport;
+use crate::gui_elements::theme::{Theme, Themed};
+
+/// A transport reading tied to the index of the sample it was taken at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransportStamp {
+    pub transport: Transport,
+    pub sample_index: u64,
+}
+
+/// Creates the audio thread and GUI ends of a scope feed. `capacity` is the number of samples
+/// that can be queued between two editor frames, `history_len` the longest span in samples a
+/// scope can show.
+pub fn scope_channel(capacity: usize, history_len: usize) -> (ScopeSender, ScopeSource) {
+    let (samples, consumer) = spsc(capacity);
+    let (transport, reader) = triple_buffer(None);
+    let feed = ScopeFeed { samples: consumer, transport: reader, history: SampleHistory::new(history_len) };
+    (
+        ScopeSender { samples, transport, pushed: 0 },
+        ScopeSource { inner: GuiEnd::new(feed) },
+    )
+}
+
+/// Publishes samples from the audio thread without blocking or allocating. Samples that do
+/// not fit are dropped.
+pub struct ScopeSender {
+    samples: Producer<f32>,
+    transport: TripleWriter<Option<TransportStamp>>,
+    pushed: u64,
+}
+
+impl ScopeSender {
+    /// Pushes one block. `transport` is the host position at its first sample, from
+    /// `Transport::from_time_info`, and locks [`Timebase::Beats`] to the host's beat grid.
+    pub fn push_block(&mut self, samples: &[f32], transport: Option<Transport>) {
+        let sample_index = self.pushed;
+        self.transport.write(transport.map(|transport| TransportStamp { transport, sample_index }));
+        self.pushed += self.samples.push_slice(samples) as u64;
+    }
+}
+
+/// The history lives here rather than in the widgets, so its sample indices keep matching the
+/// sender's count when the editor closes and a new scope is made on the next open.
+struct ScopeFeed {
+    samples: Consumer<f32>,
+    transport: TripleReader<Option<TransportStamp>>,
+    history: SampleHistory,
+}
+
+impl ScopeFeed {
+    /// Moves all pending samples into the history and returns the latest transport stamp.
+    fn drain(&mut self) -> Option<TransportStamp> {
+        // read before draining, so every sample before the stamp is in the history
+        let stamp = *self.transport.read();
+        for s in self.samples.drain() {
+            self.history.push(s);
+        }
+        stamp
+    }
+}
+
+/// The GUI end of a scope feed, cloned into every new scope widget. Scopes sharing a source
+/// share its history.
+#[derive(Clone)]
+pub struct ScopeSource {
+    inner: GuiEnd<ScopeFeed>,
+}
+
+/// A ring of the most recent samples, addressed by absolute sample index.
+#[derive(Debug, Clone)]
+pub struct SampleHistory {
+    samples: Vec<f32>,
+    total: u64,
+}
+
+impl SampleHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self { samples: vec![0.0; capacity.max(1)], total: 0 }
+    }
+
+    pub fn push(&mut self, sample: f32) {
+        let len = self.samples.len();
+        self.samples[(self.total % len as u64) as usize] = sample;
+        self.total += 1;
+    }
+
+    /// Number of samples ever pushed, which is also the index of the next one.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Index of the oldest sample still held.
+    pub fn first(&self) -> u64 {
+        self.total.saturating_sub(self.samples.len() as u64)
+    }
+
+    pub fn get(&self, index: u64) -> Option<f32> {
+        if index < self.first() || index >= self.total {
+            return None;
+        }
+        Some(self.samples[(index % self.samples.len() as u64) as usize])
+    }
+
+    /// Copies `out.len()` samples starting at `start`, filling unknown samples with zero.
+    pub fn copy_range(&self, start: u64, out: &mut [f32]) {
+        for (i, o) in out.iter_mut().enumerate() {
+            *o = self.get(start + i as u64).unwrap_or(0.0);
+        }
+    }
+}
+
+/// Reduces `samples` to `columns` (min, max) pairs so any amount of audio draws in a
+/// fixed number of segments.
+pub fn decimate_min_max(samples: &[f32], columns: usize) -> Vec<(f32, f32)> {
+    if samples.is_empty() || columns == 0 {
+        return Vec::new();
+    }
+    (0..columns)
+        .map(|c| {
+            let start = c * samples.len() / columns;
+            let end = ((c + 1) * samples.len() / columns).max(start + 1).min(samples.len());
+            samples[start..end]
+                .iter()
+                .fold((f32::MAX, f32::MIN), |(lo, hi), s| (lo.min(*s), hi.max(*s)))
+        })
+        .collect()
+}
+
+/// Finds the most recent rising crossing of `level` whose following `len` samples are all in
+/// the history, searching at most `search` samples back. Returns the crossing's index.
+pub fn find_rising_edge(history: &SampleHistory, level: f32, len: u64, search: u64) -> Option<u64> {
+    let last_start = history.total().checked_sub(len)?;
+    let first = history.first().max(last_start.saturating_sub(search)) + 1;
+    let mut i = last_start;
+    while i >= first {
+        if let (Some(prev), Some(cur)) = (history.get(i - 1), history.get(i)) {
+            if prev < level && cur >= level {
+                return Some(i);
+            }
+        }
+        i -= 1;
+    }
+    None
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trigger {
+    /// Always shows the newest samples.
+    FreeRun,
+    /// Starts the display at the latest upward crossing of `level`, falling back to free run
+    /// if there is none.
+    RisingEdge { level: f32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Timebase {
+    /// A fixed number of samples across the display.
+    Samples(usize),
+    /// A number of beats across the display, locked to the host's beat grid while it is
+    /// playing. Falls back to `fallback` samples without transport info.
+    Beats { beats: f64, fallback: usize },
+}
+
+/// Picks the first sample index and length of the span of `history` to draw.
+fn span(history: &SampleHistory, timebase: Timebase, trigger: Trigger, stamp: Option<TransportStamp>) -> (u64, u64) {
+    let total = history.total();
+    let held = history.samples.len() as u64;
+    let len = match timebase {
+        Timebase::Samples(n) => n as u64,
+        Timebase::Beats { beats, fallback } => match stamp {
+            Some(s) if s.transport.playing && s.sample_index <= total => {
+                let spb = s.transport.samples_per_beat();
+                let len = (beats * spb) as u64;
+                // the last complete window on the host's grid
+                let now = s.transport.advanced((total - s.sample_index) as f64).ppq_pos;
+                let start_ppq = (now / beats).floor() * beats - beats;
+                let start = s.sample_index as f64 + (start_ppq - s.transport.ppq_pos) * spb;
+                if start >= 0.0 {
+                    return (start.round() as u64, len.min(held).max(1));
+                }
+                len
+            }
+            _ => fallback as u64,
+        },
+    };
+    let len = len.min(held).max(1);
+    let start = match trigger {
+        Trigger::FreeRun => None,
+        Trigger::RisingEdge { level } => find_rising_edge(history, level, len, len),
+    };
+    (start.unwrap_or_else(|| total.saturating_sub(len)), len)
+}
+
+/// An oscilloscope drawing the newest samples of a [`ScopeSource`].
+pub struct UIScope {
+    /// `[x, y, width, height]` in window pixels.
+    pub rect: [f32; 4],
+    pub trigger: Trigger,
+    pub timebase: Timebase,
+    /// Sample value drawn at the top edge, the bottom edge is its negative.
+    pub range: f32,
+    pub line_width: Themed<f32>,
+    pub color: Themed<[f32; 4]>,
+    pub background: Themed<[f32; 4]>,
+    pub grid: Themed<[f32; 4]>,
+    source: ScopeSource,
+    window: Vec<f32>,
+    next_window: Vec<f32>,
+    /// Bumped whenever the samples in `window` change.
+    revision: u64,
+    shapes: GlShapePipe,
+    batch: ShapeBatch,
+    dirty: DirtyCheck<([f32; 4], u64)>,
+}
+
+impl UIScope {
+    pub fn new(rect: [f32; 4], source: ScopeSource) -> Result<Self, Box<dyn std::error::Error>> {
+        let theme = Theme::default();
+        Ok(Self {
+            rect,
+            trigger: Trigger::FreeRun,
+            timebase: Timebase::Samples(2048),
+            range: 1.0,
+            line_width: Themed::new(theme.line_width),
+            color: Themed::new(theme.accent.normal),
+            background: Themed::new(theme.panel),
+            grid: Themed::new(theme.grid),
+            source,
+            window: Vec::new(),
+            next_window: Vec::new(),
+            revision: 0,
+            shapes: GlShapePipe::new((1, 1), &TMatrix::default())?,
+            batch: ShapeBatch::new(),
+            dirty: DirtyCheck::new(),
+        })
+    }
+
+    /// Builds the batch from `self.window`, the samples to show.
+    fn build(&mut self) {
+        let [x, y, w, h] = self.rect;
+        let mut batch = std::mem::take(&mut self.batch);
+        batch.clear();
+        batch.rect(self.rect, *self.background);
+        batch.rect([x, y + h / 2.0, w, 1.0], *self.grid);
+
+        let to_y = |s: f32| y + h / 2.0 - (s / self.range).clamp(-1.0, 1.0) * h / 2.0;
+
+        let columns = w.max(1.0) as usize;
+        if self.window.len() <= columns {
+            let step = w / (self.window.len().max(2) - 1) as f32;
+            let points: Vec<[f32; 2]> = self.window
+                .iter()
+                .enumerate()
+                .map(|(i, s)| [x + i as f32 * step, to_y(*s)])
+                .collect();
+            batch.polyline(&points, *self.line_width, *self.color);
+        } else {
+            let pairs = decimate_min_max(&self.window, columns);
+            let step = w / columns as f32;
+            let top: Vec<[f32; 2]> = pairs.iter().enumerate().map(|(i, p)| [x + (i as f32 + 0.5) * step, to_y(p.1)]).collect();
+            let bottom: Vec<[f32; 2]> = pairs.iter().enumerate().map(|(i, p)| [x + (i as f32 + 0.5) * step, to_y(p.0)]).collect();
+            batch.fill_between(&top, &bottom, *self.color);
+            batch.polyline(&top, *self.line_width, *self.color);
+            batch.polyline(&bottom, *self.line_width, *self.color);
+        }
+        self.batch = batch;
+    }
+}
+
+impl UIElement for UIScope {
+    fn render(&mut self, window_size: (i32, i32)) {
+        self.build();
+        self.shapes.update_geometry(window_size);
+        self.shapes.upload(&self.batch);
+        self.shapes.draw();
+        self.dirty.drawn((self.rect, self.revision));
+    }
+
+    fn animate(&mut self, _dt: f32) -> bool {
+        {
+            let mut feed = self.source.inner.lock();
+            let stamp = feed.drain();
+            let (start, len) = span(&feed.history, self.timebase, self.trigger, stamp);
+            self.next_window.resize(len as usize, 0.0);
+            feed.history.copy_range(start, &mut self.next_window);
+        }
+        // a silent or stopped feed keeps showing the same samples
+        if self.next_window != self.window {
+            std::mem::swap(&mut self.window, &mut self.next_window);
+            self.revision += 1;
+        }
+        false
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty.is_dirty(&(self.rect, self.revision))
+    }
+
+    fn get_window_transform() -> TMatrix {
+        TMatrix::default()
+    }
+
+    fn set_rect(&mut self, rect: [f32; 4]) {
+        self.rect = rect;
+    }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        self.color.set_theme(theme.accent.normal);
+        self.background.set_theme(theme.panel);
+        self.grid.set_theme(theme.grid);
+        self.line_width.set_theme(theme.line_width);
+        self.dirty.invalidate();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_wraps() {
+        let mut h = SampleHistory::new(4);
+        for i in 0..6 {
+            h.push(i as f32);
+        }
+        assert_eq!(6, h.total());
+        assert_eq!(2, h.first());
+        assert_eq!(None, h.get(1));
+        assert_eq!(Some(2.0), h.get(2));
+        assert_eq!(Some(5.0), h.get(5));
+        let mut out = [9.0; 3];
+        h.copy_range(4, &mut out);
+        assert_eq!([4.0, 5.0, 0.0], out);
+    }
+
+    #[test]
+    fn test_decimate_min_max() {
+        let samples = [0.0, 1.0, -1.0, 0.5, 0.25, -0.5];
+        assert_eq!(vec![(-1.0, 1.0), (-0.5, 0.5)], decimate_min_max(&samples, 2));
+        // more columns than samples repeats samples rather than leaving gaps
+        assert_eq!(4, decimate_min_max(&samples[..2], 4).len());
+        assert!(decimate_min_max(&[], 4).is_empty());
+    }
+
+    #[test]
+    fn test_find_rising_edge() {
+        let mut h = SampleHistory::new(64);
+        for s in [-1.0, 1.0, -1.0, -1.0, 1.0, 1.0, -1.0, -1.0] {
+            h.push(s);
+        }
+        // the crossing at 4 leaves only 4 samples after it
+        assert_eq!(Some(4), find_rising_edge(&h, 0.0, 4, 8));
+        assert_eq!(Some(1), find_rising_edge(&h, 0.0, 5, 8));
+        assert_eq!(None, find_rising_edge(&h, 2.0, 4, 8));
+        assert_eq!(None, find_rising_edge(&h, 0.0, 9, 8));
+    }
+
+    #[test]
+    fn test_sender_stamps_block_start() {
+        let (mut tx, rx) = scope_channel(4, 16);
+        let mut feed = rx.inner.lock();
+        assert!(feed.drain().is_none());
+        let t = Transport { sample_rate: 48000.0, tempo: 120.0, ppq_pos: 1.0, playing: true };
+        tx.push_block(&[0.0; 3], Some(t));
+        // only one of these fits, the rest are dropped
+        tx.push_block(&[1.0; 2], Some(t.advanced(3.0)));
+        let stamp = feed.drain().unwrap();
+        assert_eq!(TransportStamp { transport: t.advanced(3.0), sample_index: 3 }, stamp);
+        assert_eq!(4, feed.history.total());
+        assert_eq!(Some(1.0), feed.history.get(3));
+        tx.push_block(&[], None);
+        assert!(feed.drain().is_none());
+    }
+
+    #[test]
+    fn test_beats_line_up_with_samples() {
+        let (mut tx, rx) = scope_channel(8192, 48000);
+        // 120 bpm at 48 kHz is 24000 samples a beat, the block starts a quarter beat in
+        let t = Transport { sample_rate: 48000.0, tempo: 120.0, ppq_pos: 2.25, playing: true };
+        let timebase = Timebase::Beats { beats: 1.0, fallback: 100 };
+        let mut feed = rx.inner.lock();
+        let mut pushed = 0;
+        for _ in 0..10 {
+            tx.push_block(&[0.0; 4800], Some(t.advanced(pushed as f64)));
+            pushed += 4800;
+            let stamp = feed.drain();
+            let (start, len) = span(&feed.history, timebase, Trigger::FreeRun, stamp);
+            if pushed >= 24000 + 18000 {
+                // beat 3 starts 18000 samples in, the last complete beat is the one before now
+                let beat = ((2.25 + pushed as f64 / 24000.0).floor() - 1.0 - 2.25) * 24000.0;
+                assert_eq!((beat as u64, 24000), (start, len), "{}", pushed);
+            }
+        }
+    }
+
+    #[test]
+    fn test_beat_sync_survives_reopen() {
+        let (mut tx, rx) = scope_channel(1024, 48000);
+        let t = Transport { sample_rate: 48000.0, tempo: 120.0, ppq_pos: 0.0, playing: true };
+        let timebase = Timebase::Beats { beats: 1.0, fallback: 100 };
+        let mut pushed = 0;
+
+        // first open: a scope drains a second of audio
+        let first = rx.clone();
+        for _ in 0..48 {
+            tx.push_block(&[0.0; 1000], Some(t.advanced(pushed as f64)));
+            pushed += 1000;
+            first.inner.lock().drain();
+        }
+        drop(first);
+        // closed: the queue fills up and the rest is dropped
+        for _ in 0..4 {
+            tx.push_block(&[0.0; 1000], Some(t.advanced(pushed as f64)));
+            pushed += 1000;
+        }
+
+        // the scope made on reopen lines the beat grid up with the first new block
+        let reopened = rx.clone();
+        let mut feed = reopened.inner.lock();
+        feed.drain();
+        assert_eq!(48000 + 1024, feed.history.total());
+        tx.push_block(&[0.0; 1000], Some(t.advanced(pushed as f64)));
+        let stamp = feed.drain().unwrap();
+        assert_eq!(48000 + 1024, stamp.sample_index);
+        let (start, len) = span(&feed.history, timebase, Trigger::FreeRun, Some(stamp));
+        // the block starts 52000 samples into the song, beat 1 is at 24000
+        assert_eq!(24000, len);
+        assert_eq!(48000 + 1024 - (52000 - 24000), start);
+    }
+}
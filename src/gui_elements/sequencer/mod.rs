@@ -0,0 +1,290 @@
+use std::sync::Arc;
+use glfw::{Action, MouseButton, WindowEvent};
+use parking_lot::Mutex;
+use crate::gui_elements::shapes::{GlShapePipe, ShapeBatch};
+use crate::gui_elements::utils::dirty::DirtyCheck;
+use crate::gui_elements::utils::t_matrix::TMatrix;
+use crate::gui_elements::UIElement;
+use crate::pattern::Pattern;
+use crate::transport::TransportSource;
+use crate::gui_elements::theme::{Theme, Themed};
+
+/// The cell of a `rows` x `steps` grid filling `rect` that contains `pos`, as `(row, step)`.
+pub fn cell_at(rect: [f32; 4], rows: usize, steps: usize, pos: [f32; 2]) -> Option<(usize, usize)> {
+    let [x, y, w, h] = rect;
+    let (fx, fy) = ((pos[0] - x) / w, (pos[1] - y) / h);
+    if !(0.0..1.0).contains(&fx) || !(0.0..1.0).contains(&fy) {
+        return None;
+    }
+    Some(((fy * rows as f32) as usize, (fx * steps as f32) as usize))
+}
+
+/// What dragging over the grid changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditMode {
+    /// Click toggles a step, dragging paints the same state onto the steps passed over.
+    Toggle,
+    /// The height of the click inside a cell sets the value.
+    Velocity,
+    Probability,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SequencerColors {
+    pub background: [f32; 4],
+    pub cell: [f32; 4],
+    /// Cells at the start of every beat group.
+    pub cell_accent: [f32; 4],
+    pub on: [f32; 4],
+    pub value: [f32; 4],
+    pub playhead: [f32; 4],
+}
+
+impl SequencerColors {
+    pub fn from_theme(theme: &Theme) -> Self {
+        Self {
+            background: theme.panel,
+            cell: theme.surface,
+            cell_accent: theme.surface_accent,
+            on: theme.accent.normal,
+            value: theme.secondary.normal,
+            playhead: theme.playhead,
+        }
+    }
+}
+
+impl Default for SequencerColors {
+    fn default() -> Self {
+        Self::from_theme(&Theme::default())
+    }
+}
+
+/// See [`UIStepSequencer::on_change`].
+pub type PatternChangeHandler = Box<dyn FnMut(&Pattern)>;
+
+/// A step sequencer grid editing a [`Pattern`] shared with the plugin. Right-click dragging
+/// clears steps in every mode.
+pub struct UIStepSequencer {
+    /// `[x, y, width, height]` in window pixels.
+    pub rect: [f32; 4],
+    pub mode: EditMode,
+    /// Steps per accented group, e.g. 4 to mark every beat of sixteenths.
+    pub group: usize,
+    /// Gap between cells in pixels.
+    pub gap: f32,
+    pub corner_radius: Themed<f32>,
+    pub colors: Themed<SequencerColors>,
+    pattern: Arc<Mutex<Pattern>>,
+    transport: Option<TransportSource>,
+    on_change: Option<PatternChangeHandler>,
+    cursor: (f64, f64),
+    /// The state being painted while a button is held.
+    paint: Option<bool>,
+    /// Rect, pattern and playhead step last drawn.
+    dirty: DirtyCheck<([f32; 4], Pattern, Option<usize>)>,
+    shapes: GlShapePipe,
+    batch: ShapeBatch,
+}
+
+impl UIStepSequencer {
+    pub fn new(rect: [f32; 4], pattern: Arc<Mutex<Pattern>>) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            rect,
+            mode: EditMode::Toggle,
+            group: 4,
+            gap: 2.0,
+            corner_radius: Themed::new(Theme::default().corner_radius),
+            colors: Themed::new(SequencerColors::default()),
+            pattern,
+            transport: None,
+            on_change: None,
+            cursor: (0.0, 0.0),
+            paint: None,
+            dirty: DirtyCheck::new(),
+            shapes: GlShapePipe::new((1, 1), &TMatrix::default())?,
+            batch: ShapeBatch::new(),
+        })
+    }
+
+    /// Moves a playhead along with the host while it plays.
+    pub fn set_transport(&mut self, transport: Option<TransportSource>) {
+        self.transport = transport;
+    }
+
+    /// Called after every edit.
+    pub fn on_change(&mut self, f: impl FnMut(&Pattern) + 'static) {
+        self.on_change = Some(Box::new(f));
+    }
+
+    /// The step under the playhead, none while the host is stopped.
+    fn playhead_step(&self, pattern: &Pattern) -> Option<usize> {
+        self.transport.as_ref().and_then(|t| t.now()).filter(|t| t.playing).map(|t| pattern.step_at(&t))
+    }
+
+    fn cell_rect(&self, rows: usize, steps: usize, row: usize, step: usize) -> [f32; 4] {
+        let [x, y, w, h] = self.rect;
+        let (cw, ch) = (w / steps as f32, h / rows as f32);
+        let half = self.gap / 2.0;
+        [x + step as f32 * cw + half, y + row as f32 * ch + half, cw - self.gap, ch - self.gap]
+    }
+
+    /// Applies the current paint to the cell under the cursor. `start` decides the paint state
+    /// in toggle mode.
+    fn paint_at_cursor(&mut self, start: bool) {
+        let pos = [self.cursor.0 as f32, self.cursor.1 as f32];
+        let mut pattern = self.pattern.lock();
+        let (rows, steps) = (pattern.rows(), pattern.steps());
+        let (row, step) = match cell_at(self.rect, rows, steps, pos) {
+            Some(cell) => cell,
+            None => return,
+        };
+        let [_, cy, _, ch] = self.cell_rect(rows, steps, row, step);
+        let level = (1.0 - (pos[1] - cy) / ch).clamp(0.0, 1.0);
+        let cell = pattern.get_mut(row, step);
+        let before = *cell;
+        match (self.paint, self.mode) {
+            (Some(false), _) => cell.on = false,
+            (Some(true), EditMode::Toggle) => cell.on = true,
+            (None, EditMode::Toggle) if start => {
+                cell.on = !cell.on;
+                self.paint = Some(cell.on);
+            }
+            (_, EditMode::Velocity) => {
+                cell.on = true;
+                cell.velocity = level;
+                self.paint = Some(true);
+            }
+            (_, EditMode::Probability) => {
+                cell.on = true;
+                cell.probability = level;
+                self.paint = Some(true);
+            }
+            _ => {}
+        }
+        let changed = *cell != before;
+        drop(pattern);
+        if changed {
+            if let Some(f) = self.on_change.as_mut() {
+                f(&self.pattern.lock());
+            }
+        }
+    }
+
+    fn build(&mut self) {
+        let colors = self.colors;
+        let pattern = self.pattern.lock().clone();
+        let (rows, steps) = (pattern.rows(), pattern.steps());
+        let mut batch = std::mem::take(&mut self.batch);
+        batch.clear();
+        batch.rect(self.rect, colors.background);
+
+        for row in 0..rows {
+            for step in 0..steps {
+                let r = self.cell_rect(rows, steps, row, step);
+                let accent = self.group > 0 && step % self.group == 0;
+                batch.rounded_rect(r, *self.corner_radius, if accent { colors.cell_accent } else { colors.cell });
+                let s = pattern.get(row, step);
+                if !s.on {
+                    continue;
+                }
+                match self.mode {
+                    EditMode::Toggle => {
+                        let c = colors.on;
+                        batch.rounded_rect(r, *self.corner_radius, [c[0], c[1], c[2], c[3] * (0.35 + 0.65 * s.velocity)]);
+                        if s.probability < 1.0 {
+                            let bar = [r[0], r[1] + r[3] - 2.0, r[2] * s.probability, 2.0];
+                            batch.rect(bar, colors.value);
+                        }
+                    }
+                    EditMode::Velocity | EditMode::Probability => {
+                        let v = if self.mode == EditMode::Velocity { s.velocity } else { s.probability };
+                        batch.rect([r[0], r[1] + r[3] * (1.0 - v), r[2], r[3] * v], colors.value);
+                    }
+                }
+            }
+        }
+
+        let playhead = self.playhead_step(&pattern);
+        if let Some(step) = playhead {
+            let [x, y, w, h] = self.rect;
+            let cw = w / steps as f32;
+            batch.rect([x + step as f32 * cw, y, cw, h], colors.playhead);
+        }
+        self.batch = batch;
+        self.dirty.drawn((self.rect, pattern, playhead));
+    }
+}
+
+impl UIElement for UIStepSequencer {
+    fn render(&mut self, window_size: (i32, i32)) {
+        self.build();
+        self.shapes.update_geometry(window_size);
+        self.shapes.upload(&self.batch);
+        self.shapes.draw();
+    }
+
+    fn get_window_transform() -> TMatrix {
+        TMatrix::default()
+    }
+
+    fn set_rect(&mut self, rect: [f32; 4]) {
+        self.rect = rect;
+    }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        self.colors.set_theme(SequencerColors::from_theme(theme));
+        self.corner_radius.set_theme(theme.corner_radius);
+        self.dirty.invalidate();
+    }
+
+    fn is_dirty(&self) -> bool {
+        let pattern = self.pattern.lock();
+        let playhead = self.playhead_step(&pattern);
+        self.dirty.is_dirty(&(self.rect, pattern.clone(), playhead))
+    }
+
+    fn handle_event(&mut self, event: &WindowEvent) -> bool {
+        let over = |s: &Self| {
+            let pattern = s.pattern.lock();
+            cell_at(s.rect, pattern.rows(), pattern.steps(), [s.cursor.0 as f32, s.cursor.1 as f32]).is_some()
+        };
+        match event {
+            WindowEvent::CursorPos(x, y) => {
+                self.cursor = (*x, *y);
+                if self.paint.is_some() {
+                    self.paint_at_cursor(false);
+                    true
+                } else {
+                    false
+                }
+            }
+            WindowEvent::MouseButton(button @ (MouseButton::Button1 | MouseButton::Button2), Action::Press, _) => {
+                if !over(self) {
+                    return false;
+                }
+                self.paint = if *button == MouseButton::Button2 { Some(false) } else { None };
+                self.paint_at_cursor(true);
+                true
+            }
+            WindowEvent::MouseButton(MouseButton::Button1 | MouseButton::Button2, Action::Release, _) => {
+                self.paint.take().is_some()
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell_at() {
+        let rect = [10.0, 20.0, 160.0, 40.0];
+        assert_eq!(Some((0, 0)), cell_at(rect, 2, 16, [10.0, 20.0]));
+        assert_eq!(Some((1, 15)), cell_at(rect, 2, 16, [169.0, 59.0]));
+        assert_eq!(Some((1, 3)), cell_at(rect, 2, 16, [45.0, 45.0]));
+        assert_eq!(None, cell_at(rect, 2, 16, [170.0, 30.0]));
+        assert_eq!(None, cell_at(rect, 2, 16, [50.0, 19.0]));
+    }
+}
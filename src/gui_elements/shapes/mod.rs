@@ -0,0 +1,267 @@
+use gl::types::GLfloat;
+use crate::gui_elements::color::Gradient;
+
+mod shape_pipe;
+
+pub use shape_pipe::GlShapePipe;
+
+/// `[x, y, r, g, b, a]`
+pub type ShapeVertex = [GLfloat; 6];
+
+/// Largest cell side [`ShapeBatch::rect_fill`] splits a gradient fill into.
+pub const GRADIENT_CELL: f32 = 8.0;
+
+/// A list of colored triangles in window pixel coordinates (origin top left), built up on the
+/// CPU each frame and handed to a [`GlShapePipe`] for drawing.
+#[derive(Debug, Default, Clone)]
+pub struct ShapeBatch {
+    vertices: Vec<ShapeVertex>,
+}
+
+impl ShapeBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Removes all shapes but keeps the allocation.
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vertices.is_empty()
+    }
+
+    pub fn vertices(&self) -> &[ShapeVertex] {
+        &self.vertices
+    }
+
+    fn vertex(&mut self, p: [f32; 2], c: [f32; 4]) {
+        self.vertices.push([p[0], p[1], c[0], c[1], c[2], c[3]]);
+    }
+
+    pub fn triangle(&mut self, a: [f32; 2], b: [f32; 2], c: [f32; 2], color: [f32; 4]) {
+        self.vertex(a, color);
+        self.vertex(b, color);
+        self.vertex(c, color);
+    }
+
+    /// Adds a quad with a color per corner, given in the order top left, top right,
+    /// bottom right, bottom left.
+    pub fn quad(&mut self, p: [[f32; 2]; 4], c: [[f32; 4]; 4]) {
+        self.vertex(p[0], c[0]);
+        self.vertex(p[1], c[1]);
+        self.vertex(p[2], c[2]);
+        self.vertex(p[0], c[0]);
+        self.vertex(p[2], c[2]);
+        self.vertex(p[3], c[3]);
+    }
+
+    /// Fills `rect` (`[x, y, width, height]`) with a solid color.
+    pub fn rect(&mut self, rect: [f32; 4], color: [f32; 4]) {
+        self.rect_gradient(rect, color, color);
+    }
+
+    /// Fills `rect` with a vertical gradient from `top` to `bottom`.
+    pub fn rect_gradient(&mut self, rect: [f32; 4], top: [f32; 4], bottom: [f32; 4]) {
+        let [x, y, w, h] = rect;
+        self.quad(
+            [[x, y], [x + w, y], [x + w, y + h], [x, y + h]],
+            [top, top, bottom, bottom],
+        );
+    }
+
+    /// Fills `rect` with `gradient`. Vertex colors are interpolated linearly, so the rect is
+    /// split into cells of at most [`GRADIENT_CELL`] pixels to follow stops and radial falloff.
+    pub fn rect_fill(&mut self, rect: [f32; 4], gradient: &Gradient) {
+        let [x, y, w, h] = rect;
+        let cells = |len: f32| ((len / GRADIENT_CELL).ceil() as usize).clamp(1, 64);
+        let (nx, ny) = (cells(w), cells(h));
+        let at = |i: usize, j: usize| [x + w * i as f32 / nx as f32, y + h * j as f32 / ny as f32];
+        let color = |p: [f32; 2]| gradient.color_at(p).to_array();
+        for j in 0..ny {
+            for i in 0..nx {
+                let p = [at(i, j), at(i + 1, j), at(i + 1, j + 1), at(i, j + 1)];
+                self.quad(p, [color(p[0]), color(p[1]), color(p[2]), color(p[3])]);
+            }
+        }
+    }
+
+    /// Draws the outline of `rect` with the stroke inside the rect.
+    pub fn rect_outline(&mut self, rect: [f32; 4], width: f32, color: [f32; 4]) {
+        let [x, y, w, h] = rect;
+        let width = width.min(w / 2.0).min(h / 2.0);
+        self.rect([x, y, w, width], color);
+        self.rect([x, y + h - width, w, width], color);
+        self.rect([x, y + width, width, h - width * 2.0], color);
+        self.rect([x + w - width, y + width, width, h - width * 2.0], color);
+    }
+
+    /// Fills `rect` with its corners rounded to `radius`, at most half the shorter side.
+    pub fn rounded_rect(&mut self, rect: [f32; 4], radius: f32, color: [f32; 4]) {
+        let [x, y, w, h] = rect;
+        let r = radius.min(w / 2.0).min(h / 2.0);
+        if r < 0.5 {
+            self.rect(rect, color);
+            return;
+        }
+        let steps = ((r / 2.0) as usize).clamp(2, 8);
+        // clockwise from the top right corner, in window coordinates
+        let centers = [[x + w - r, y + r], [x + w - r, y + h - r], [x + r, y + h - r], [x + r, y + r]];
+        let mut outline = Vec::with_capacity(centers.len() * (steps + 1));
+        for (corner, c) in centers.iter().enumerate() {
+            for i in 0..=steps {
+                let a = (corner as f32 - 1.0 + i as f32 / steps as f32) * std::f32::consts::FRAC_PI_2;
+                outline.push([c[0] + a.cos() * r, c[1] + a.sin() * r]);
+            }
+        }
+        let center = [x + w / 2.0, y + h / 2.0];
+        for i in 0..outline.len() {
+            self.triangle(center, outline[i], outline[(i + 1) % outline.len()], color);
+        }
+    }
+
+    /// Draws a straight line of the given width with square ends.
+    pub fn line(&mut self, a: [f32; 2], b: [f32; 2], width: f32, color: [f32; 4]) {
+        let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+        let len = (dx * dx + dy * dy).sqrt();
+        if len == 0.0 {
+            return;
+        }
+        let (nx, ny) = (-dy / len * width / 2.0, dx / len * width / 2.0);
+        self.quad(
+            [[a[0] + nx, a[1] + ny], [b[0] + nx, b[1] + ny], [b[0] - nx, b[1] - ny], [a[0] - nx, a[1] - ny]],
+            [color; 4],
+        );
+    }
+
+    /// Draws an anti-aliased polyline through `points`. Joints are mitered and the edges fade
+    /// out over one pixel. Lines thinner than a pixel are drawn one pixel wide and fainter.
+    pub fn polyline(&mut self, points: &[[f32; 2]], width: f32, color: [f32; 4]) {
+        if points.len() < 2 {
+            return;
+        }
+        let mut color = color;
+        if width < 1.0 {
+            color[3] *= width.max(0.0);
+        }
+        let half = (width.max(1.0) - 1.0) / 2.0;
+        let clear = [color[0], color[1], color[2], 0.0];
+
+        let normals = miter_normals(points);
+        for i in 0..points.len() - 1 {
+            let (a, b) = (points[i], points[i + 1]);
+            let (na, nb) = (normals[i], normals[i + 1]);
+            let off = |p: [f32; 2], n: [f32; 2], d: f32| [p[0] + n[0] * d, p[1] + n[1] * d];
+            // solid core, then a fringe on either side fading to transparent
+            if half > 0.0 {
+                self.quad(
+                    [off(a, na, half), off(b, nb, half), off(b, nb, -half), off(a, na, -half)],
+                    [color; 4],
+                );
+            }
+            self.quad(
+                [off(a, na, half + 1.0), off(b, nb, half + 1.0), off(b, nb, half), off(a, na, half)],
+                [clear, clear, color, color],
+            );
+            self.quad(
+                [off(a, na, -half), off(b, nb, -half), off(b, nb, -half - 1.0), off(a, na, -half - 1.0)],
+                [color, color, clear, clear],
+            );
+        }
+    }
+
+    /// A filled circle with a 1px anti-aliased edge.
+    pub fn circle(&mut self, center: [f32; 2], radius: f32, color: [f32; 4]) {
+        let clear = [color[0], color[1], color[2], 0.0];
+        let segments = ((radius * 2.0) as usize).clamp(12, 64);
+        let at = |i: usize, r: f32| {
+            let a = i as f32 / segments as f32 * std::f32::consts::TAU;
+            [center[0] + a.cos() * r, center[1] + a.sin() * r]
+        };
+        for i in 0..segments {
+            self.triangle(center, at(i, radius), at(i + 1, radius), color);
+            self.quad(
+                [at(i, radius), at(i + 1, radius), at(i + 1, radius + 1.0), at(i, radius + 1.0)],
+                [color, color, clear, clear],
+            );
+        }
+    }
+
+    /// Fills the area between two polylines with matching x positions, e.g. a curve and a
+    /// baseline. Both slices must have the same length.
+    pub fn fill_between(&mut self, top: &[[f32; 2]], bottom: &[[f32; 2]], color: [f32; 4]) {
+        self.fill_between_gradient(top, bottom, color, color);
+    }
+
+    /// Like [`ShapeBatch::fill_between`] but fades from `top_color` along `top` to
+    /// `bottom_color` along `bottom`.
+    pub fn fill_between_gradient(&mut self, top: &[[f32; 2]], bottom: &[[f32; 2]], top_color: [f32; 4], bottom_color: [f32; 4]) {
+        for i in 0..top.len().min(bottom.len()).saturating_sub(1) {
+            self.quad(
+                [top[i], top[i + 1], bottom[i + 1], bottom[i]],
+                [top_color, top_color, bottom_color, bottom_color],
+            );
+        }
+    }
+}
+
+/// Unit normals at every point of a polyline, lengthened at joints so the offset edges stay
+/// parallel to each segment. Very sharp joints are clamped to avoid spikes.
+fn miter_normals(points: &[[f32; 2]]) -> Vec<[f32; 2]> {
+    let segment_normal = |a: [f32; 2], b: [f32; 2]| {
+        let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+        let len = (dx * dx + dy * dy).sqrt();
+        if len == 0.0 { [0.0, 0.0] } else { [-dy / len, dx / len] }
+    };
+    let n = points.len();
+    (0..n)
+        .map(|i| {
+            let prev = if i > 0 { segment_normal(points[i - 1], points[i]) } else { [0.0, 0.0] };
+            let next = if i + 1 < n { segment_normal(points[i], points[i + 1]) } else { [0.0, 0.0] };
+            let sum = [prev[0] + next[0], prev[1] + next[1]];
+            let len = (sum[0] * sum[0] + sum[1] * sum[1]).sqrt();
+            if len == 0.0 {
+                return if i > 0 { prev } else { next };
+            }
+            let m = [sum[0] / len, sum[1] / len];
+            let reference = if i > 0 { prev } else { next };
+            let cos = (m[0] * reference[0] + m[1] * reference[1]).max(0.5);
+            [m[0] / cos, m[1] / cos]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_miter_normals_straight_line() {
+        let n = miter_normals(&[[0.0, 0.0], [1.0, 0.0], [2.0, 0.0]]);
+        assert_eq!(vec![[0.0, 1.0], [0.0, 1.0], [0.0, 1.0]], n);
+    }
+
+    #[test]
+    fn test_miter_normals_right_angle() {
+        let n = miter_normals(&[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0]]);
+        // the corner normal bisects the two segment normals and is sqrt(2) long
+        assert!((n[1][0] + 1.0).abs() < 1e-6 && (n[1][1] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_vertex_counts() {
+        let mut b = ShapeBatch::new();
+        b.rect([0.0, 0.0, 1.0, 1.0], [1.0; 4]);
+        assert_eq!(6, b.vertices().len());
+        b.clear();
+        b.polyline(&[[0.0, 0.0], [1.0, 0.0], [2.0, 1.0]], 3.0, [1.0; 4]);
+        assert_eq!(2 * 3 * 6, b.vertices().len());
+        b.clear();
+        b.fill_between(&[[0.0, 0.0], [1.0, 0.0]], &[[0.0, 1.0], [1.0, 1.0]], [1.0; 4]);
+        assert_eq!(6, b.vertices().len());
+        b.clear();
+        b.rect_fill([0.0, 0.0, 20.0, 8.0], &Gradient::linear([0.0, 0.0], [20.0, 0.0], Vec::new()));
+        assert_eq!(3 * 6, b.vertices().len());
+    }
+}
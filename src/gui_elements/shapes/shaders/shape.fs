@@ -0,0 +1,9 @@
+#version 150
+
+in vec4 f_color;
+
+out vec4 out_color;
+
+void main() {
+    out_color = f_color;
+}
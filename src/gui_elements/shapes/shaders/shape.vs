@@ -0,0 +1,22 @@
+#version 150
+
+const mat4 INVERT_Y_AXIS = mat4(
+    vec4(1.0, 0.0, 0.0, 0.0),
+    vec4(0.0, -1.0, 0.0, 0.0),
+    vec4(0.0, 0.0, 1.0, 0.0),
+    vec4(0.0, 0.0, 0.0, 1.0)
+);
+
+uniform mat4 proj;
+uniform mat3 window_transform;
+
+in vec2 pos;
+in vec4 color;
+
+out vec4 f_color;
+
+void main() {
+    vec3 p = window_transform * vec3(pos, 1.0);
+    f_color = color;
+    gl_Position = INVERT_Y_AXIS * proj * vec4(p.xy, 0.0, 1.0);
+}
@@ -0,0 +1,158 @@
+use gl::types::*;
+use std::{ffi::CString, mem};
+use crate::gui_elements::utils::gl::{compile_shader, link_program, gl_err_to_str, GlObject};
+use crate::gui_elements::text::text_utils::{ortho, Res};
+use crate::gui_elements::utils::t_matrix::TMatrix;
+use crate::gl_log_error;
+use super::{ShapeBatch, ShapeVertex};
+
+/// Draws the triangles of a [`ShapeBatch`] in window pixel coordinates.
+pub struct GlShapePipe {
+    shaders: [GLuint; 2],
+    program: GLuint,
+    vao: GLuint,
+    vbo: GLuint,
+    proj_uniform: GLint,
+    window_transform_uniform: GLint,
+    vertex_count: usize,
+    vertex_buffer_len: usize,
+}
+
+impl GlShapePipe {
+    pub fn new(window_size: (i32, i32), window_transform: &TMatrix) -> Res<Self> {
+        let (w, h) = (window_size.0 as f32, window_size.1 as f32);
+
+        let vs = compile_shader(include_str!("shaders/shape.vs"), gl::VERTEX_SHADER)?;
+        let fs = compile_shader(include_str!("shaders/shape.fs"), gl::FRAGMENT_SHADER)?;
+        let program = link_program(vs, fs)?;
+
+        let vao;
+        let vbo;
+        let window_transform_uniform;
+        let proj_uniform = unsafe {
+            vao = GlObject::VertexArray.gen();
+            gl::BindVertexArray(vao);
+
+            vbo = GlObject::Buffer.gen();
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+
+            gl::UseProgram(program);
+            gl::BindFragDataLocation(program, 0, CString::new("out_color")?.as_ptr());
+
+            window_transform_uniform = gl::GetUniformLocation(program, CString::new("window_transform")?.as_ptr());
+            if window_transform_uniform < 0 {
+                return Err(format!("GetUniformLocation(\"window_transform\") -> {}", window_transform_uniform).into());
+            }
+            gl::UniformMatrix3fv(window_transform_uniform, 1, 0, window_transform.as_ptr());
+
+            let uniform = gl::GetUniformLocation(program, CString::new("proj")?.as_ptr());
+            if uniform < 0 {
+                return Err(format!("GetUniformLocation(\"proj\") -> {}", uniform).into());
+            }
+            let transform = ortho(0.0, w, 0.0, h, 1.0, -1.0);
+            gl::UniformMatrix4fv(uniform, 1, 0, transform.as_ptr());
+
+            let mut offset = 0;
+            for (v_field, float_count) in &[("pos", 2), ("color", 4)] {
+                let attr = gl::GetAttribLocation(program, CString::new(*v_field)?.as_ptr());
+                if attr < 0 {
+                    return Err(format!("{} GetAttribLocation -> {}", v_field, attr).into());
+                }
+                gl::VertexAttribPointer(
+                    attr as _,
+                    *float_count,
+                    gl::FLOAT,
+                    gl::FALSE as _,
+                    mem::size_of::<ShapeVertex>() as _,
+                    offset as _,
+                );
+                gl::EnableVertexAttribArray(attr as _);
+
+                offset += float_count * 4;
+            }
+            gl_log_error!();
+            uniform
+        };
+
+        Ok(Self {
+            shaders: [vs, fs],
+            program,
+            vao,
+            vbo,
+            proj_uniform,
+            window_transform_uniform,
+            vertex_count: 0,
+            vertex_buffer_len: 0,
+        })
+    }
+
+    pub fn upload(&mut self, batch: &ShapeBatch) {
+        let vertices = batch.vertices();
+        self.vertex_count = vertices.len();
+        if vertices.is_empty() {
+            return;
+        }
+
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            if self.vertex_buffer_len < self.vertex_count {
+                gl::BufferData(
+                    gl::ARRAY_BUFFER,
+                    (self.vertex_count * mem::size_of::<ShapeVertex>()) as GLsizeiptr,
+                    vertices.as_ptr() as _,
+                    gl::DYNAMIC_DRAW,
+                );
+                self.vertex_buffer_len = self.vertex_count;
+            } else {
+                gl::BufferSubData(
+                    gl::ARRAY_BUFFER,
+                    0,
+                    (self.vertex_count * mem::size_of::<ShapeVertex>()) as GLsizeiptr,
+                    vertices.as_ptr() as _,
+                );
+            }
+            gl_log_error!();
+        }
+    }
+
+    pub fn update_geometry(&self, window_size: (i32, i32)) {
+        let (w, h) = (window_size.0 as f32, window_size.1 as f32);
+        let transform = ortho(0.0, w, 0.0, h, 1.0, -1.0);
+
+        unsafe {
+            gl::UseProgram(self.program);
+            gl::UniformMatrix4fv(self.proj_uniform, 1, 0, transform.as_ptr());
+            gl_log_error!();
+        }
+    }
+
+    pub fn update_window_transform(&self, t: &TMatrix) {
+        unsafe {
+            gl::UseProgram(self.program);
+            gl::UniformMatrix3fv(self.window_transform_uniform, 1, 0, t.as_ptr());
+            gl_log_error!();
+        }
+    }
+
+    pub fn draw(&self) {
+        if self.vertex_count == 0 {
+            return;
+        }
+        unsafe {
+            gl::UseProgram(self.program);
+            gl::BindVertexArray(self.vao);
+            gl::DrawArrays(gl::TRIANGLES, 0, self.vertex_count as _);
+            gl_log_error!();
+        }
+    }
+}
+
+impl Drop for GlShapePipe {
+    fn drop(&mut self) {
+        GlObject::Program.delete(self.program);
+        self.shaders.iter().for_each(|&s| GlObject::Shader.delete(s));
+        GlObject::Buffer.delete(self.vbo);
+        GlObject::VertexArray.delete(self.vao);
+    }
+}
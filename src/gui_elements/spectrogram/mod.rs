@@ -0,0 +1,376 @@
+use gl::types::*;
+use std::{ffi::CString, mem};
+use crate::channel::SampleSource;
+use crate::dsp::spectrum::SpectrumAnalyzer;
+use crate::gui_elements::analyzer::spectrum_to_columns;
+use crate::gui_elements::text::text_utils::{ortho, Res};
+use crate::gui_elements::utils::dirty::DirtyCheck;
+use crate::gui_elements::utils::gl::{compile_shader, link_program, gl_err_to_str, GlObject};
+use crate::gui_elements::utils::t_matrix::TMatrix;
+use crate::gui_elements::utils::texture::{GlTexture, TextureFormat};
+use crate::gui_elements::UIElement;
+use crate::gui_elements::theme::{Theme, Themed};
+use crate::gl_log_error;
+
+const LUT_SIZE: usize = 256;
+
+/// Maps a normalized level to a color.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColorMap {
+    Grayscale,
+    /// Black through red and yellow to white.
+    Heat,
+    /// Black through blue to white.
+    Ice,
+    /// Dark purple through pink to pale yellow.
+    Magma,
+    /// Color stops as `(position, rgba)` with positions from 0 to 1 in ascending order.
+    Custom(Vec<(f32, [f32; 4])>),
+}
+
+impl ColorMap {
+    /// Runs from the theme's panel color through the secondary and accent colors to the text
+    /// color, so quiet bins blend into the panel.
+    pub fn from_theme(theme: &Theme) -> Self {
+        ColorMap::Custom(vec![
+            (0.0, theme.panel),
+            (0.4, theme.secondary.normal),
+            (0.75, theme.accent.normal),
+            (1.0, theme.text),
+        ])
+    }
+
+    fn stops(&self) -> Vec<(f32, [f32; 4])> {
+        match self {
+            ColorMap::Grayscale => vec![(0.0, [0.0, 0.0, 0.0, 1.0]), (1.0, [1.0, 1.0, 1.0, 1.0])],
+            ColorMap::Heat => vec![
+                (0.0, [0.0, 0.0, 0.0, 1.0]),
+                (0.35, [0.5, 0.0, 0.0, 1.0]),
+                (0.6, [1.0, 0.3, 0.0, 1.0]),
+                (0.85, [1.0, 0.9, 0.1, 1.0]),
+                (1.0, [1.0, 1.0, 1.0, 1.0]),
+            ],
+            ColorMap::Ice => vec![
+                (0.0, [0.0, 0.0, 0.0, 1.0]),
+                (0.4, [0.0, 0.2, 0.6, 1.0]),
+                (0.75, [0.2, 0.7, 1.0, 1.0]),
+                (1.0, [1.0, 1.0, 1.0, 1.0]),
+            ],
+            ColorMap::Magma => vec![
+                (0.0, [0.0, 0.0, 0.02, 1.0]),
+                (0.25, [0.23, 0.06, 0.44, 1.0]),
+                (0.5, [0.72, 0.21, 0.47, 1.0]),
+                (0.75, [0.99, 0.53, 0.38, 1.0]),
+                (1.0, [0.99, 0.99, 0.75, 1.0]),
+            ],
+            ColorMap::Custom(stops) => stops.clone(),
+        }
+    }
+
+    /// The color at `t`, clamped to the first and last stop.
+    pub fn sample(&self, t: f32) -> [f32; 4] {
+        let stops = self.stops();
+        let (first, last) = match (stops.first(), stops.last()) {
+            (Some(f), Some(l)) => (f, l),
+            _ => return [0.0; 4],
+        };
+        if t <= first.0 {
+            return first.1;
+        }
+        for pair in stops.windows(2) {
+            let ((p0, c0), (p1, c1)) = (pair[0], pair[1]);
+            if t <= p1 {
+                let f = if p1 > p0 { (t - p0) / (p1 - p0) } else { 1.0 };
+                let mut c = [0.0; 4];
+                for i in 0..4 {
+                    c[i] = c0[i] + (c1[i] - c0[i]) * f;
+                }
+                return c;
+            }
+        }
+        last.1
+    }
+
+    /// A lookup table of `n` RGBA8 entries covering 0 to 1.
+    pub fn lut(&self, n: usize) -> Vec<u8> {
+        (0..n)
+            .flat_map(|i| {
+                let c = self.sample(i as f32 / (n.max(2) - 1) as f32);
+                c.map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8)
+            })
+            .collect()
+    }
+}
+
+/// A scrolling spectrogram. Every FFT frame becomes one column of a circular texture, the
+/// display scrolls by moving a texture offset so old columns are never uploaded again.
+///
+/// The texture resolution is taken from the size of `rect` at creation. The picture is all
+/// color map, which follows the theme (see [`ColorMap::from_theme`]) until one is picked with
+/// [`Self::set_color_map`].
+pub struct UISpectrogram {
+    /// `[x, y, width, height]` in window pixels.
+    pub rect: [f32; 4],
+    pub sample_rate: f32,
+    pub freq_range: (f32, f32),
+    /// Levels mapped to the ends of the color map, in dB.
+    pub db_range: (f32, f32),
+    /// Frame size, overlap and averaging live here. Averaging defaults to off.
+    pub analyzer: SpectrumAnalyzer,
+    color_map: Themed<ColorMap>,
+    source: SampleSource<f32>,
+    pending: Vec<f32>,
+    /// Finished columns waiting for upload, one texture row after another.
+    queued: Vec<u8>,
+    /// Silent columns written in a row. Once they fill the texture nothing changes anymore.
+    blank_run: u32,
+    write_pos: u32,
+    levels: GlTexture,
+    lut: GlTexture,
+    shaders: [GLuint; 2],
+    program: GLuint,
+    vao: GLuint,
+    vbo: GLuint,
+    proj_uniform: GLint,
+    offset_uniform: GLint,
+    dirty: DirtyCheck<[f32; 4]>,
+}
+
+impl UISpectrogram {
+    pub fn new(rect: [f32; 4], source: SampleSource<f32>, fft_size: usize) -> Res<Self> {
+        let (columns, rows) = (rect[2].max(1.0) as u32, rect[3].max(1.0) as u32);
+        let levels = GlTexture::new((columns, rows), TextureFormat::Red, Some(&vec![0; (columns * rows) as usize]));
+        levels.set_wrap(gl::REPEAT, gl::CLAMP_TO_EDGE);
+        let color_map = Themed::new(ColorMap::from_theme(&Theme::default()));
+        let lut = GlTexture::new((LUT_SIZE as u32, 1), TextureFormat::Rgba, Some(&color_map.lut(LUT_SIZE)));
+
+        let vs = compile_shader(include_str!("shaders/spectrogram.vs"), gl::VERTEX_SHADER)?;
+        let fs = compile_shader(include_str!("shaders/spectrogram.fs"), gl::FRAGMENT_SHADER)?;
+        let program = link_program(vs, fs)?;
+
+        let vao;
+        let vbo;
+        let (proj_uniform, offset_uniform) = unsafe {
+            vao = GlObject::VertexArray.gen();
+            gl::BindVertexArray(vao);
+            vbo = GlObject::Buffer.gen();
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+
+            gl::UseProgram(program);
+            gl::BindFragDataLocation(program, 0, CString::new("out_color")?.as_ptr());
+
+            let mut uniforms = [0; 4];
+            for (u, name) in uniforms.iter_mut().zip(["proj", "offset", "levels", "lut"]) {
+                *u = gl::GetUniformLocation(program, CString::new(name)?.as_ptr());
+                if *u < 0 {
+                    return Err(format!("GetUniformLocation(\"{}\") -> {}", name, u).into());
+                }
+            }
+            gl::Uniform1i(uniforms[2], 0);
+            gl::Uniform1i(uniforms[3], 1);
+
+            let mut offset = 0;
+            for (v_field, float_count) in &[("pos", 2), ("uv", 2)] {
+                let attr = gl::GetAttribLocation(program, CString::new(*v_field)?.as_ptr());
+                if attr < 0 {
+                    return Err(format!("{} GetAttribLocation -> {}", v_field, attr).into());
+                }
+                gl::VertexAttribPointer(
+                    attr as _,
+                    *float_count,
+                    gl::FLOAT,
+                    gl::FALSE as _,
+                    (4 * mem::size_of::<GLfloat>()) as _,
+                    offset as _,
+                );
+                gl::EnableVertexAttribArray(attr as _);
+                offset += float_count * 4;
+            }
+            gl_log_error!();
+            (uniforms[0], uniforms[1])
+        };
+
+        let mut analyzer = SpectrumAnalyzer::new(fft_size, 0.75);
+        analyzer.averaging = 0.0;
+        Ok(Self {
+            rect,
+            sample_rate: 44100.0,
+            freq_range: (20.0, 20000.0),
+            db_range: (-100.0, 0.0),
+            analyzer,
+            color_map,
+            source,
+            pending: Vec::new(),
+            queued: Vec::new(),
+            blank_run: columns,
+            write_pos: 0,
+            levels,
+            lut,
+            shaders: [vs, fs],
+            program,
+            vao,
+            vbo,
+            proj_uniform,
+            offset_uniform,
+            dirty: DirtyCheck::new(),
+        })
+    }
+
+    pub fn color_map(&self) -> &ColorMap {
+        &self.color_map
+    }
+
+    /// Swaps the color map, overriding the theme's. Only the lookup table is uploaded again,
+    /// the history is kept.
+    pub fn set_color_map(&mut self, color_map: ColorMap) {
+        self.color_map.set(color_map);
+        self.upload_lut();
+    }
+
+    fn upload_lut(&mut self) {
+        self.lut.upload(&self.color_map.lut(LUT_SIZE));
+        self.dirty.invalidate();
+    }
+
+    /// Runs the FFT over new samples and queues each finished frame as a column.
+    fn queue_frames(&mut self) {
+        self.pending.clear();
+        self.source.drain_into(&mut self.pending);
+
+        let (columns, rows) = self.levels.size();
+        let bin_hz = self.sample_rate / self.analyzer.size() as f32;
+        let (lo, hi) = self.db_range;
+        let (freq_range, queued, blank_run) = (self.freq_range, &mut self.queued, &mut self.blank_run);
+        self.analyzer.push_with(&self.pending, |frame| {
+            let start = queued.len();
+            queued.extend(
+                spectrum_to_columns(frame, bin_hz, rows as usize, freq_range)
+                    .into_iter()
+                    .map(|db| (((db - lo) / (hi - lo)).clamp(0.0, 1.0) * 255.0) as u8),
+            );
+            if queued[start..].iter().any(|c| *c > 0) {
+                *blank_run = 0;
+            } else if *blank_run >= columns {
+                queued.truncate(start);
+            } else {
+                *blank_run += 1;
+            }
+        });
+        // after a long gap only the newest texture width of columns is still visible
+        let excess = self.queued.len().saturating_sub((columns * rows) as usize);
+        self.queued.drain(..excess);
+    }
+
+    /// Writes the queued columns into the texture.
+    fn upload_frames(&mut self) {
+        let (columns, rows) = self.levels.size();
+        for column in self.queued.chunks(rows as usize) {
+            self.levels.upload_region([self.write_pos, 0, 1, rows], column);
+            self.write_pos = (self.write_pos + 1) % columns;
+        }
+        self.queued.clear();
+    }
+
+    fn draw(&self, window_size: (i32, i32)) {
+        let [x, y, w, h] = self.rect;
+        // top edge shows the highest frequencies, texture row 0 holds the lowest
+        let vertices: [GLfloat; 16] = [
+            x, y, 0.0, 1.0,
+            x + w, y, 1.0, 1.0,
+            x, y + h, 0.0, 0.0,
+            x + w, y + h, 1.0, 0.0,
+        ];
+        let proj = ortho(0.0, window_size.0 as f32, 0.0, window_size.1 as f32, 1.0, -1.0);
+        unsafe {
+            gl::UseProgram(self.program);
+            gl::UniformMatrix4fv(self.proj_uniform, 1, 0, proj.as_ptr());
+            gl::Uniform1f(self.offset_uniform, self.write_pos as f32 / self.levels.size().0 as f32);
+            self.levels.bind(0);
+            self.lut.bind(1);
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                mem::size_of_val(&vertices) as GLsizeiptr,
+                vertices.as_ptr() as _,
+                gl::DYNAMIC_DRAW,
+            );
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl_log_error!();
+        }
+    }
+}
+
+impl UIElement for UISpectrogram {
+    fn render(&mut self, window_size: (i32, i32)) {
+        self.upload_frames();
+        self.draw(window_size);
+        self.dirty.drawn(self.rect);
+    }
+
+    fn animate(&mut self, _dt: f32) -> bool {
+        self.queue_frames();
+        false
+    }
+
+    fn is_dirty(&self) -> bool {
+        !self.queued.is_empty() || self.dirty.is_dirty(&self.rect)
+    }
+
+    fn get_window_transform() -> TMatrix {
+        TMatrix::default()
+    }
+
+    fn set_rect(&mut self, rect: [f32; 4]) {
+        self.rect = rect;
+    }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        self.color_map.set_theme(ColorMap::from_theme(theme));
+        if !self.color_map.is_custom() {
+            self.upload_lut();
+        }
+    }
+}
+
+impl Drop for UISpectrogram {
+    fn drop(&mut self) {
+        GlObject::Program.delete(self.program);
+        self.shaders.iter().for_each(|&s| GlObject::Shader.delete(s));
+        GlObject::Buffer.delete(self.vbo);
+        GlObject::VertexArray.delete(self.vao);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_interpolates_between_stops() {
+        let map = ColorMap::Grayscale;
+        assert_eq!([0.0, 0.0, 0.0, 1.0], map.sample(-1.0));
+        assert_eq!([0.5, 0.5, 0.5, 1.0], map.sample(0.5));
+        assert_eq!([1.0, 1.0, 1.0, 1.0], map.sample(2.0));
+        let custom = ColorMap::Custom(vec![(0.2, [1.0, 0.0, 0.0, 1.0]), (0.6, [0.0, 0.0, 1.0, 1.0])]);
+        let c = custom.sample(0.4);
+        assert!((c[0] - 0.5).abs() < 1e-6 && (c[2] - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_theme_map_starts_at_panel() {
+        let theme = Theme::default();
+        let map = ColorMap::from_theme(&theme);
+        assert_eq!(theme.panel, map.sample(0.0));
+        assert_eq!(theme.text, map.sample(1.0));
+    }
+
+    #[test]
+    fn test_lut_ends() {
+        let lut = ColorMap::Heat.lut(LUT_SIZE);
+        assert_eq!(LUT_SIZE * 4, lut.len());
+        assert_eq!([0, 0, 0, 255], lut[..4]);
+        assert_eq!([255, 255, 255, 255], lut[lut.len() - 4..]);
+    }
+}
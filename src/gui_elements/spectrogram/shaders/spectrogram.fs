@@ -0,0 +1,15 @@
+#version 150
+
+uniform sampler2D levels;
+uniform sampler2D lut;
+// column of the oldest frame as a fraction of the texture width
+uniform float offset;
+
+in vec2 f_uv;
+
+out vec4 out_color;
+
+void main() {
+    float level = texture(levels, vec2(fract(f_uv.x + offset), f_uv.y)).r;
+    out_color = texture(lut, vec2(level, 0.5));
+}
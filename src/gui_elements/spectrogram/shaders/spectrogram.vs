@@ -0,0 +1,20 @@
+#version 150
+
+const mat4 INVERT_Y_AXIS = mat4(
+    vec4(1.0, 0.0, 0.0, 0.0),
+    vec4(0.0, -1.0, 0.0, 0.0),
+    vec4(0.0, 0.0, 1.0, 0.0),
+    vec4(0.0, 0.0, 0.0, 1.0)
+);
+
+uniform mat4 proj;
+
+in vec2 pos;
+in vec2 uv;
+
+out vec2 f_uv;
+
+void main() {
+    f_uv = uv;
+    gl_Position = INVERT_Y_AXIS * proj * vec4(pos, 0.0, 1.0);
+}
@@ -0,0 +1,427 @@
+use std::f32::consts::FRAC_1_SQRT_2;
+use glyph_brush::ab_glyph::FontRef;
+use crate::channel::SampleSource;
+use crate::dsp::correlation::Correlation;
+use crate::gui_elements::shapes::{GlShapePipe, ShapeBatch};
+use crate::gui_elements::text::UIText;
+use crate::gui_elements::text::text_utils::Res;
+use crate::gui_elements::utils::blit::GlBlitPipe;
+use crate::gui_elements::utils::dirty::DirtyCheck;
+use crate::gui_elements::utils::framebuffer::GlFramebuffer;
+use crate::gui_elements::utils::t_matrix::TMatrix;
+use crate::gui_elements::UIElement;
+use crate::gui_elements::theme::{Theme, Themed};
+
+/// Turns a stereo pair into `[side, mid]`, so mono lands on the vertical axis, left only on
+/// the upper left diagonal and right only on the upper right one. Both are halved, which puts
+/// a full scale mono signal at 1.
+pub fn mid_side([l, r]: [f32; 2]) -> [f32; 2] {
+    [(l - r) / 2.0, (l + r) / 2.0]
+}
+
+const LABEL_SIZE: f32 = 11.0;
+/// The goniometer trail is gone after this many `persistence` times without new samples.
+const FADE_OUT: f32 = 6.0;
+/// Pairs with both channels below this are not plotted, so silence leaves an empty display.
+const SILENCE: f32 = 1e-5;
+
+#[derive(Debug, Clone, Copy)]
+pub struct GoniometerColors {
+    pub background: [f32; 4],
+    pub grid: [f32; 4],
+    pub label: [f32; 4],
+    pub trace: [f32; 4],
+}
+
+impl GoniometerColors {
+    pub fn from_theme(theme: &Theme) -> Self {
+        Self {
+            background: theme.panel,
+            grid: theme.grid,
+            label: theme.text_dim,
+            trace: theme.good,
+        }
+    }
+}
+
+impl Default for GoniometerColors {
+    fn default() -> Self {
+        Self::from_theme(&Theme::default())
+    }
+}
+
+/// A goniometer. Sample pairs are plotted mid/side rotated into an offscreen texture with
+/// additive blending, and the texture fades a little every frame so older samples leave a
+/// trail.
+///
+/// The texture resolution is taken from the size of `rect` at creation. Clones of a
+/// [`SampleSource`] drain the same queue, so a goniometer and a [`UICorrelationMeter`] each
+/// need their own channel, fed the same samples.
+pub struct UIGoniometer {
+    /// `[x, y, width, height]` in window pixels.
+    pub rect: [f32; 4],
+    /// Gain applied before plotting. At 1 a full scale mono signal reaches the edge.
+    pub scale: f32,
+    /// Time for the trail to fade to about a third, in seconds.
+    pub persistence: f32,
+    /// Size of each plotted sample in pixels.
+    pub point_size: f32,
+    /// How much each sample adds to the trace color, 0 to 1.
+    pub intensity: f32,
+    /// Only the newest samples of each frame are plotted.
+    pub max_points: usize,
+    pub colors: Themed<GoniometerColors>,
+    source: SampleSource<[f32; 2]>,
+    pending: Vec<[f32; 2]>,
+    /// Time since the texture was last faded, in seconds.
+    fade_time: f32,
+    /// Time left until the trail has faded out, in seconds.
+    trail: f32,
+    target: GlFramebuffer,
+    blit: GlBlitPipe,
+    shapes: GlShapePipe,
+    batch: ShapeBatch,
+    labels: UIText,
+}
+
+impl UIGoniometer {
+    pub fn new(rect: [f32; 4], source: SampleSource<[f32; 2]>) -> Res<Self> {
+        let font = FontRef::try_from_slice(include_bytes!("../text/fonts/source-code-pro.regular.ttf"))?;
+        let mut labels = UIText::new("", LABEL_SIZE, [1.0; 4], [0.0, 0.0], font)?;
+        labels.set_transform(TMatrix::default());
+        let target = GlFramebuffer::new((rect[2].max(1.0) as u32, rect[3].max(1.0) as u32))?;
+        Ok(Self {
+            rect,
+            scale: 1.0,
+            persistence: 0.15,
+            point_size: 1.5,
+            intensity: 0.3,
+            max_points: 8192,
+            colors: Themed::new(GoniometerColors::default()),
+            source,
+            pending: Vec::new(),
+            fade_time: 0.0,
+            trail: 0.0,
+            target,
+            blit: GlBlitPipe::new()?,
+            shapes: GlShapePipe::new((1, 1), &TMatrix::default())?,
+            batch: ShapeBatch::new(),
+            labels,
+        })
+    }
+
+    /// Fades the texture and adds the new samples.
+    fn accumulate(&mut self, dt: f32, window_size: (i32, i32)) {
+        let (w, h) = self.target.size();
+        let (w, h) = (w as f32, h as f32);
+        let fade = 1.0 - (-dt / self.persistence.max(1e-3)).exp();
+        let radius = w.min(h) / 2.0;
+        let (cx, cy) = (w / 2.0, h / 2.0);
+        let c = self.colors.trace;
+        let a = self.intensity;
+        let color = [c[0] * a, c[1] * a, c[2] * a, a];
+        let half = self.point_size / 2.0;
+
+        self.target.bind();
+        self.shapes.update_geometry((w as i32, h as i32));
+        let mut batch = std::mem::take(&mut self.batch);
+        unsafe {
+            batch.clear();
+            batch.rect([0.0, 0.0, w, h], [0.0, 0.0, 0.0, fade]);
+            self.shapes.upload(&batch);
+            self.shapes.draw();
+
+            // 8 bit channels never fade out completely by scaling alone
+            let step = 1.0 / 255.0;
+            batch.clear();
+            batch.rect([0.0, 0.0, w, h], [step; 4]);
+            gl::BlendEquation(gl::FUNC_REVERSE_SUBTRACT);
+            gl::BlendFunc(gl::ONE, gl::ONE);
+            self.shapes.upload(&batch);
+            self.shapes.draw();
+            gl::BlendEquation(gl::FUNC_ADD);
+
+            batch.clear();
+            let start = self.pending.len().saturating_sub(self.max_points);
+            for pair in &self.pending[start..] {
+                let [side, mid] = mid_side(*pair);
+                let x = cx - side * self.scale * radius;
+                let y = cy - mid * self.scale * radius;
+                batch.rect([x - half, y - half, self.point_size, self.point_size], color);
+            }
+            self.shapes.upload(&batch);
+            self.shapes.draw();
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+        self.batch = batch;
+        self.target.unbind(window_size);
+    }
+
+    fn build_grid(&mut self) {
+        let [x, y, w, h] = self.rect;
+        let colors = self.colors;
+        let (cx, cy) = (x + w / 2.0, y + h / 2.0);
+        let r = w.min(h) / 2.0;
+        let d = r * FRAC_1_SQRT_2;
+        let mut batch = std::mem::take(&mut self.batch);
+        batch.clear();
+        batch.rect(self.rect, colors.background);
+        let ring: Vec<[f32; 2]> = (0..=64)
+            .map(|i| {
+                let a = i as f32 / 64.0 * std::f32::consts::TAU;
+                [cx + a.cos() * r, cy + a.sin() * r]
+            })
+            .collect();
+        batch.polyline(&ring, 1.0, colors.grid);
+        batch.line([cx, cy - r], [cx, cy + r], 1.0, colors.grid);
+        batch.line([cx - r, cy], [cx + r, cy], 1.0, colors.grid);
+        batch.line([cx - d, cy - d], [cx + d, cy + d], 1.0, colors.grid);
+        batch.line([cx + d, cy - d], [cx - d, cy + d], 1.0, colors.grid);
+        self.batch = batch;
+    }
+
+    fn queue_labels(&mut self) {
+        let [x, y, w, h] = self.rect;
+        let (cx, cy) = (x + w / 2.0, y + h / 2.0);
+        let d = w.min(h) / 2.0 * FRAC_1_SQRT_2;
+        let color = self.colors.label;
+        let bounds = (self.labels.font_size * 2.0, self.labels.font_size * 2.0);
+        self.labels.queue_spans(&[("L", color)], [cx - d - self.labels.font_size, cy - d - self.labels.font_size], bounds);
+        self.labels.queue_spans(&[("R", color)], [cx + d + 2.0, cy - d - self.labels.font_size], bounds);
+        self.labels.queue_spans(&[("M", color)], [cx + 3.0, y + 2.0], bounds);
+    }
+}
+
+impl UIElement for UIGoniometer {
+    fn render(&mut self, window_size: (i32, i32)) {
+        self.accumulate(self.fade_time, window_size);
+        self.fade_time = 0.0;
+        self.pending.clear();
+
+        self.build_grid();
+        self.shapes.update_geometry(window_size);
+        self.shapes.upload(&self.batch);
+        self.shapes.draw();
+        unsafe {
+            gl::BlendFunc(gl::ONE, gl::ONE);
+            self.blit.draw(&self.target.texture, self.rect, window_size);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+
+        self.labels.resize(window_size);
+        self.queue_labels();
+        self.labels.draw_queued();
+    }
+
+    fn get_window_transform() -> TMatrix {
+        TMatrix::default()
+    }
+
+    fn set_rect(&mut self, rect: [f32; 4]) {
+        self.rect = rect;
+        let size = (rect[2].max(1.0) as u32, rect[3].max(1.0) as u32);
+        if size != self.target.size() {
+            // the trace starts over at the new size
+            match GlFramebuffer::new(size) {
+                Ok(target) => self.target = target,
+                Err(e) => vst_log::log(format!("goniometer resize: {}", e)),
+            }
+        }
+    }
+
+    fn animate(&mut self, dt: f32) -> bool {
+        let before = self.pending.len();
+        self.source.drain_into(&mut self.pending);
+        self.pending.retain(|[l, r]| l.abs() >= SILENCE || r.abs() >= SILENCE);
+        let arrived = self.pending.len() > before;
+        let excess = self.pending.len().saturating_sub(self.max_points);
+        self.pending.drain(..excess);
+        self.fade_time += dt;
+        if arrived {
+            self.trail = self.persistence * FADE_OUT;
+        } else {
+            self.trail = (self.trail - dt).max(0.0);
+        }
+        self.trail > 0.0
+    }
+
+    fn is_dirty(&self) -> bool {
+        !self.pending.is_empty() || self.trail > 0.0
+    }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        self.colors.set_theme(GoniometerColors::from_theme(theme));
+        theme.apply_to_labels(&mut self.labels);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CorrelationColors {
+    pub background: [f32; 4],
+    pub grid: [f32; 4],
+    pub label: [f32; 4],
+    pub positive: [f32; 4],
+    pub negative: [f32; 4],
+    pub marker: [f32; 4],
+}
+
+impl CorrelationColors {
+    pub fn from_theme(theme: &Theme) -> Self {
+        Self {
+            background: theme.panel,
+            grid: theme.grid,
+            label: theme.text_dim,
+            positive: theme.good,
+            negative: theme.danger,
+            marker: theme.text,
+        }
+    }
+}
+
+impl Default for CorrelationColors {
+    fn default() -> Self {
+        Self::from_theme(&Theme::default())
+    }
+}
+
+/// A horizontal phase correlation meter from -1 on the left to +1 on the right. The bar grows
+/// from the center and turns the negative color when the channels cancel. Like the
+/// [`UIGoniometer`] it needs a source of its own.
+pub struct UICorrelationMeter {
+    /// `[x, y, width, height]` in window pixels, the labels go below the bar.
+    pub rect: [f32; 4],
+    pub colors: Themed<CorrelationColors>,
+    correlation: Correlation,
+    integration_time: f32,
+    sample_rate: f32,
+    source: SampleSource<[f32; 2]>,
+    pending: Vec<[f32; 2]>,
+    shapes: GlShapePipe,
+    batch: ShapeBatch,
+    labels: UIText,
+    dirty: DirtyCheck<([f32; 4], f32)>,
+}
+
+impl UICorrelationMeter {
+    /// `sample_rate` is the rate of the samples in `source`.
+    pub fn new(rect: [f32; 4], source: SampleSource<[f32; 2]>, sample_rate: f32) -> Res<Self> {
+        let font = FontRef::try_from_slice(include_bytes!("../text/fonts/source-code-pro.regular.ttf"))?;
+        let mut labels = UIText::new("", LABEL_SIZE, [1.0; 4], [0.0, 0.0], font)?;
+        labels.set_transform(TMatrix::default());
+        let integration_time = 0.3;
+        Ok(Self {
+            rect,
+            colors: Themed::new(CorrelationColors::default()),
+            correlation: Correlation::new(integration_time, sample_rate),
+            integration_time,
+            sample_rate,
+            source,
+            pending: Vec::new(),
+            shapes: GlShapePipe::new((1, 1), &TMatrix::default())?,
+            batch: ShapeBatch::new(),
+            labels,
+            dirty: DirtyCheck::new(),
+        })
+    }
+
+    /// Sets how many seconds of signal the reading averages. Starts over from zero.
+    pub fn set_integration_time(&mut self, time: f32) {
+        self.integration_time = time;
+        self.correlation = Correlation::new(time, self.sample_rate);
+    }
+
+    /// Follows a change of the host's sample rate. Starts over from zero.
+    pub fn set_sample_rate(&mut self, sample_rate: f32) {
+        self.sample_rate = sample_rate;
+        self.correlation = Correlation::new(self.integration_time, sample_rate);
+    }
+
+    pub fn value(&self) -> f32 {
+        self.correlation.value()
+    }
+
+    fn build(&mut self) {
+        let [x, y, w, h] = self.rect;
+        let colors = self.colors;
+        let value = self.correlation.value();
+        let cx = x + w / 2.0;
+        let vx = cx + value * w / 2.0;
+        let mut batch = std::mem::take(&mut self.batch);
+        batch.clear();
+        batch.rect(self.rect, colors.background);
+        for i in 1..8 {
+            batch.rect([(x + w * i as f32 / 8.0).round(), y, 1.0, h], colors.grid);
+        }
+        let color = if value < 0.0 { colors.negative } else { colors.positive };
+        batch.rect([cx.min(vx), y + 2.0, (vx - cx).abs(), h - 4.0], color);
+        batch.rect([(vx - 1.0).round(), y, 2.0, h], colors.marker);
+        self.batch = batch;
+    }
+
+    fn queue_labels(&mut self) {
+        let [x, y, w, h] = self.rect;
+        let color = self.colors.label;
+        let bounds = (self.labels.font_size * 3.0, self.labels.font_size * 2.0);
+        let top = y + h + 2.0;
+        self.labels.queue_spans(&[("-1", color)], [x, top], bounds);
+        self.labels.queue_spans(&[("0", color)], [x + w / 2.0 - self.labels.font_size * 0.3, top], bounds);
+        self.labels.queue_spans(&[("+1", color)], [x + w - self.labels.font_size * 1.2, top], bounds);
+    }
+}
+
+impl UIElement for UICorrelationMeter {
+    fn render(&mut self, window_size: (i32, i32)) {
+        self.build();
+        self.shapes.update_geometry(window_size);
+        self.shapes.upload(&self.batch);
+        self.shapes.draw();
+
+        self.labels.resize(window_size);
+        self.queue_labels();
+        self.labels.draw_queued();
+        self.dirty.drawn((self.rect, self.value()));
+    }
+
+    fn animate(&mut self, _dt: f32) -> bool {
+        self.pending.clear();
+        self.source.drain_into(&mut self.pending);
+        self.correlation.push(&self.pending);
+        false
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty.is_dirty(&(self.rect, self.value()))
+    }
+
+    fn get_window_transform() -> TMatrix {
+        TMatrix::default()
+    }
+
+    fn set_rect(&mut self, rect: [f32; 4]) {
+        self.rect = rect;
+    }
+
+    fn set_theme(&mut self, theme: &Theme) {
+        self.colors.set_theme(CorrelationColors::from_theme(theme));
+        theme.apply_to_labels(&mut self.labels);
+        self.dirty.invalidate();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mid_side() {
+        let mono = mid_side([0.5, 0.5]);
+        assert!(mono[0].abs() < 1e-6 && (mono[1] - 0.5).abs() < 1e-6);
+        // full scale mono reaches the edge and no further
+        assert_eq!([0.0, 1.0], mid_side([1.0, 1.0]));
+        assert_eq!([1.0, 0.0], mid_side([1.0, -1.0]));
+        let left = mid_side([1.0, 0.0]);
+        assert!(left[0] > 0.0 && (left[0] - left[1]).abs() < 1e-6);
+        let right = mid_side([0.0, 1.0]);
+        assert!(right[0] < 0.0 && (right[0] + right[1]).abs() < 1e-6);
+    }
+}
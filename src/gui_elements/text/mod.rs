@@ -2,7 +2,7 @@ use glyph_brush::{ab_glyph::*, *};
 use text_utils::*;
 use crate::gui_elements::utils::t_matrix::TMatrix;
 
-mod text_utils;
+pub(crate) mod text_utils;
 
 
 pub struct UIText {
@@ -43,7 +43,51 @@ impl UIText {
                 .add_text(base_text.with_color(self.color))
                 .with_bounds((window_size.0 as f32, window_size.1 as f32)),
         );
-    
+        self.draw_queued();
+    }
+
+    /// Renders several differently colored spans as one section, starting at `position`
+    /// and wrapped inside `bounds`. `self.text` and `self.color` are ignored.
+    pub fn render_spans(&mut self, spans: &[(&str, [f32; 4])], position: [f32; 2], bounds: (f32, f32)) {
+        self.queue_spans(spans, position, bounds);
+        self.draw_queued();
+    }
+
+    /// Queues spans like [`UIText::render_spans`] without drawing them, so several sections
+    /// can go out in one [`UIText::draw_queued`] call.
+    pub fn queue_spans(&mut self, spans: &[(&str, [f32; 4])], position: [f32; 2], bounds: (f32, f32)) {
+        let font_size = self.font_size;
+        self.glyph_brush.queue(
+            Section::default()
+                .with_screen_position((position[0], position[1]))
+                .with_bounds(bounds)
+                .with_text(
+                    spans.iter()
+                        .map(|(s, color)| Text::new(s).with_scale(font_size).with_color(*color))
+                        .collect(),
+                ),
+        );
+    }
+
+    /// Updates the projection after the window has been resized.
+    pub fn resize(&self, window_size: (i32, i32)) {
+        self.text_pipe.update_geometry(window_size);
+    }
+
+    /// Switches to another font. Queued text is dropped and the glyph cache starts over.
+    pub fn set_font(&mut self, font: FontRef<'static>) {
+        self.glyph_brush = GlyphBrushBuilder::using_font(font).build();
+        self.texture = GlGlyphTexture::new(self.glyph_brush.texture_dimensions());
+    }
+
+    /// Replaces the transform applied to the text.
+    pub fn set_transform(&mut self, trs: TMatrix) {
+        self.text_pipe.update_window_transform(&trs);
+        self.trs = trs;
+    }
+
+    /// Draws every section queued since the last draw.
+    pub fn draw_queued(&mut self) {
         // Tell glyph_brush to process the queued text
         let mut brush_action;
         loop {
@@ -1,7 +1,7 @@
 use gl::types::*;
 use glyph_brush::{ab_glyph::*};
 use std::{ffi::CString, mem, ptr, str};
-use crate::gui_elements::utils::gl::{compile_shader, link_program, gl_err_to_str};
+use crate::gui_elements::utils::gl::{compile_shader, link_program, gl_err_to_str, GlObject};
 use crate::gl_log_error;
 
 use crate::gui_elements::utils::t_matrix::TMatrix;
@@ -87,12 +87,12 @@ pub struct GlGlyphTexture {
 
 impl GlGlyphTexture {
     pub fn new((width, height): (u32, u32)) -> Self {
-        let mut name = 0;
+        let name;
         unsafe {
             // Create a texture for the glyphs
             // The texture holds 1 byte per pixel as alpha data
             gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
-            gl::GenTextures(1, &mut name);
+            name = GlObject::Texture.gen();
             gl::BindTexture(gl::TEXTURE_2D, name);
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as _);
             gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as _);
@@ -132,9 +132,7 @@ impl GlGlyphTexture {
 
 impl Drop for GlGlyphTexture {
     fn drop(&mut self) {
-        unsafe {
-            gl::DeleteTextures(1, &self.name);
-        }
+        GlObject::Texture.delete(self.name);
     }
 }
 
@@ -157,16 +155,16 @@ impl GlTextPipe {
         let fs = compile_shader(include_str!("shaders/text.fs"), gl::FRAGMENT_SHADER)?;
         let program = link_program(vs, fs)?;
 
-        let mut vao = 0;
-        let mut vbo = 0;
+        let vao;
+        let vbo;
         let window_transform_uniform;
         let proj_uniform = unsafe {
             // Create Vertex Array Object
-            gl::GenVertexArrays(1, &mut vao);
+            vao = GlObject::VertexArray.gen();
             gl::BindVertexArray(vao);
 
             // Create a Vertex Buffer Object
-            gl::GenBuffers(1, &mut vbo);
+            vbo = GlObject::Buffer.gen();
             gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
 
             // Use shader program
@@ -222,9 +220,8 @@ impl GlTextPipe {
             // Enabled alpha blending
             gl::Enable(gl::BLEND);
             gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
-            // Use srgb for consistency with other examples
-            gl::Enable(gl::FRAMEBUFFER_SRGB);
-            gl::ClearColor(0.02, 0.02, 0.02, 1.0);
+            // colors are sRGB already, see `Color`, so GL must not encode them again
+            gl::Disable(gl::FRAMEBUFFER_SRGB);
             gl_log_error!();
             uniform
         };
@@ -279,10 +276,10 @@ impl GlTextPipe {
         }
     }
 
-    pub fn update_window_transform(&self, t: TMatrix) {
+    pub fn update_window_transform(&self, t: &TMatrix) {
         unsafe {
             gl::UseProgram(self.program);
-            gl::UniformMatrix4fv(self.window_transform_uniform, 1, 0, t.as_ptr());
+            gl::UniformMatrix3fv(self.window_transform_uniform, 1, 0, t.as_ptr());
             gl_log_error!();
         }
     }
@@ -304,11 +301,9 @@ impl GlTextPipe {
 
 impl Drop for GlTextPipe {
     fn drop(&mut self) {
-        unsafe {
-            gl::DeleteProgram(self.program);
-            self.shaders.iter().for_each(|s| gl::DeleteShader(*s));
-            gl::DeleteBuffers(1, &self.vbo);
-            gl::DeleteVertexArrays(1, &self.vao);
-        }
+        GlObject::Program.delete(self.program);
+        self.shaders.iter().for_each(|&s| GlObject::Shader.delete(s));
+        GlObject::Buffer.delete(self.vbo);
+        GlObject::VertexArray.delete(self.vao);
     }
 }
\ No newline at end of file
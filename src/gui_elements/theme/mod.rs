@@ -0,0 +1,365 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use glyph_brush::ab_glyph::FontRef;
+use parking_lot::Mutex;
+use serde::Deserialize;
+use crate::gui_elements::color::Color;
+use crate::gui_elements::text::text_utils::Res;
+use crate::gui_elements::text::UIText;
+use crate::ui_file;
+
+/// The font every widget uses unless the theme names another one.
+pub const DEFAULT_FONT: &[u8] = include_bytes!("../text/fonts/source-code-pro.regular.ttf");
+
+/// `color` with its alpha replaced.
+pub fn with_alpha(color: [f32; 4], alpha: f32) -> [f32; 4] {
+    [color[0], color[1], color[2], alpha]
+}
+
+/// Interaction state of a control, for picking a [`StateColors`] variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WidgetState {
+    Normal,
+    /// The cursor is over the control.
+    Hover,
+    /// The control is being dragged or held.
+    Active,
+    Disabled,
+}
+
+/// One color per [`WidgetState`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StateColors {
+    pub normal: [f32; 4],
+    pub hover: [f32; 4],
+    pub active: [f32; 4],
+    pub disabled: [f32; 4],
+}
+
+impl StateColors {
+    /// Derives the other states from `normal`: hover is lighter, active lighter still and
+    /// disabled a faded gray.
+    pub fn from_normal(normal: [f32; 4]) -> Self {
+        let mix = |c: [f32; 4], t: [f32; 3], f: f32| [
+            c[0] + (t[0] - c[0]) * f,
+            c[1] + (t[1] - c[1]) * f,
+            c[2] + (t[2] - c[2]) * f,
+            c[3],
+        ];
+        let gray = (normal[0] + normal[1] + normal[2]) / 3.0;
+        let disabled = mix(normal, [gray; 3], 0.8);
+        Self {
+            normal,
+            hover: mix(normal, [1.0; 3], 0.25),
+            active: mix(normal, [1.0; 3], 0.5),
+            disabled: [disabled[0] * 0.6, disabled[1] * 0.6, disabled[2] * 0.6, normal[3] * 0.6],
+        }
+    }
+
+    pub fn get(&self, state: WidgetState) -> [f32; 4] {
+        match state {
+            WidgetState::Normal => self.normal,
+            WidgetState::Hover => self.hover,
+            WidgetState::Active => self.active,
+            WidgetState::Disabled => self.disabled,
+        }
+    }
+}
+
+/// A style value a widget takes from the theme unless it was set on the widget itself, so
+/// customizing one instance survives [`UIElement::set_theme`](crate::gui_elements::UIElement::set_theme).
+/// Derefs to whichever value is in effect.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Themed<T> {
+    theme: T,
+    custom: Option<T>,
+}
+
+impl<T> Themed<T> {
+    pub fn new(theme: T) -> Self {
+        Self { theme, custom: None }
+    }
+
+    /// Overrides the theme's value for this widget.
+    pub fn set(&mut self, value: T) {
+        self.custom = Some(value);
+    }
+
+    /// Follows the theme again.
+    pub fn reset(&mut self) {
+        self.custom = None;
+    }
+
+    pub fn is_custom(&self) -> bool {
+        self.custom.is_some()
+    }
+
+    /// Takes the theme's value, in effect unless a custom one is set.
+    pub fn set_theme(&mut self, theme: T) {
+        self.theme = theme;
+    }
+}
+
+impl<T> Deref for Themed<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.custom.as_ref().unwrap_or(&self.theme)
+    }
+}
+
+/// Colors, font and sizes shared by all widgets. Widgets map the palette onto their own color
+/// structs (e.g. [`EqColors::from_theme`](crate::gui_elements::eq::EqColors::from_theme)) in
+/// [`UIElement::set_theme`](crate::gui_elements::UIElement::set_theme), so changing the theme
+/// at runtime restyles everything except values set on a widget through [`Themed::set`].
+#[derive(Clone, PartialEq)]
+pub struct Theme {
+    /// Clears the window behind the widgets.
+    pub background: [f32; 4],
+    /// Background of displays and editors.
+    pub panel: [f32; 4],
+    /// Cells, keys and other surfaces drawn on a panel.
+    pub surface: [f32; 4],
+    /// Surfaces that stand out, e.g. the first step of every beat.
+    pub surface_accent: [f32; 4],
+    pub grid: [f32; 4],
+    /// Grid lines at a reference value, e.g. 0 dB.
+    pub grid_strong: [f32; 4],
+    pub text: [f32; 4],
+    pub text_dim: [f32; 4],
+    /// The main color of values, curves and active steps.
+    pub accent: StateColors,
+    /// A second accent for overlays like played notes or step values.
+    pub secondary: StateColors,
+    /// Points and handles that can be dragged.
+    pub control: StateColors,
+    pub playhead: [f32; 4],
+    /// Level colors, from safe to clipping.
+    pub good: [f32; 4],
+    pub warning: [f32; 4],
+    pub danger: [f32; 4],
+    /// Multiplied into images and filmstrips, white leaves artwork as drawn.
+    pub artwork_tint: [f32; 4],
+
+    /// TrueType font data.
+    pub font: &'static [u8],
+    pub label_size: f32,
+    pub line_width: f32,
+    /// Radius of draggable points.
+    pub point_radius: f32,
+    pub corner_radius: f32,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            background: [0.02, 0.02, 0.02, 1.0],
+            panel: [0.05, 0.05, 0.06, 1.0],
+            surface: [0.13, 0.13, 0.15, 1.0],
+            surface_accent: [0.18, 0.18, 0.21, 1.0],
+            grid: [0.16, 0.16, 0.18, 1.0],
+            grid_strong: [0.3, 0.3, 0.33, 1.0],
+            text: [0.92, 0.92, 0.9, 1.0],
+            text_dim: [0.5, 0.5, 0.55, 1.0],
+            accent: StateColors::from_normal([0.95, 0.65, 0.3, 1.0]),
+            secondary: StateColors::from_normal([0.45, 0.75, 1.0, 1.0]),
+            control: StateColors {
+                normal: [0.9, 0.9, 0.9, 1.0],
+                hover: [1.0, 1.0, 1.0, 0.9],
+                active: [1.0, 1.0, 1.0, 1.0],
+                disabled: [0.4, 0.4, 0.4, 0.6],
+            },
+            playhead: [1.0, 1.0, 1.0, 0.12],
+            good: [0.3, 0.8, 0.4, 1.0],
+            warning: [0.9, 0.8, 0.2, 1.0],
+            danger: [0.95, 0.25, 0.2, 1.0],
+            artwork_tint: [1.0; 4],
+            font: DEFAULT_FONT,
+            label_size: 11.0,
+            line_width: 2.0,
+            point_radius: 5.0,
+            corner_radius: 3.0,
+        }
+    }
+}
+
+impl Theme {
+    /// Reads a theme file, see [`Theme::from_ron`].
+    pub fn load<P: AsRef<Path>>(path: P) -> Res<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Self::from_ron(&text, path.parent())
+    }
+
+    /// Parses a theme like `Theme(accent: (0.2, 0.6, 1.0), label_size: 12, font: "ui.ttf")`.
+    /// Fields left out keep their default. Colors are `(r, g, b)`, `(r, g, b, a)` or hex
+    /// strings like `"#ff8000"`; state colors are a single color to derive the states from or
+    /// `(normal: .., hover: .., active: .., disabled: ..)`. Relative font paths start at
+    /// `dir`.
+    pub fn from_ron(text: &str, dir: Option<&Path>) -> Res<Self> {
+        let spec: ThemeSpec = ui_file::from_ron(text)?;
+        let mut theme = Self::default();
+        let colors = [
+            (spec.background, &mut theme.background),
+            (spec.panel, &mut theme.panel),
+            (spec.surface, &mut theme.surface),
+            (spec.surface_accent, &mut theme.surface_accent),
+            (spec.grid, &mut theme.grid),
+            (spec.grid_strong, &mut theme.grid_strong),
+            (spec.text, &mut theme.text),
+            (spec.text_dim, &mut theme.text_dim),
+            (spec.playhead, &mut theme.playhead),
+            (spec.good, &mut theme.good),
+            (spec.warning, &mut theme.warning),
+            (spec.danger, &mut theme.danger),
+            (spec.artwork_tint, &mut theme.artwork_tint),
+        ];
+        for (color, field) in colors {
+            if let Some(color) = color {
+                *field = color.to_array();
+            }
+        }
+        let states = [(spec.accent, &mut theme.accent), (spec.secondary, &mut theme.secondary), (spec.control, &mut theme.control)];
+        for (states, field) in states {
+            if let Some(states) = states {
+                *field = states.into();
+            }
+        }
+        let sizes = [
+            (spec.label_size, &mut theme.label_size),
+            (spec.line_width, &mut theme.line_width),
+            (spec.point_radius, &mut theme.point_radius),
+            (spec.corner_radius, &mut theme.corner_radius),
+        ];
+        for (size, field) in sizes {
+            if let Some(size) = size {
+                *field = size;
+            }
+        }
+        if let Some(path) = spec.font {
+            theme.font = load_font(&dir.map(|d| d.join(&path)).unwrap_or(path)).map_err(|e| format!("Theme.font: {}", e))?;
+        }
+        Ok(theme)
+    }
+
+    pub fn font_ref(&self) -> FontRef<'static> {
+        // checked when the theme was made, the default font is known to be valid
+        FontRef::try_from_slice(self.font).unwrap_or_else(|_| FontRef::try_from_slice(DEFAULT_FONT).unwrap())
+    }
+
+    /// Gives `text` the theme's font and label size.
+    pub fn apply_to_labels(&self, text: &mut UIText) {
+        text.set_font(self.font_ref());
+        text.font_size = self.label_size;
+    }
+}
+
+/// Reads a font file once and keeps it for the rest of the process, since glyph brushes need
+/// `'static` font data. Loading the same path again reuses the data.
+pub fn load_font(path: &Path) -> Res<&'static [u8]> {
+    static FONTS: OnceLock<Mutex<HashMap<PathBuf, &'static [u8]>>> = OnceLock::new();
+    let mut fonts = FONTS.get_or_init(Default::default).lock();
+    if let Some(data) = fonts.get(path) {
+        return Ok(data);
+    }
+    let data = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    FontRef::try_from_slice(&data).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let data: &'static [u8] = Box::leak(data.into_boxed_slice());
+    fonts.insert(path.to_path_buf(), data);
+    Ok(data)
+}
+
+/// The fields of a theme file, see [`Theme::from_ron`].
+#[derive(Default, Deserialize)]
+#[serde(rename = "Theme", default, deny_unknown_fields)]
+struct ThemeSpec {
+    background: Option<Color>,
+    panel: Option<Color>,
+    surface: Option<Color>,
+    surface_accent: Option<Color>,
+    grid: Option<Color>,
+    grid_strong: Option<Color>,
+    text: Option<Color>,
+    text_dim: Option<Color>,
+    accent: Option<StateColorsSpec>,
+    secondary: Option<StateColorsSpec>,
+    control: Option<StateColorsSpec>,
+    playhead: Option<Color>,
+    good: Option<Color>,
+    warning: Option<Color>,
+    danger: Option<Color>,
+    artwork_tint: Option<Color>,
+    font: Option<PathBuf>,
+    label_size: Option<f32>,
+    line_width: Option<f32>,
+    point_radius: Option<f32>,
+    corner_radius: Option<f32>,
+}
+
+/// A single color to derive the states from, or the states with any left out derived.
+#[derive(Deserialize)]
+#[serde(untagged, expecting = "expected a color or (normal: .., hover: .., active: .., disabled: ..)")]
+enum StateColorsSpec {
+    Normal(Color),
+    States {
+        normal: Color,
+        #[serde(default)]
+        hover: Option<Color>,
+        #[serde(default)]
+        active: Option<Color>,
+        #[serde(default)]
+        disabled: Option<Color>,
+    },
+}
+
+impl From<StateColorsSpec> for StateColors {
+    fn from(spec: StateColorsSpec) -> Self {
+        match spec {
+            StateColorsSpec::Normal(normal) => Self::from_normal(normal.to_array()),
+            StateColorsSpec::States { normal, hover, active, disabled } => {
+                let derived = Self::from_normal(normal.to_array());
+                Self {
+                    normal: derived.normal,
+                    hover: hover.map_or(derived.hover, |c| c.to_array()),
+                    active: active.map_or(derived.active, |c| c.to_array()),
+                    disabled: disabled.map_or(derived.disabled, |c| c.to_array()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_from_ron() {
+        let text = r##"Theme(
+            background: (0.1, 0.2, 0.3),
+            grid: "#ff000080",
+            accent: (0.2, 0.4, 0.6, 0.5),
+            control: (normal: (1, 1, 1), hover: (1, 0, 0)),
+            label_size: 13,
+            artwork_tint: "#ccc",
+        )"##;
+        let theme = Theme::from_ron(text, None).unwrap();
+        assert_eq!([0.1, 0.2, 0.3, 1.0], theme.background);
+        assert_eq!(StateColors::from_normal([0.2, 0.4, 0.6, 0.5]), theme.accent);
+        assert_eq!([1.0, 0.0, 0.0, 1.0], theme.control.get(WidgetState::Hover));
+        assert_eq!(StateColors::from_normal([1.0; 4]).active, theme.control.active);
+        assert_eq!(13.0, theme.label_size);
+        assert_eq!([1.0, 0.0, 0.0, 128.0 / 255.0], theme.grid);
+        assert_eq!(Theme::default().panel, theme.panel);
+        assert_eq!([0.8, 0.8, 0.8, 1.0], theme.artwork_tint);
+
+        let err = |text: &str| Theme::from_ron(text, None).err().unwrap().to_string();
+        assert!(err("Theme(colour: (1, 1, 1))").contains("`colour`"));
+        assert!(err("Theme(grid: (1, 1))").ends_with("expected (r, g, b), (r, g, b, a) or \"#rrggbb\""));
+        assert!(err("Theme(grid: \"#12\")").ends_with("invalid hex color \"#12\""));
+        assert!(err("Theme(accent: (hover: (1, 1, 1)))").contains("expected a color or"));
+        assert!(err("Theme(font: \"missing.ttf\")").starts_with("Theme.font:"));
+    }
+}
@@ -0,0 +1,113 @@
+use gl::types::*;
+use std::{ffi::CString, mem};
+use crate::gui_elements::text::text_utils::{ortho, Res};
+use crate::gui_elements::utils::gl::{compile_shader, link_program, gl_err_to_str, GlObject};
+use crate::gui_elements::utils::texture::GlTexture;
+use crate::gl_log_error;
+
+/// Draws a texture stretched over a rectangle in window pixels and multiplied by a tint, using
+/// whatever blend mode is set. [`GlBlitPipe::draw`] puts row 0 of the texture at the bottom, which is how framebuffer
+/// textures are laid out.
+pub struct GlBlitPipe {
+    shaders: [GLuint; 2],
+    program: GLuint,
+    vao: GLuint,
+    vbo: GLuint,
+    proj_uniform: GLint,
+    tint_uniform: GLint,
+}
+
+impl GlBlitPipe {
+    pub fn new() -> Res<Self> {
+        let vs = compile_shader(include_str!("shaders/blit.vs"), gl::VERTEX_SHADER)?;
+        let fs = compile_shader(include_str!("shaders/blit.fs"), gl::FRAGMENT_SHADER)?;
+        let program = link_program(vs, fs)?;
+
+        let vao;
+        let vbo;
+        let (proj_uniform, tint_uniform) = unsafe {
+            vao = GlObject::VertexArray.gen();
+            gl::BindVertexArray(vao);
+            vbo = GlObject::Buffer.gen();
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+
+            gl::UseProgram(program);
+            gl::BindFragDataLocation(program, 0, CString::new("out_color")?.as_ptr());
+
+            let mut uniforms = [0; 2];
+            for (u, name) in uniforms.iter_mut().zip(["proj", "tint"]) {
+                *u = gl::GetUniformLocation(program, CString::new(name)?.as_ptr());
+                if *u < 0 {
+                    return Err(format!("GetUniformLocation(\"{}\") -> {}", name, u).into());
+                }
+            }
+            let image = gl::GetUniformLocation(program, CString::new("image")?.as_ptr());
+            gl::Uniform1i(image, 0);
+
+            let mut offset = 0;
+            for (v_field, float_count) in &[("pos", 2), ("uv", 2)] {
+                let attr = gl::GetAttribLocation(program, CString::new(*v_field)?.as_ptr());
+                if attr < 0 {
+                    return Err(format!("{} GetAttribLocation -> {}", v_field, attr).into());
+                }
+                gl::VertexAttribPointer(
+                    attr as _,
+                    *float_count,
+                    gl::FLOAT,
+                    gl::FALSE as _,
+                    (4 * mem::size_of::<GLfloat>()) as _,
+                    offset as _,
+                );
+                gl::EnableVertexAttribArray(attr as _);
+                offset += float_count * 4;
+            }
+            gl_log_error!();
+            (uniforms[0], uniforms[1])
+        };
+
+        Ok(Self { shaders: [vs, fs], program, vao, vbo, proj_uniform, tint_uniform })
+    }
+
+    pub fn draw(&self, texture: &GlTexture, rect: [f32; 4], window_size: (i32, i32)) {
+        self.draw_region(texture, [0.0, 1.0, 1.0, 0.0], rect, [1.0; 4], window_size);
+    }
+
+    /// Draws part of a texture multiplied by `tint`. `uv` is `[left, top, right, bottom]` in
+    /// texture coordinates, so images uploaded top row first use a smaller `top` than `bottom`.
+    pub fn draw_region(&self, texture: &GlTexture, uv: [f32; 4], rect: [f32; 4], tint: [f32; 4], window_size: (i32, i32)) {
+        let [x, y, w, h] = rect;
+        let [u0, v0, u1, v1] = uv;
+        let vertices: [GLfloat; 16] = [
+            x, y, u0, v0,
+            x + w, y, u1, v0,
+            x, y + h, u0, v1,
+            x + w, y + h, u1, v1,
+        ];
+        let proj = ortho(0.0, window_size.0 as f32, 0.0, window_size.1 as f32, 1.0, -1.0);
+        unsafe {
+            gl::UseProgram(self.program);
+            gl::UniformMatrix4fv(self.proj_uniform, 1, 0, proj.as_ptr());
+            gl::Uniform4fv(self.tint_uniform, 1, tint.as_ptr());
+            texture.bind(0);
+            gl::BindVertexArray(self.vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                mem::size_of_val(&vertices) as GLsizeiptr,
+                vertices.as_ptr() as _,
+                gl::DYNAMIC_DRAW,
+            );
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+            gl_log_error!();
+        }
+    }
+}
+
+impl Drop for GlBlitPipe {
+    fn drop(&mut self) {
+        GlObject::Program.delete(self.program);
+        self.shaders.iter().for_each(|&s| GlObject::Shader.delete(s));
+        GlObject::Buffer.delete(self.vbo);
+        GlObject::VertexArray.delete(self.vao);
+    }
+}
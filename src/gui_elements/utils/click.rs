@@ -0,0 +1,49 @@
+use std::time::{Duration, Instant};
+
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(400);
+const DOUBLE_CLICK_DISTANCE: f64 = 4.0;
+
+/// Tells double clicks from single ones. Feed it every press of the button.
+#[derive(Debug, Clone, Default)]
+pub struct ClickTracker {
+    last: Option<(Instant, (f64, f64))>,
+}
+
+impl ClickTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a press at `pos` and returns true if it completes a double click.
+    pub fn press(&mut self, pos: (f64, f64)) -> bool {
+        self.press_at(Instant::now(), pos)
+    }
+
+    fn press_at(&mut self, now: Instant, pos: (f64, f64)) -> bool {
+        let double = match self.last {
+            Some((time, (x, y))) => {
+                now - time < DOUBLE_CLICK_TIME && ((x - pos.0).powi(2) + (y - pos.1).powi(2)).sqrt() < DOUBLE_CLICK_DISTANCE
+            }
+            None => false,
+        };
+        // a third click starts a new pair
+        self.last = if double { None } else { Some((now, pos)) };
+        double
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_double_click() {
+        let mut c = ClickTracker::new();
+        let t = Instant::now();
+        assert!(!c.press_at(t, (10.0, 10.0)));
+        assert!(c.press_at(t + Duration::from_millis(200), (11.0, 10.0)));
+        assert!(!c.press_at(t + Duration::from_millis(300), (11.0, 10.0)));
+        assert!(!c.press_at(t + Duration::from_millis(900), (11.0, 10.0)));
+        assert!(!c.press_at(t + Duration::from_millis(1000), (30.0, 10.0)));
+    }
+}
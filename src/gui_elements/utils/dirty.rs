@@ -0,0 +1,50 @@
+/// Remembers what an element showed when it was last drawn, so it can tell whether it has to
+/// be drawn again. `T` holds everything the picture depends on, e.g. the rect, parameter
+/// values and the hovered part.
+#[derive(Debug, Clone)]
+pub struct DirtyCheck<T> {
+    drawn: Option<T>,
+}
+
+impl<T: PartialEq> DirtyCheck<T> {
+    pub fn new() -> Self {
+        Self { drawn: None }
+    }
+
+    /// True if `state` differs from the one last drawn, or nothing was drawn yet.
+    pub fn is_dirty(&self, state: &T) -> bool {
+        self.drawn.as_ref() != Some(state)
+    }
+
+    /// Records `state` as drawn.
+    pub fn drawn(&mut self, state: T) {
+        self.drawn = Some(state);
+    }
+
+    /// Forces the next check to report a change, e.g. after a style change.
+    pub fn invalidate(&mut self) {
+        self.drawn = None;
+    }
+}
+
+impl<T: PartialEq> Default for DirtyCheck<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dirty_check() {
+        let mut d = DirtyCheck::new();
+        assert!(d.is_dirty(&1));
+        d.drawn(1);
+        assert!(!d.is_dirty(&1));
+        assert!(d.is_dirty(&2));
+        d.invalidate();
+        assert!(d.is_dirty(&1));
+    }
+}
@@ -0,0 +1,70 @@
+use gl::types::*;
+use crate::gui_elements::text::text_utils::Res;
+use crate::gui_elements::utils::gl::{gl_err_to_str, GlObject};
+use crate::gui_elements::utils::texture::{GlTexture, TextureFormat};
+use crate::gl_log_error;
+
+/// An offscreen render target backed by an RGBA texture, for effects that build up over
+/// several frames. Deleted when dropped.
+pub struct GlFramebuffer {
+    pub texture: GlTexture,
+    fbo: GLuint,
+}
+
+impl GlFramebuffer {
+    pub fn new(size: (u32, u32)) -> Res<Self> {
+        let texture = GlTexture::new(size, TextureFormat::Rgba, Some(&vec![0; size.0 as usize * size.1 as usize * 4]));
+        let fbo;
+        unsafe {
+            fbo = GlObject::Framebuffer.gen();
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+            gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, texture.name, 0);
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl_log_error!();
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                GlObject::Framebuffer.delete(fbo);
+                return Err(format!("framebuffer incomplete: 0x{:x}", status).into());
+            }
+        }
+        Ok(Self { texture, fbo })
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.texture.size()
+    }
+
+    /// Directs drawing into the texture and sets the viewport to cover it.
+    pub fn bind(&self) {
+        let (w, h) = self.size();
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+            gl::Viewport(0, 0, w as _, h as _);
+        }
+    }
+
+    /// Goes back to drawing into the window.
+    pub fn unbind(&self, window_size: (i32, i32)) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(0, 0, window_size.0, window_size.1);
+        }
+    }
+
+    /// Clears the whole texture. The framebuffer must be bound.
+    pub fn clear(&self, color: [f32; 4]) {
+        unsafe {
+            let mut previous = [0.0; 4];
+            gl::GetFloatv(gl::COLOR_CLEAR_VALUE, previous.as_mut_ptr());
+            gl::ClearColor(color[0], color[1], color[2], color[3]);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+            gl::ClearColor(previous[0], previous[1], previous[2], previous[3]);
+        }
+    }
+}
+
+impl Drop for GlFramebuffer {
+    fn drop(&mut self) {
+        GlObject::Framebuffer.delete(self.fbo);
+    }
+}
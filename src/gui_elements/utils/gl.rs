@@ -1,6 +1,7 @@
 use gl::types::*;
 use std::{ffi::CString, ptr, str};
 use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use vst_log::log;
 
 #[macro_export]
@@ -28,12 +29,97 @@ pub fn gl_err_to_str(err: u32) -> &'static str {
     }
 }
 
+/// The kinds of GL objects widgets own. Every object is made and deleted through this type,
+/// or [`compile_shader`] and [`link_program`], so the live ones can be counted and leaks
+/// reported when the editor closes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlObject {
+    Program,
+    Shader,
+    VertexArray,
+    Buffer,
+    Texture,
+    Framebuffer,
+}
+
+static LIVE: [AtomicUsize; 6] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+
+impl GlObject {
+    const ALL: [GlObject; 6] = [
+        GlObject::Program,
+        GlObject::Shader,
+        GlObject::VertexArray,
+        GlObject::Buffer,
+        GlObject::Texture,
+        GlObject::Framebuffer,
+    ];
+
+    /// Generates an object of this kind. Shaders and programs come from [`compile_shader`]
+    /// and [`link_program`] instead.
+    pub fn gen(self) -> GLuint {
+        let mut name = 0;
+        unsafe {
+            match self {
+                GlObject::VertexArray => gl::GenVertexArrays(1, &mut name),
+                GlObject::Buffer => gl::GenBuffers(1, &mut name),
+                GlObject::Texture => gl::GenTextures(1, &mut name),
+                GlObject::Framebuffer => gl::GenFramebuffers(1, &mut name),
+                GlObject::Program | GlObject::Shader => panic!("{:?} cannot be generated", self),
+            }
+        }
+        self.track(true);
+        name
+    }
+
+    /// Deletes `name`, which must have been made in the current context.
+    pub fn delete(self, name: GLuint) {
+        unsafe {
+            match self {
+                GlObject::Program => gl::DeleteProgram(name),
+                GlObject::Shader => gl::DeleteShader(name),
+                GlObject::VertexArray => gl::DeleteVertexArrays(1, &name),
+                GlObject::Buffer => gl::DeleteBuffers(1, &name),
+                GlObject::Texture => gl::DeleteTextures(1, &name),
+                GlObject::Framebuffer => gl::DeleteFramebuffers(1, &name),
+            }
+        }
+        self.track(false);
+    }
+
+    /// Counts an object of this kind as made or deleted.
+    pub(crate) fn track(self, created: bool) {
+        let live = &LIVE[self as usize];
+        if created {
+            live.fetch_add(1, Ordering::Relaxed);
+        } else {
+            live.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The objects of each kind made and not yet deleted, in all contexts of the process.
+pub fn live_objects() -> Vec<(GlObject, usize)> {
+    GlObject::ALL
+        .iter()
+        .map(|&kind| (kind, LIVE[kind as usize].load(Ordering::Relaxed)))
+        .filter(|&(_, n)| n > 0)
+        .collect()
+}
+
 pub fn compile_shader(src: &str, ty: GLenum) -> Result<GLuint, Box<dyn Error>> {
     let shader;
     unsafe {
+        let c_str = CString::new(src.as_bytes())?;
         shader = gl::CreateShader(ty);
+        GlObject::Shader.track(true);
         // Attempt to compile the shader
-        let c_str = CString::new(src.as_bytes())?;
         gl::ShaderSource(shader, 1, &c_str.as_ptr(), ptr::null());
         gl::CompileShader(shader);
 
@@ -52,6 +138,7 @@ pub fn compile_shader(src: &str, ty: GLenum) -> Result<GLuint, Box<dyn Error>> {
                 ptr::null_mut(),
                 buf.as_mut_ptr() as *mut GLchar,
             );
+            GlObject::Shader.delete(shader);
             let e = str::from_utf8(&buf)?;
             vst_log::log(e.into());
             return Err(e.into());
@@ -63,6 +150,7 @@ pub fn compile_shader(src: &str, ty: GLenum) -> Result<GLuint, Box<dyn Error>> {
 pub fn link_program(vs: GLuint, fs: GLuint) -> Result<GLuint, Box<dyn Error>> {
     unsafe {
         let program = gl::CreateProgram();
+        GlObject::Program.track(true);
         gl::AttachShader(program, vs);
         gl::AttachShader(program, fs);
         gl::LinkProgram(program);
@@ -81,10 +169,32 @@ pub fn link_program(vs: GLuint, fs: GLuint) -> Result<GLuint, Box<dyn Error>> {
                 ptr::null_mut(),
                 buf.as_mut_ptr() as *mut GLchar,
             );
+            GlObject::Program.delete(program);
             let e = str::from_utf8(&buf)?;
             vst_log::log(e.into());
             return Err(e.into());
         }
         Ok(program)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_live_objects() {
+        // no test makes real GL objects, and only this one counts textures
+        assert_eq!(None, live(GlObject::Texture));
+        GlObject::Texture.track(true);
+        GlObject::Texture.track(true);
+        GlObject::Texture.track(false);
+        assert_eq!(Some(1), live(GlObject::Texture));
+        GlObject::Texture.track(false);
+        assert_eq!(None, live(GlObject::Texture));
+    }
+
+    fn live(kind: GlObject) -> Option<usize> {
+        live_objects().into_iter().find(|&(k, _)| k == kind).map(|(_, n)| n)
+    }
+}
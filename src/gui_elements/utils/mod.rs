@@ -1,2 +1,7 @@
 pub mod t_matrix;
-pub mod gl;
\ No newline at end of file
+pub mod gl;
+pub mod texture;
+pub mod framebuffer;
+pub mod blit;
+pub mod click;
+pub mod dirty;
@@ -0,0 +1,12 @@
+#version 150
+
+uniform sampler2D image;
+uniform vec4 tint;
+
+in vec2 f_uv;
+
+out vec4 out_color;
+
+void main() {
+    out_color = texture(image, f_uv) * tint;
+}
@@ -0,0 +1,146 @@
+use gl::types::*;
+use std::ptr;
+use crate::gui_elements::utils::gl::{gl_err_to_str, GlObject};
+use crate::gl_log_error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextureFormat {
+    /// One byte per pixel, sampled as `.r`.
+    Red,
+    /// Four bytes per pixel.
+    Rgba,
+}
+
+impl TextureFormat {
+    pub fn bytes_per_pixel(&self) -> usize {
+        match self {
+            TextureFormat::Red => 1,
+            TextureFormat::Rgba => 4,
+        }
+    }
+
+    fn gl_format(&self) -> GLenum {
+        match self {
+            TextureFormat::Red => gl::RED,
+            TextureFormat::Rgba => gl::RGBA,
+        }
+    }
+
+    fn gl_internal_format(&self) -> GLenum {
+        match self {
+            TextureFormat::Red => gl::R8,
+            TextureFormat::Rgba => gl::RGBA8,
+        }
+    }
+}
+
+/// A 2D texture of 8 bit channels. Deleted when dropped, so it must not outlive its context.
+pub struct GlTexture {
+    pub name: GLuint,
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+}
+
+impl GlTexture {
+    /// Creates a texture with linear filtering and clamped edges. `data` must hold
+    /// `width * height` pixels if given, otherwise the contents start out undefined.
+    pub fn new((width, height): (u32, u32), format: TextureFormat, data: Option<&[u8]>) -> Self {
+        if let Some(data) = data {
+            assert_eq!(width as usize * height as usize * format.bytes_per_pixel(), data.len());
+        }
+        let name;
+        unsafe {
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            name = GlObject::Texture.gen();
+            gl::BindTexture(gl::TEXTURE_2D, name);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as _);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                format.gl_internal_format() as _,
+                width as _,
+                height as _,
+                0,
+                format.gl_format(),
+                gl::UNSIGNED_BYTE,
+                data.map(|d| d.as_ptr() as *const _).unwrap_or(ptr::null()),
+            );
+            gl_log_error!();
+        }
+        Self { name, width, height, format }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    /// Sets the wrap mode of both axes, e.g. `gl::REPEAT` for textures read circularly.
+    pub fn set_wrap(&self, wrap_s: GLenum, wrap_t: GLenum) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.name);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, wrap_s as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrap_t as _);
+            gl_log_error!();
+        }
+    }
+
+    /// Sets the min and mag filter, e.g. `gl::NEAREST` for pixel art.
+    pub fn set_filter(&self, filter: GLenum) {
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, self.name);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, filter as _);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, filter as _);
+            gl_log_error!();
+        }
+    }
+
+    /// Replaces the whole texture.
+    pub fn upload(&self, data: &[u8]) {
+        self.upload_region([0, 0, self.width, self.height], data);
+    }
+
+    /// Replaces the pixels in `rect` (`[x, y, width, height]`) with tightly packed `data`.
+    pub fn upload_region(&self, rect: [u32; 4], data: &[u8]) {
+        let [x, y, w, h] = rect;
+        assert!(x + w <= self.width && y + h <= self.height);
+        assert_eq!(w as usize * h as usize * self.format.bytes_per_pixel(), data.len());
+        unsafe {
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::BindTexture(gl::TEXTURE_2D, self.name);
+            gl::TexSubImage2D(
+                gl::TEXTURE_2D,
+                0,
+                x as _,
+                y as _,
+                w as _,
+                h as _,
+                self.format.gl_format(),
+                gl::UNSIGNED_BYTE,
+                data.as_ptr() as _,
+            );
+            gl_log_error!();
+        }
+    }
+
+    /// Binds the texture to texture unit `unit`.
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_2D, self.name);
+        }
+    }
+}
+
+impl Drop for GlTexture {
+    fn drop(&mut self) {
+        GlObject::Texture.delete(self.name);
+    }
+}
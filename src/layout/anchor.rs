@@ -0,0 +1,72 @@
+use crate::layout::{Align, Node};
+
+/// Content size of an anchor container, padding excluded: the largest child with its margin
+/// and offset.
+pub(crate) fn content_size(children: &[Node]) -> [f32; 2] {
+    let mut size = [0.0f32; 2];
+    for child in children {
+        let measured = child.measure();
+        for axis in 0..2 {
+            let extent = measured[axis] + child.style.margin.axis(axis) + child.style.offset[axis].abs();
+            size[axis] = size[axis].max(extent);
+        }
+    }
+    size
+}
+
+/// Rects of `children` pinned inside `content` by their [`Style::anchor`]. Per axis `Start`
+/// and `End` pin to that edge with the margin as distance, `Center` centers, and `Stretch`
+/// spans between both margins unless the child has a fixed size. The offset is added last.
+///
+/// [`Style::anchor`]: crate::layout::Style::anchor
+pub(crate) fn arrange(children: &[Node], content: [f32; 4]) -> Vec<[f32; 4]> {
+    children
+        .iter()
+        .map(|child| {
+            let s = &child.style;
+            let mut rect = [0.0; 4];
+            for axis in 0..2 {
+                let (origin, extent) = (content[axis], content[axis + 2]);
+                let room = (extent - s.margin.axis(axis)).max(0.0);
+                let size = match (s.anchor[axis], s.size[axis]) {
+                    (Align::Stretch, None) => s.clamp(axis, room),
+                    _ => child.measure()[axis],
+                };
+                let before = s.margin.start(axis);
+                let pos = match s.anchor[axis] {
+                    Align::Start | Align::Stretch => origin + before,
+                    Align::Center => origin + before + (room - size) / 2.0,
+                    Align::End => origin + extent - (s.margin.axis(axis) - before) - size,
+                };
+                rect[axis] = pos + s.offset[axis];
+                rect[axis + 2] = size;
+            }
+            rect
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::layout::{Align, Edges, Node, Style};
+
+    #[test]
+    fn test_anchor_layout() {
+        let logo = Style {
+            anchor: [Align::End, Align::Start],
+            size: [Some(60.0), Some(20.0)],
+            margin: Edges::all(8.0),
+            ..Style::default()
+        };
+        let knob = Style { anchor: [Align::Center, Align::Center], size: [Some(50.0), Some(50.0)], offset: [0.0, -10.0], ..Style::default() };
+        let footer = Style { anchor: [Align::Stretch, Align::End], size: [None, Some(24.0)], ..Style::default() };
+        let mut root = Node::anchor(
+            Style { padding: Edges::all(2.0), ..Style::default() },
+            vec![Node::widget(0, logo), Node::widget(1, knob), Node::widget(2, footer)],
+        );
+        root.layout((304.0, 204.0));
+        assert_eq!(Some([234.0, 10.0, 60.0, 20.0]), root.widget_rect(0));
+        assert_eq!(Some([127.0, 67.0, 50.0, 50.0]), root.widget_rect(1));
+        assert_eq!(Some([2.0, 178.0, 300.0, 24.0]), root.widget_rect(2));
+    }
+}
@@ -0,0 +1,262 @@
+use serde::Deserialize;
+use crate::layout::{Node, Style};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    /// Children left to right, the main axis is horizontal.
+    #[default]
+    Row,
+    /// Children top to bottom.
+    Column,
+}
+
+impl Direction {
+    fn main(&self) -> usize {
+        match self {
+            Direction::Row => 0,
+            Direction::Column => 1,
+        }
+    }
+}
+
+/// Placement of the children along the main axis when they do not fill it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum Justify {
+    #[default]
+    Start,
+    Center,
+    End,
+    /// First and last child at the edges, the rest spread evenly in between.
+    SpaceBetween,
+    /// Equal space on both sides of every child.
+    SpaceAround,
+    /// Equal space between the children and the edges.
+    SpaceEvenly,
+}
+
+/// Placement of a child across the main axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+    /// Fills the cross axis unless the child has a fixed size on it.
+    #[default]
+    Stretch,
+}
+
+/// Resolves the main axis sizes of items with the given base sizes and limits so they add up
+/// to `available`, following the flexbox rules: leftover space goes to the items by `grow`,
+/// overflow is taken away by `shrink` weighted by base size, and items hitting a limit are
+/// frozen there while the rest is distributed again.
+pub fn resolve_flexible(available: f32, items: &[FlexItem]) -> Vec<f32> {
+    let mut sizes: Vec<f32> = items.iter().map(|i| i.base.clamp_to(i.min, i.max)).collect();
+    let mut frozen = vec![false; items.len()];
+    let initial_free = available - sizes.iter().sum::<f32>();
+    let growing = initial_free > 0.0;
+    for (i, item) in items.iter().enumerate() {
+        // items that cannot flex in this direction keep their hypothetical size
+        let factor = if growing { item.grow } else { item.shrink };
+        if factor <= 0.0 || initial_free == 0.0 {
+            frozen[i] = true;
+        }
+    }
+
+    loop {
+        let unfrozen: Vec<usize> = (0..items.len()).filter(|i| !frozen[*i]).collect();
+        if unfrozen.is_empty() {
+            break;
+        }
+        let used: f32 = (0..items.len()).map(|i| if frozen[i] { sizes[i] } else { items[i].base }).sum();
+        let free = available - used;
+        let weight = |i: usize| if growing { items[i].grow } else { items[i].shrink * items[i].base };
+        let total: f32 = unfrozen.iter().map(|i| weight(*i)).sum();
+
+        let mut violation = 0.0;
+        let mut targets = Vec::with_capacity(unfrozen.len());
+        for &i in &unfrozen {
+            let share = if total > 0.0 { weight(i) / total } else { 0.0 };
+            let target = items[i].base + free * share;
+            let clamped = target.clamp_to(items[i].min, items[i].max);
+            violation += clamped - target;
+            targets.push((i, target, clamped));
+        }
+        for &(i, _, clamped) in &targets {
+            sizes[i] = clamped;
+        }
+        if violation.abs() < 1e-4 {
+            break;
+        }
+        // freeze the items clamped in the direction of the total violation and retry
+        for &(i, target, clamped) in &targets {
+            if (violation > 0.0 && clamped > target) || (violation < 0.0 && clamped < target) {
+                frozen[i] = true;
+            }
+        }
+    }
+    sizes
+}
+
+/// A child along the main axis, see [`resolve_flexible`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlexItem {
+    pub base: f32,
+    pub min: f32,
+    pub max: f32,
+    pub grow: f32,
+    pub shrink: f32,
+}
+
+trait ClampTo {
+    fn clamp_to(self, min: f32, max: f32) -> f32;
+}
+
+impl ClampTo for f32 {
+    /// Like `clamp` but `min` wins over a smaller `max` instead of panicking.
+    fn clamp_to(self, min: f32, max: f32) -> f32 {
+        self.min(max).max(min)
+    }
+}
+
+/// Offset of the first child and the extra space after every child for `justify`, given the
+/// space left over on the main axis.
+fn justify_spacing(justify: Justify, leftover: f32, count: usize) -> (f32, f32) {
+    let leftover = leftover.max(0.0);
+    let n = count as f32;
+    match justify {
+        Justify::Start => (0.0, 0.0),
+        Justify::Center => (leftover / 2.0, 0.0),
+        Justify::End => (leftover, 0.0),
+        Justify::SpaceBetween if count > 1 => (0.0, leftover / (n - 1.0)),
+        Justify::SpaceBetween => (0.0, 0.0),
+        Justify::SpaceAround => (leftover / n / 2.0, leftover / n),
+        Justify::SpaceEvenly => (leftover / (n + 1.0), leftover / (n + 1.0)),
+    }
+}
+
+/// Content size of a flex container with `children`, padding excluded.
+pub(crate) fn content_size(style: &Style, children: &[Node]) -> [f32; 2] {
+    let main = style.direction.main();
+    let cross = 1 - main;
+    let mut size = [0.0; 2];
+    for child in children {
+        let measured = child.measure();
+        let margin = [child.style.margin.axis(0), child.style.margin.axis(1)];
+        let base = child.style.basis.map(|b| child.style.clamp(main, b)).unwrap_or(measured[main]);
+        size[main] += base + margin[main];
+        size[cross] = f32::max(size[cross], measured[cross] + margin[cross]);
+    }
+    size[main] += style.gap * children.len().saturating_sub(1) as f32;
+    size
+}
+
+/// Rects of `children` laid out in `content` (`[x, y, width, height]`, padding already
+/// removed) according to the container's `style`.
+pub(crate) fn arrange(style: &Style, children: &[Node], content: [f32; 4]) -> Vec<[f32; 4]> {
+    if children.is_empty() {
+        return Vec::new();
+    }
+    let main = style.direction.main();
+    let cross = 1 - main;
+    let origin = [content[0], content[1]];
+    let extent = [content[2], content[3]];
+
+    let gaps = style.gap * (children.len() - 1) as f32;
+    let margins: f32 = children.iter().map(|c| c.style.margin.axis(main)).sum();
+    let items: Vec<FlexItem> = children
+        .iter()
+        .map(|c| FlexItem {
+            base: c.style.basis.unwrap_or_else(|| c.measure()[main]),
+            min: c.style.min[main],
+            max: c.style.max[main],
+            grow: c.style.grow,
+            shrink: c.style.shrink,
+        })
+        .collect();
+    let sizes = resolve_flexible(extent[main] - gaps - margins, &items);
+
+    let used = sizes.iter().sum::<f32>() + gaps + margins;
+    let (mut pos, spacing) = justify_spacing(style.justify, extent[main] - used, children.len());
+    pos += origin[main];
+
+    children
+        .iter()
+        .zip(sizes)
+        .map(|(child, main_size)| {
+            let s = &child.style;
+            let margin_before = [s.margin.start(0), s.margin.start(1)];
+            let cross_room = (extent[cross] - s.margin.axis(cross)).max(0.0);
+            let align = s.align_self.unwrap_or(style.align_items);
+            let cross_size = match (align, s.size[cross]) {
+                (Align::Stretch, None) => s.clamp(cross, cross_room),
+                _ => child.measure()[cross],
+            };
+            let cross_offset = match align {
+                Align::Start | Align::Stretch => 0.0,
+                Align::Center => (cross_room - cross_size) / 2.0,
+                Align::End => cross_room - cross_size,
+            };
+
+            let mut rect = [0.0; 4];
+            rect[main] = pos + margin_before[main];
+            rect[cross] = origin[cross] + margin_before[cross] + cross_offset;
+            rect[main + 2] = main_size;
+            rect[cross + 2] = cross_size;
+            pos += main_size + s.margin.axis(main) + style.gap + spacing;
+            rect
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::Edges;
+
+    fn item(base: f32, grow: f32, shrink: f32) -> FlexItem {
+        FlexItem { base, min: 0.0, max: f32::INFINITY, grow, shrink }
+    }
+
+    #[test]
+    fn test_grow_and_shrink() {
+        assert_eq!(vec![50.0, 150.0], resolve_flexible(200.0, &[item(0.0, 1.0, 1.0), item(0.0, 3.0, 1.0)]));
+        // shrinking is weighted by base size
+        assert_eq!(vec![50.0, 100.0], resolve_flexible(150.0, &[item(100.0, 0.0, 1.0), item(200.0, 0.0, 1.0)]));
+        // the first item hits its max, the rest goes to the second
+        let capped = FlexItem { max: 30.0, ..item(0.0, 1.0, 1.0) };
+        assert_eq!(vec![30.0, 70.0], resolve_flexible(100.0, &[capped, item(0.0, 1.0, 1.0)]));
+        // nothing flexes, sizes stay even if they overflow
+        assert_eq!(vec![80.0, 80.0], resolve_flexible(100.0, &[item(80.0, 0.0, 0.0), item(80.0, 0.0, 0.0)]));
+    }
+
+    #[test]
+    fn test_row_layout() {
+        let fixed = |w: f32| Style { size: [Some(w), Some(20.0)], ..Style::default() };
+        let mut root = Node::row(
+            Style { padding: Edges::all(10.0), gap: 5.0, align_items: Align::Center, ..Style::default() },
+            vec![
+                Node::widget(0, fixed(40.0)),
+                Node::widget(1, Style { grow: 1.0, align_self: Some(Align::Stretch), ..Style::default() }),
+                Node::widget(2, Style { margin: Edges::symmetric(5.0, 0.0), ..fixed(30.0) }),
+            ],
+        );
+        root.layout((200.0, 100.0));
+        assert_eq!(Some([10.0, 40.0, 40.0, 20.0]), root.widget_rect(0));
+        assert_eq!(Some([55.0, 10.0, 90.0, 80.0]), root.widget_rect(1));
+        assert_eq!(Some([155.0, 40.0, 30.0, 20.0]), root.widget_rect(2));
+    }
+
+    #[test]
+    fn test_justify() {
+        let fixed = || Style { size: [Some(20.0), Some(20.0)], ..Style::default() };
+        let mut column = Node::column(
+            Style { justify: Justify::SpaceBetween, align_items: Align::End, ..Style::default() },
+            vec![Node::widget(0, fixed()), Node::widget(1, fixed()), Node::widget(2, fixed())],
+        );
+        column.layout((100.0, 100.0));
+        assert_eq!(Some([80.0, 0.0, 20.0, 20.0]), column.widget_rect(0));
+        assert_eq!(Some([80.0, 40.0, 20.0, 20.0]), column.widget_rect(1));
+        assert_eq!(Some([80.0, 80.0, 20.0, 20.0]), column.widget_rect(2));
+        assert_eq!((20.0, 20.0), justify_spacing(Justify::SpaceEvenly, 60.0, 2));
+    }
+}
@@ -0,0 +1,275 @@
+use serde::Deserialize;
+use crate::layout::{Align, Node, Style};
+
+/// Size of a grid row or column.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum Track {
+    /// Fixed size in pixels.
+    Px(f32),
+    /// Share of the space left after fixed and auto tracks.
+    Fr(f32),
+    /// Fits the largest child placed only in this track.
+    Auto,
+}
+
+/// The tracks of a grid container. Children are placed with [`Style::cell`], children without
+/// a cell fill the free cells row by row. Rows added for children that do not fit are
+/// [`Track::Auto`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Grid {
+    pub columns: Vec<Track>,
+    pub rows: Vec<Track>,
+    pub column_gap: f32,
+    pub row_gap: f32,
+}
+
+impl Grid {
+    pub fn new(columns: Vec<Track>, rows: Vec<Track>) -> Self {
+        Self { columns, rows, ..Self::default() }
+    }
+
+    /// `columns` by `rows` equal tracks sharing the space.
+    pub fn uniform(columns: usize, rows: usize) -> Self {
+        Self::new(vec![Track::Fr(1.0); columns], vec![Track::Fr(1.0); rows])
+    }
+
+    pub fn gap(mut self, column_gap: f32, row_gap: f32) -> Self {
+        self.column_gap = column_gap;
+        self.row_gap = row_gap;
+        self
+    }
+}
+
+/// Where a child sits in a grid, counted from zero. Written `(column, row)` or
+/// `(column, row, column_span, row_span)` in UI files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(from = "CellRepr")]
+pub struct GridCell {
+    pub column: usize,
+    pub row: usize,
+    pub column_span: usize,
+    pub row_span: usize,
+}
+
+impl GridCell {
+    pub fn new(column: usize, row: usize) -> Self {
+        Self { column, row, column_span: 1, row_span: 1 }
+    }
+
+    pub fn span(mut self, columns: usize, rows: usize) -> Self {
+        self.column_span = columns.max(1);
+        self.row_span = rows.max(1);
+        self
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged, expecting = "expected (column, row) or (column, row, column_span, row_span)")]
+enum CellRepr {
+    Cell(usize, usize),
+    Span(usize, usize, usize, usize),
+}
+
+impl From<CellRepr> for GridCell {
+    fn from(repr: CellRepr) -> Self {
+        match repr {
+            CellRepr::Cell(column, row) => Self::new(column, row),
+            CellRepr::Span(column, row, columns, rows) => Self::new(column, row).span(columns, rows),
+        }
+    }
+}
+
+/// Gives every child a cell: explicit cells first, then the rest in the first free cells
+/// row by row. Spans wider than the grid are cut to fit.
+pub fn place_cells(grid: &Grid, children: &[Node]) -> Vec<GridCell> {
+    let columns = grid.columns.len().max(1);
+    let mut taken: Vec<Vec<bool>> = Vec::new();
+    let occupy = |taken: &mut Vec<Vec<bool>>, cell: &GridCell| {
+        while taken.len() < cell.row + cell.row_span {
+            taken.push(vec![false; columns]);
+        }
+        for row in &mut taken[cell.row..cell.row + cell.row_span] {
+            row[cell.column..cell.column + cell.column_span].iter_mut().for_each(|t| *t = true);
+        }
+    };
+    let fit = |cell: GridCell| {
+        let column = cell.column.min(columns - 1);
+        GridCell { column, column_span: cell.column_span.clamp(1, columns - column), row_span: cell.row_span.max(1), ..cell }
+    };
+
+    let mut cells: Vec<Option<GridCell>> = children.iter().map(|c| c.style.cell.map(fit)).collect();
+    for cell in cells.iter().flatten() {
+        occupy(&mut taken, cell);
+    }
+    let mut cursor = 0;
+    for cell in cells.iter_mut().filter(|c| c.is_none()) {
+        let free = |taken: &Vec<Vec<bool>>, i: usize| taken.get(i / columns).map(|r| !r[i % columns]).unwrap_or(true);
+        while !free(&taken, cursor) {
+            cursor += 1;
+        }
+        let placed = GridCell::new(cursor % columns, cursor / columns);
+        occupy(&mut taken, &placed);
+        *cell = Some(placed);
+    }
+    cells.into_iter().flatten().collect()
+}
+
+/// Resolves track sizes. `content` holds the size each track needs for its auto children.
+/// Fractional tracks share what is left of `available`, or take their content size if
+/// `available` is none.
+pub fn size_tracks(tracks: &[Track], content: &[f32], gap: f32, available: Option<f32>) -> Vec<f32> {
+    let gaps = gap * tracks.len().saturating_sub(1) as f32;
+    let fixed: f32 = tracks
+        .iter()
+        .zip(content)
+        .map(|(t, c)| match t {
+            Track::Px(px) => *px,
+            Track::Auto => *c,
+            Track::Fr(_) => 0.0,
+        })
+        .sum();
+    let fr_total: f32 = tracks.iter().map(|t| if let Track::Fr(fr) = t { *fr } else { 0.0 }).sum();
+    let fr_space = available.map(|a| (a - fixed - gaps).max(0.0));
+    tracks
+        .iter()
+        .zip(content)
+        .map(|(t, c)| match (t, fr_space) {
+            (Track::Px(px), _) => *px,
+            (Track::Auto, _) => *c,
+            (Track::Fr(fr), Some(space)) if fr_total > 0.0 => space * fr / fr_total,
+            (Track::Fr(_), _) => *c,
+        })
+        .collect()
+}
+
+/// All tracks of one axis, with implicit auto tracks added for `count`.
+fn tracks(explicit: &[Track], count: usize) -> Vec<Track> {
+    let mut tracks = explicit.to_vec();
+    tracks.resize(count.max(explicit.len()), Track::Auto);
+    tracks
+}
+
+/// Space each track of `axis` needs for the children spanning only that track.
+fn track_content(children: &[Node], cells: &[GridCell], axis: usize, count: usize) -> Vec<f32> {
+    let mut content = vec![0.0f32; count];
+    for (child, cell) in children.iter().zip(cells) {
+        let (index, span) = if axis == 0 { (cell.column, cell.column_span) } else { (cell.row, cell.row_span) };
+        if span == 1 {
+            let size = child.measure()[axis] + child.style.margin.axis(axis);
+            content[index] = content[index].max(size);
+        }
+    }
+    content
+}
+
+fn layout_tracks(grid: &Grid, children: &[Node], available: [Option<f32>; 2]) -> (Vec<GridCell>, [Vec<f32>; 2]) {
+    let cells = place_cells(grid, children);
+    let row_count = cells.iter().map(|c| c.row + c.row_span).max().unwrap_or(0);
+    let columns = tracks(&grid.columns, grid.columns.len().max(1));
+    let rows = tracks(&grid.rows, row_count);
+    let widths = size_tracks(&columns, &track_content(children, &cells, 0, columns.len()), grid.column_gap, available[0]);
+    let heights = size_tracks(&rows, &track_content(children, &cells, 1, rows.len()), grid.row_gap, available[1]);
+    (cells, [widths, heights])
+}
+
+/// Content size of a grid container, padding excluded.
+pub(crate) fn content_size(grid: &Grid, children: &[Node]) -> [f32; 2] {
+    let (_, [widths, heights]) = layout_tracks(grid, children, [None, None]);
+    let total = |sizes: &[f32], gap: f32| sizes.iter().sum::<f32>() + gap * sizes.len().saturating_sub(1) as f32;
+    [total(&widths, grid.column_gap), total(&heights, grid.row_gap)]
+}
+
+/// Rects of `children` in the grid filling `content`. Children are aligned in their cell on
+/// both axes by `align_self`, or the container's `align_items`.
+pub(crate) fn arrange(grid: &Grid, style: &Style, children: &[Node], content: [f32; 4]) -> Vec<[f32; 4]> {
+    let (cells, sizes) = layout_tracks(grid, children, [Some(content[2]), Some(content[3])]);
+    let gaps = [grid.column_gap, grid.row_gap];
+    let starts: Vec<Vec<f32>> = (0..2)
+        .map(|axis| {
+            let mut pos = content[axis];
+            sizes[axis]
+                .iter()
+                .map(|size| {
+                    let start = pos;
+                    pos += size + gaps[axis];
+                    start
+                })
+                .collect()
+        })
+        .collect();
+
+    children
+        .iter()
+        .zip(cells)
+        .map(|(child, cell)| {
+            let s = &child.style;
+            let align = s.align_self.unwrap_or(style.align_items);
+            let mut rect = [0.0; 4];
+            for axis in 0..2 {
+                let (index, span) = if axis == 0 { (cell.column, cell.column_span) } else { (cell.row, cell.row_span) };
+                let area = sizes[axis][index..index + span].iter().sum::<f32>() + gaps[axis] * (span - 1) as f32;
+                let room = (area - s.margin.axis(axis)).max(0.0);
+                let size = match (align, s.size[axis]) {
+                    (Align::Stretch, None) => s.clamp(axis, room),
+                    _ => child.measure()[axis],
+                };
+                let offset = match align {
+                    Align::Start | Align::Stretch => 0.0,
+                    Align::Center => (room - size) / 2.0,
+                    Align::End => room - size,
+                };
+                rect[axis] = starts[axis][index] + s.margin.start(axis) + offset;
+                rect[axis + 2] = size;
+            }
+            rect
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::Edges;
+
+    #[test]
+    fn test_track_sizes() {
+        let tracks = [Track::Px(50.0), Track::Fr(1.0), Track::Auto, Track::Fr(2.0)];
+        let content = [0.0, 10.0, 30.0, 0.0];
+        assert_eq!(vec![50.0, 30.0, 30.0, 60.0], size_tracks(&tracks, &content, 10.0, Some(200.0)));
+        assert_eq!(vec![50.0, 10.0, 30.0, 0.0], size_tracks(&tracks, &content, 10.0, None));
+    }
+
+    #[test]
+    fn test_auto_placement() {
+        let grid = Grid::uniform(3, 2);
+        let at = |cell: GridCell| Node::new(Style { cell: Some(cell), ..Style::default() });
+        let children = vec![at(GridCell::new(0, 0).span(2, 1)), Node::default(), Node::default(), at(GridCell::new(1, 1)), Node::default()];
+        let cells = place_cells(&grid, &children);
+        assert_eq!(GridCell::new(2, 0), cells[1]);
+        assert_eq!(GridCell::new(0, 1), cells[2]);
+        assert_eq!(GridCell::new(2, 1), cells[4]);
+        // spans are cut at the last column
+        let wide = [at(GridCell::new(2, 0).span(4, 1))];
+        assert_eq!(GridCell::new(2, 0), place_cells(&grid, &wide)[0]);
+    }
+
+    #[test]
+    fn test_grid_layout() {
+        let knob = |i: usize| Node::widget(i, Style { margin: Edges::all(5.0), ..Style::default() });
+        let label = Style {
+            cell: Some(GridCell::new(0, 1).span(2, 1)),
+            size: [Some(40.0), Some(20.0)],
+            align_self: Some(Align::Center),
+            ..Style::default()
+        };
+        let mut root = Node::grid(
+            Style::default(),
+            Grid::new(vec![Track::Fr(1.0), Track::Fr(1.0)], vec![Track::Fr(1.0), Track::Auto]).gap(10.0, 0.0),
+            vec![knob(0), knob(1), Node::widget(2, label)],
+        );
+        root.layout((210.0, 120.0));
+        assert_eq!(Some([5.0, 5.0, 90.0, 90.0]), root.widget_rect(0));
+        assert_eq!(Some([115.0, 5.0, 90.0, 90.0]), root.widget_rect(1));
+        assert_eq!(Some([85.0, 100.0, 40.0, 20.0]), root.widget_rect(2));
+    }
+}
@@ -0,0 +1,261 @@
+//! Computes widget rects from a tree of [`Node`]s, so editors do not have to position every
+//! control by hand. Containers lay out their children as flexbox style rows or columns, in a
+//! grid, or pinned to their edges, see [`Display`].
+
+pub mod anchor;
+pub mod flex;
+pub mod grid;
+
+use serde::Deserialize;
+use crate::gui_elements::utils::t_matrix::TMatrix;
+use crate::gui_elements::UIElement;
+pub use flex::{Align, Direction, Justify};
+pub use grid::{Grid, GridCell, Track};
+
+/// How a container places its children.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Display {
+    /// A row or column, see [`Style::direction`].
+    #[default]
+    Flex,
+    Grid(Grid),
+    /// Every child pinned on its own, see [`Style::anchor`].
+    Anchor,
+}
+
+/// Space around the four sides of a box, in pixels. Written as one number for all sides,
+/// `(horizontal, vertical)` or `(left, top, right, bottom)` in UI files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(from = "EdgesRepr")]
+pub struct Edges {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl Edges {
+    pub const ZERO: Self = Self { left: 0.0, top: 0.0, right: 0.0, bottom: 0.0 };
+
+    pub fn all(v: f32) -> Self {
+        Self { left: v, top: v, right: v, bottom: v }
+    }
+
+    /// `h` on the left and right, `v` on the top and bottom.
+    pub fn symmetric(h: f32, v: f32) -> Self {
+        Self { left: h, top: v, right: h, bottom: v }
+    }
+
+    /// Total on an axis, 0 for horizontal and 1 for vertical.
+    pub fn axis(&self, axis: usize) -> f32 {
+        match axis {
+            0 => self.left + self.right,
+            _ => self.top + self.bottom,
+        }
+    }
+
+    /// Space before the content on an axis.
+    pub fn start(&self, axis: usize) -> f32 {
+        match axis {
+            0 => self.left,
+            _ => self.top,
+        }
+    }
+
+    /// Shrinks `rect` (`[x, y, width, height]`) by the edges, never below zero size.
+    pub fn inset(&self, rect: [f32; 4]) -> [f32; 4] {
+        let [x, y, w, h] = rect;
+        [x + self.left, y + self.top, (w - self.axis(0)).max(0.0), (h - self.axis(1)).max(0.0)]
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged, expecting = "expected 1, 2 or 4 numbers")]
+enum EdgesRepr {
+    All(f32),
+    Symmetric(f32, f32),
+    Sides(f32, f32, f32, f32),
+}
+
+impl From<EdgesRepr> for Edges {
+    fn from(repr: EdgesRepr) -> Self {
+        match repr {
+            EdgesRepr::All(v) => Self::all(v),
+            EdgesRepr::Symmetric(h, v) => Self::symmetric(h, v),
+            EdgesRepr::Sides(left, top, right, bottom) => Self { left, top, right, bottom },
+        }
+    }
+}
+
+/// How a node sizes itself and places its children. Sizes are in pixels, two element arrays
+/// are `[width, height]`.
+#[derive(Debug, Clone)]
+pub struct Style {
+    /// Fixed size per axis, none to size from the content (or stretch, see [`Align`]).
+    pub size: [Option<f32>; 2],
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+    /// Share of the leftover space along the parent's main axis.
+    pub grow: f32,
+    /// Share of the overflow taken away when the children do not fit, weighted by their size.
+    pub shrink: f32,
+    /// Main axis size before growing and shrinking, overrides `size` on that axis.
+    pub basis: Option<f32>,
+    pub margin: Edges,
+    pub padding: Edges,
+    /// Overrides the parent's `align_items`. Applies to both axes in a grid.
+    pub align_self: Option<Align>,
+    /// Cell of the child in a grid parent, none to take the next free one.
+    pub cell: Option<GridCell>,
+    /// Horizontal and vertical pinning of the child in an anchor parent. The margin is the
+    /// distance from the pinned edge.
+    pub anchor: [Align; 2],
+    /// Extra displacement of the child in an anchor parent.
+    pub offset: [f32; 2],
+    pub display: Display,
+    /// How children of a flex container are stacked.
+    pub direction: Direction,
+    pub justify: Justify,
+    pub align_items: Align,
+    /// Space between children along the main axis.
+    pub gap: f32,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            size: [None, None],
+            min: [0.0, 0.0],
+            max: [f32::INFINITY, f32::INFINITY],
+            grow: 0.0,
+            shrink: 1.0,
+            basis: None,
+            margin: Edges::ZERO,
+            padding: Edges::ZERO,
+            align_self: None,
+            cell: None,
+            anchor: [Align::Start, Align::Start],
+            offset: [0.0, 0.0],
+            display: Display::Flex,
+            direction: Direction::Row,
+            justify: Justify::Start,
+            align_items: Align::Stretch,
+            gap: 0.0,
+        }
+    }
+}
+
+impl Style {
+    fn clamp(&self, axis: usize, v: f32) -> f32 {
+        v.min(self.max[axis]).max(self.min[axis])
+    }
+}
+
+/// A box in the layout tree. Nodes with a `widget` index place that widget of the list passed
+/// to [`Node::apply`].
+#[derive(Debug, Clone, Default)]
+pub struct Node {
+    pub style: Style,
+    pub children: Vec<Node>,
+    pub widget: Option<usize>,
+    rect: [f32; 4],
+}
+
+impl Node {
+    pub fn new(style: Style) -> Self {
+        Self { style, ..Self::default() }
+    }
+
+    /// A node that places `widget`.
+    pub fn widget(index: usize, style: Style) -> Self {
+        Self { style, widget: Some(index), ..Self::default() }
+    }
+
+    /// A row of `children`.
+    pub fn row(style: Style, children: Vec<Node>) -> Self {
+        Self { style: Style { direction: Direction::Row, ..style }, children, ..Self::default() }
+    }
+
+    /// A column of `children`.
+    pub fn column(style: Style, children: Vec<Node>) -> Self {
+        Self { style: Style { direction: Direction::Column, ..style }, children, ..Self::default() }
+    }
+
+    /// A grid of `children`.
+    pub fn grid(style: Style, grid: Grid, children: Vec<Node>) -> Self {
+        Self { style: Style { display: Display::Grid(grid), ..style }, children, ..Self::default() }
+    }
+
+    /// `children` pinned to the node's edges or center.
+    pub fn anchor(style: Style, children: Vec<Node>) -> Self {
+        Self { style: Style { display: Display::Anchor, ..style }, children, ..Self::default() }
+    }
+
+    pub fn child(mut self, child: Node) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    /// `[x, y, width, height]` in window pixels from the last [`Node::layout`].
+    pub fn rect(&self) -> [f32; 4] {
+        self.rect
+    }
+
+    /// Moves drawing in local coordinates to the node's top left corner.
+    pub fn transform(&self) -> TMatrix {
+        TMatrix::translation(self.rect[0], self.rect[1])
+    }
+
+    /// Lays the tree out in a window of `size`. The root fills the window minus its margin.
+    pub fn layout(&mut self, size: (f32, f32)) {
+        let rect = self.style.margin.inset([0.0, 0.0, size.0, size.1]);
+        self.place(rect);
+    }
+
+    /// Gives the node its rect and lays out its children inside.
+    fn place(&mut self, rect: [f32; 4]) {
+        self.rect = rect;
+        let content = self.style.padding.inset(rect);
+        let rects = match &self.style.display {
+            Display::Flex => flex::arrange(&self.style, &self.children, content),
+            Display::Grid(grid) => grid::arrange(grid, &self.style, &self.children, content),
+            Display::Anchor => anchor::arrange(&self.children, content),
+        };
+        for (child, rect) in self.children.iter_mut().zip(rects) {
+            child.place(rect);
+        }
+    }
+
+    /// Size the node wants without outside constraints, margin excluded.
+    pub fn measure(&self) -> [f32; 2] {
+        let content = match &self.style.display {
+            Display::Flex => flex::content_size(&self.style, &self.children),
+            Display::Grid(grid) => grid::content_size(grid, &self.children),
+            Display::Anchor => anchor::content_size(&self.children),
+        };
+        let padding = [self.style.padding.axis(0), self.style.padding.axis(1)];
+        let size = |axis: usize| {
+            let v = self.style.size[axis].unwrap_or(content[axis] + padding[axis]);
+            self.style.clamp(axis, v)
+        };
+        [size(0), size(1)]
+    }
+
+    /// Hands every widget node's rect to its widget. Indices out of range are skipped.
+    pub fn apply(&self, widgets: &mut [Box<dyn UIElement>]) {
+        if let Some(widget) = self.widget.and_then(|i| widgets.get_mut(i)) {
+            widget.set_rect(self.rect);
+        }
+        for child in &self.children {
+            child.apply(widgets);
+        }
+    }
+
+    /// Finds the rect of the node placing `widget`.
+    pub fn widget_rect(&self, widget: usize) -> Option<[f32; 4]> {
+        if self.widget == Some(widget) {
+            return Some(self.rect);
+        }
+        self.children.iter().find_map(|c| c.widget_rect(widget))
+    }
+}
@@ -6,68 +6,282 @@ use vst::editor::Editor;
 use std::marker::PhantomData;
 use std::os::raw::c_void;
 use std::sync::mpsc::Receiver;
-use std::thread::{JoinHandle, spawn};
 
 use parking_lot::Mutex;
 use std::sync::Arc;
 
-use glfw::{Action, Context, Key, Window, WindowEvent, Glfw, WindowHint};
+use glfw::{Action, Context, Key, Window, WindowEvent, WindowHint};
 use winapi::um::winuser::SetParent;
 use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
 use winapi::shared::windef::HWND;
 
+pub mod animation;
+pub mod channel;
+pub mod curve;
+pub mod dsp;
 pub mod gui_elements;
+pub mod layout;
+pub mod params;
+pub mod pattern;
+pub mod render;
+pub mod runtime;
+pub mod transport;
+pub mod ui_file;
+
+use animation::Clock;
+use gui_elements::UIElement;
+use gui_elements::theme::Theme;
+use layout::Node;
+use render::Scene;
+use render::thread::{RenderMessage, RenderThread};
+use render::{FramePacing, FrameStats};
+use runtime::GlfwRuntime;
+use ui_file::{UiSource, WidgetFactory};
+
+/// Creates the editor's widgets, see [`VstEditor::on_open`].
+pub type WidgetBuilder = Box<dyn FnMut() -> Vec<Box<dyn UIElement>>>;
+
+/// Creates the editor's widgets on its render thread, see [`VstEditor::use_render_thread`].
+pub type SendWidgetBuilder = Box<dyn FnMut() -> Vec<Box<dyn UIElement>> + Send>;
 
 pub struct VstEditor
 {
-    size: (u32, u32),
     position: (i32, i32),
     window: Option<Window>,
     events: Option<Receiver<(f64, WindowEvent)>>,
-    glfw: Glfw,
-    color: [f32; 3],
+    /// Held from `open` to `close`, see [`runtime`].
+    runtime: Option<GlfwRuntime>,
     is_open: bool,
+    idle_tasks: Vec<Box<dyn FnMut() + Send>>,
+    builder: Option<WidgetBuilder>,
+    scene: Scene,
+    ui: Option<(UiSource, WidgetFactory)>,
+    #[cfg(debug_assertions)]
+    ui_watcher: Option<ui_file::watch::FileWatcher>,
+    /// Set by [`VstEditor::use_render_thread`]. The builder is away while the thread runs.
+    threaded: Option<(FramePacing, Option<SendWidgetBuilder>)>,
+    render_thread: Option<RenderThread>,
 }
 
 impl VstEditor {
-    pub fn new(size: (u32, u32), position: (i32, i32), color: [f32; 3]) -> Self {
-        let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
-        glfw.window_hint(WindowHint::Decorated(false));
-        glfw.window_hint(WindowHint::Decorated(false));
-        glfw.window_hint(WindowHint::Visible(false));
-
-        
+    pub fn new(size: (u32, u32), position: (i32, i32), theme: Theme) -> Self {
         Self {
-            size,
             position,
             window: None,
             events: None,
-            glfw: glfw,
-            color,
+            runtime: None,
             is_open: false,
+            idle_tasks: Vec::new(),
+            builder: None,
+            scene: Scene::new(size, theme),
+            ui: None,
+            #[cfg(debug_assertions)]
+            ui_watcher: None,
+            threaded: None,
+            render_thread: None,
+        }
+    }
+
+    /// Replaces the clock animations are timed with, e.g. by an
+    /// [`animation::ManualClock`] to step them in tests. A render thread always uses the
+    /// system clock.
+    pub fn set_clock<C: Clock + 'static>(&mut self, clock: C) {
+        self.scene.set_clock(Box::new(clock));
+    }
+
+    /// Draws the next frame even if no widget reports a change, see
+    /// [`UIElement::is_dirty`].
+    pub fn invalidate(&mut self) {
+        self.scene.invalidate();
+        self.send(RenderMessage::Invalidate);
+    }
+
+    /// True if a widget or the log console reported a running animation last frame.
+    pub fn is_animating(&mut self) -> bool {
+        match self.frame_stats() {
+            Some(stats) => stats.animating,
+            None => self.scene.is_animating(),
+        }
+    }
+
+    /// Draws on a dedicated thread from the next open on, instead of in `idle`. The GL
+    /// context is made current there, and frames are paced by vsync or a timer as `pacing`
+    /// says. `idle` then only runs the idle tasks and passes window events on.
+    ///
+    /// The widgets are made by `builder` on the render thread, which takes the place of
+    /// [`VstEditor::on_open`] and [`VstEditor::load_ui`]. Layout, theme and size changes are
+    /// sent over as they happen.
+    pub fn use_render_thread<F>(&mut self, pacing: FramePacing, builder: F)
+    where
+        F: FnMut() -> Vec<Box<dyn UIElement>> + Send + 'static,
+    {
+        self.threaded = Some((pacing, Some(Box::new(builder))));
+    }
+
+    /// The render thread's latest report, if it is running.
+    pub fn frame_stats(&mut self) -> Option<FrameStats> {
+        self.render_thread.as_mut().map(|t| t.stats())
+    }
+
+    fn send(&self, message: RenderMessage) {
+        if let Some(thread) = self.render_thread.as_ref() {
+            thread.send(message);
+        }
+    }
+
+    /// Registers `f` to be run at the start of every `idle` call while the editor is open.
+    /// This is where consumers of [`channel`] queues should be drained.
+    pub fn on_idle<F: FnMut() + Send + 'static>(&mut self, f: F) {
+        self.idle_tasks.push(Box::new(f));
+    }
+
+    /// Sets the function that creates the widgets. It is called every time the editor opens,
+    /// with the GL context current, since widgets own GL objects that do not outlive a close.
+    pub fn on_open<F: FnMut() -> Vec<Box<dyn UIElement>> + 'static>(&mut self, f: F) {
+        self.builder = Some(Box::new(f));
+    }
+
+    /// Sets the layout that positions the widgets made by [`VstEditor::on_open`]. Widget nodes
+    /// refer to widgets by their index in that list. The layout is redone whenever the window
+    /// size changes.
+    pub fn set_layout(&mut self, layout: Node) {
+        if self.render_thread.is_some() {
+            self.send(RenderMessage::Layout(layout.clone()));
+        }
+        self.scene.set_layout(layout);
+    }
+
+    /// Builds the widgets and layout from a UI file on every open instead of using
+    /// [`VstEditor::on_open`] and [`VstEditor::set_layout`]. In debug builds a
+    /// [`UiSource::File`] is watched and the UI rebuilt when it changes, keeping the old UI if
+    /// the new file has errors.
+    pub fn load_ui(&mut self, source: UiSource, factory: WidgetFactory) {
+        self.ui = Some((source, factory));
+        if self.is_open && self.render_thread.is_none() {
+            // the old widgets' GL objects are deleted in the editor's own context
+            self.make_current();
+            self.build_ui();
+        }
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.scene.theme
+    }
+
+    /// Restyles the window background, the log console and every widget. Widgets made later
+    /// by [`VstEditor::on_open`] or a UI file get the theme as well.
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.send(RenderMessage::Theme(theme.clone()));
+        // widgets remake fonts and textures in the editor's own context
+        self.make_current();
+        self.scene.set_theme(theme);
+    }
+
+    /// Reads a theme file, see [`Theme::from_ron`], and switches to it. On error the current
+    /// theme is kept.
+    pub fn load_theme<P: AsRef<std::path::Path>>(&mut self, path: P) -> Result<(), Box<dyn std::error::Error>> {
+        self.set_theme(Theme::load(path)?);
+        Ok(())
+    }
+
+    fn build_ui(&mut self) {
+        let (source, factory) = match self.ui.as_mut() {
+            Some(ui) => ui,
+            None => return,
+        };
+        #[cfg(debug_assertions)]
+        if let (UiSource::File(path), None) = (&*source, &self.ui_watcher) {
+            self.ui_watcher = Some(ui_file::watch::FileWatcher::new(path.clone()));
+        }
+        match source.read().and_then(|text| ui_file::build(&text, factory)) {
+            Ok((widgets, layout)) => {
+                self.scene.layout = Some(layout);
+                self.scene.set_widgets(widgets);
+            }
+            Err(e) => vst_log::log(format!("failed to build UI: {}", e)),
+        }
+    }
+
+    /// Resizes the editor window, e.g. after the host agreed to a new size.
+    pub fn set_size(&mut self, size: (u32, u32)) {
+        if let Some(window) = self.window.as_mut() {
+            window.set_size(size.0 as i32, size.1 as i32);
         }
+        self.send(RenderMessage::Size(size));
+        // widgets remake framebuffers in the editor's own context
+        self.make_current();
+        self.scene.set_size(size);
+    }
+
+    /// Makes the editor's context current unless it is closed or a render thread holds it.
+    fn make_current(&mut self) {
+        if self.render_thread.is_none() {
+            if let Some(window) = self.window.as_mut() {
+                window.make_current();
+            }
+        }
+    }
+
+    /// Hands the context to a new render thread. The builder comes back when it stops.
+    fn start_render_thread(&mut self, window: &mut Window) -> bool {
+        let (pacing, builder) = match self.threaded.as_mut() {
+            Some((pacing, builder)) => (*pacing, builder),
+            None => return false,
+        };
+        let builder = match builder.take() {
+            Some(b) => b,
+            None => {
+                vst_log::log("render thread lost its widget builder".to_string());
+                return false;
+            }
+        };
+        self.runtime.as_ref().unwrap().glfw().set_swap_interval(pacing.swap_interval());
+        glfw::make_context_current(None);
+        let scene = (self.scene.size, self.scene.theme.clone(), self.scene.layout.clone());
+        self.render_thread = Some(RenderThread::spawn(window.render_context(), pacing, scene, builder));
+        true
     }
 }
 
 impl Editor for VstEditor {
     fn size(&self) -> (i32, i32) {
-        (self.size.0 as i32, self.size.1 as i32)
+        (self.scene.size.0 as i32, self.scene.size.1 as i32)
     }
     fn position(&self) -> (i32, i32) {
         (0, 0)
     }
     fn open(&mut self, parent: *mut c_void) -> bool {
-        let (mut window, events) = self.glfw.create_window(self.size.0, self.size.1, "", glfw::WindowMode::Windowed)
+        // GLFW is initialized on the first open, and a host may open editors on any thread
+        let runtime = match GlfwRuntime::acquire() {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                vst_log::log(format!("failed to open editor: {}", e));
+                return false;
+            }
+        };
+        // hints are shared by every window in the process, another editor may have set some
+        let mut glfw = runtime.glfw();
+        self.runtime = Some(runtime);
+        glfw.default_window_hints();
+        glfw.window_hint(WindowHint::Decorated(false));
+        glfw.window_hint(WindowHint::Visible(false));
+        let (mut window, events) = glfw.create_window(self.scene.size.0, self.scene.size.1, "", glfw::WindowMode::Windowed)
             .expect("Failed to create GLFW window.");
         window.set_pos(0, 0);
         window.make_current();
         
         window.set_key_polling(true);
+        window.set_char_polling(true);
+        window.set_scroll_polling(true);
+        window.set_mouse_button_polling(true);
+        window.set_cursor_pos_polling(true);
+        window.set_size_polling(true);
+        // frames are only drawn when something changed, so the window has to say when its
+        // contents were lost
+        window.set_refresh_polling(true);
+        window.set_focus_polling(true);
+        window.set_iconify_polling(true);
         gl::load_with(|s| window.get_proc_address(s) as *const _);
-        unsafe {
-            gl::Enable(gl::BLEND);
-            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
-        }
         let window_handle = match window.raw_window_handle() {
             RawWindowHandle::Win32(h) => {
                 h.hwnd
@@ -76,6 +290,14 @@ impl Editor for VstEditor {
         };
         unsafe { SetParent(window_handle as HWND, parent as HWND); }
         
+        if !self.start_render_thread(&mut window) {
+            self.scene.open();
+            if let Some(builder) = self.builder.as_mut() {
+                self.scene.set_widgets(builder());
+            }
+            self.build_ui();
+        }
+
         window.show();
         self.window = Some(window);
         self.events = Some(events);
@@ -89,9 +311,26 @@ impl Editor for VstEditor {
 
     fn close(&mut self) {
         if self.is_open() {
+            match self.render_thread.take() {
+                Some(thread) => {
+                    let builder = thread.stop();
+                    if let Some((_, slot)) = self.threaded.as_mut() {
+                        *slot = builder;
+                    }
+                }
+                None => {
+                    self.window.as_mut().unwrap().make_current();
+                    self.scene.close();
+                }
+            }
+            #[cfg(debug_assertions)]
+            {
+                self.ui_watcher = None;
+            }
             let window = self.window.take().unwrap();
             window.close();
             self.events = None;
+            self.runtime = None;
             self.is_open = false;
         }
         
@@ -99,20 +338,42 @@ impl Editor for VstEditor {
 
     fn idle(&mut self) {
         if self.is_open() {
+            // other editors on this thread make their own contexts current
+            self.make_current();
+            #[cfg(debug_assertions)]
+            if self.ui_watcher.as_mut().is_some_and(|w| w.changed()) {
+                self.build_ui();
+            }
+
             let window = self.window.as_mut().unwrap();
             let events = self.events.as_mut().unwrap();
-            let glfw = &mut self.glfw;
+            let mut glfw = self.runtime.as_ref().unwrap().glfw();
     
+            for task in self.idle_tasks.iter_mut() {
+                task();
+            }
+
             // Poll for and process events
             glfw.poll_events();
+            for (_, event) in glfw::flush_messages(events) {
+                match self.render_thread.as_ref() {
+                    Some(thread) => {
+                        if let WindowEvent::Size(w, h) = event {
+                            self.scene.size = (w.max(0) as u32, h.max(0) as u32);
+                        }
+                        thread.send(RenderMessage::Event(event));
+                    }
+                    None => self.scene.handle_event(&event),
+                }
+            }
             /*for (_, event) in glfw::flush_messages(&events) {
                 match event {
                     glfw::WindowEvent::Key(Key::A, _, Action::Press, _) => {
                         unsafe {
-                            gl::ClearColor(self.color[0], self.color[1], self.color[2], 1.0);
+                            gl::ClearColor(self.theme.background[0], self.theme.background[1], self.theme.background[2], 1.0);
                             gl::Clear(gl::COLOR_BUFFER_BIT);
                             window.swap_buffers();
-                            gl::ClearColor(self.color[0], self.color[1], self.color[2], 1.0);
+                            gl::ClearColor(self.theme.background[0], self.theme.background[1], self.theme.background[2], 1.0);
                             gl::Clear(gl::COLOR_BUFFER_BIT);
                         }
                     },
@@ -128,26 +389,19 @@ impl Editor for VstEditor {
                     _ => {},
                 }
             }*/
-            unsafe {
-                gl::Clear(gl::COLOR_BUFFER_BIT);
+
+            if self.render_thread.is_none() && self.scene.draw() {
+                window.swap_buffers();
             }
-            
-            let mut text = match gui_elements::text::UIText::new(
-                "fortnite battle pass", 
-                50.0, 
-                [1.0, 1.0, 1.0, 1.0], 
-                [100.0,100.0], 
-                glyph_brush::ab_glyph::FontRef::try_from_slice(include_bytes!("gui_elements/text/fonts/source-code-pro.regular.ttf")).unwrap()
-            ) {
-                Ok(v) => v,
-                Err(e) => {
-                    vst_log::log(e.to_string());
-                    panic!("bruh");
-                }
-            };
-            text.render((640, 320));
-            window.swap_buffers();
         }
         
     }
-}
\ No newline at end of file
+}
+
+impl Drop for VstEditor {
+    /// Hosts may drop an editor without closing it first. Closing here deletes the GL objects
+    /// while the window's context still exists.
+    fn drop(&mut self) {
+        self.close();
+    }
+}
@@ -0,0 +1,203 @@
+use std::sync::Arc;
+use vst::host::Host;
+use vst::plugin::PluginParameters;
+
+/// Where widgets read and write plugin parameters. Values are normalized to 0..1 like VST
+/// parameters. Every change made by a user gesture is wrapped in `begin_edit` / `end_edit`
+/// so the host records it as one automation edit.
+pub trait ParamHost {
+    fn get(&self, index: i32) -> f32;
+    fn begin_edit(&self, index: i32);
+    /// Sets the parameter and tells the host about the change.
+    fn set(&self, index: i32, value: f32);
+    fn end_edit(&self, index: i32);
+}
+
+/// Forwards to the plugin's parameter object and the host callback.
+pub struct VstParamHost<P, H> {
+    pub params: Arc<P>,
+    pub host: H,
+}
+
+impl<P: PluginParameters, H: Host> VstParamHost<P, H> {
+    pub fn new(params: Arc<P>, host: H) -> Self {
+        Self { params, host }
+    }
+}
+
+impl<P: PluginParameters, H: Host> ParamHost for VstParamHost<P, H> {
+    fn get(&self, index: i32) -> f32 {
+        self.params.get_parameter(index)
+    }
+
+    fn begin_edit(&self, index: i32) {
+        self.host.begin_edit(index);
+    }
+
+    fn set(&self, index: i32, value: f32) {
+        let value = value.clamp(0.0, 1.0);
+        self.params.set_parameter(index, value);
+        self.host.automate(index, value);
+    }
+
+    fn end_edit(&self, index: i32) {
+        self.host.end_edit(index);
+    }
+}
+
+/// How a parameter's normalized value maps to the value a widget works with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Taper {
+    Linear { min: f32, max: f32 },
+    /// Equal ratios get equal travel, e.g. frequencies. `min` must be positive.
+    Log { min: f32, max: f32 },
+}
+
+impl Taper {
+    pub fn to_plain(&self, normalized: f32) -> f32 {
+        let n = normalized.clamp(0.0, 1.0);
+        match *self {
+            Taper::Linear { min, max } => min + (max - min) * n,
+            Taper::Log { min, max } => min * (max / min).powf(n),
+        }
+    }
+
+    pub fn to_normalized(&self, plain: f32) -> f32 {
+        let n = match *self {
+            Taper::Linear { min, max } => (plain - min) / (max - min),
+            Taper::Log { min, max } => (plain / min).ln() / (max / min).ln(),
+        };
+        n.clamp(0.0, 1.0)
+    }
+}
+
+/// A parameter index with the taper used to show it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Binding {
+    pub index: i32,
+    pub taper: Taper,
+}
+
+impl Binding {
+    pub fn new(index: i32, taper: Taper) -> Self {
+        Self { index, taper }
+    }
+
+    pub fn get(&self, host: &dyn ParamHost) -> f32 {
+        self.taper.to_plain(host.get(self.index))
+    }
+
+    /// Sets the plain value. Must be called between `begin_edit` and `end_edit`.
+    pub fn set(&self, host: &dyn ParamHost, plain: f32) {
+        host.set(self.index, self.taper.to_normalized(plain));
+    }
+}
+
+/// The parameters one drag is editing. Beginning while a gesture is open does nothing, so
+/// each `begin_edit` is matched by exactly one `end_edit`.
+#[derive(Debug, Default)]
+pub struct Gesture {
+    open: Vec<i32>,
+}
+
+impl Gesture {
+    /// Begins editing `indices` unless a gesture is open already or `indices` is empty.
+    /// Returns true if it began.
+    pub fn begin(&mut self, host: &dyn ParamHost, indices: &[i32]) -> bool {
+        if self.is_open() || indices.is_empty() {
+            return false;
+        }
+        for &index in indices {
+            host.begin_edit(index);
+        }
+        self.open = indices.to_vec();
+        true
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.open.is_empty()
+    }
+
+    /// Ends the open gesture. Returns false if there was none.
+    pub fn end(&mut self, host: &dyn ParamHost) -> bool {
+        let open = std::mem::take(&mut self.open);
+        for &index in &open {
+            host.end_edit(index);
+        }
+        !open.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    /// Records the calls widgets make.
+    #[derive(Default)]
+    struct RecordingHost {
+        calls: RefCell<Vec<String>>,
+    }
+
+    impl ParamHost for RecordingHost {
+        fn get(&self, _index: i32) -> f32 {
+            0.5
+        }
+
+        fn begin_edit(&self, index: i32) {
+            self.calls.borrow_mut().push(format!("begin {}", index));
+        }
+
+        fn set(&self, index: i32, value: f32) {
+            self.calls.borrow_mut().push(format!("set {} {}", index, value));
+        }
+
+        fn end_edit(&self, index: i32) {
+            self.calls.borrow_mut().push(format!("end {}", index));
+        }
+    }
+
+    #[test]
+    fn test_gesture_stays_balanced() {
+        let host = RecordingHost::default();
+        let freq = Binding::new(0, Taper::Linear { min: 0.0, max: 100.0 });
+        let mut gesture = Gesture::default();
+
+        assert!(gesture.begin(&host, &[0, 1]));
+        freq.set(&host, 25.0);
+        // a second press while dragging must not open another gesture
+        assert!(!gesture.begin(&host, &[2]));
+        freq.set(&host, 50.0);
+        assert!(gesture.end(&host));
+        // neither may a second release close one again
+        assert!(!gesture.end(&host));
+
+        let expected = ["begin 0", "begin 1", "set 0 0.25", "set 0 0.5", "end 0", "end 1"];
+        assert_eq!(expected.to_vec(), *host.calls.borrow());
+    }
+
+    #[test]
+    fn test_empty_gesture_does_not_begin() {
+        let host = RecordingHost::default();
+        let mut gesture = Gesture::default();
+        assert!(!gesture.begin(&host, &[]));
+        assert!(!gesture.is_open());
+        assert!(!gesture.end(&host));
+        assert!(host.calls.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_linear_taper() {
+        let t = Taper::Linear { min: -24.0, max: 24.0 };
+        assert_eq!(0.0, t.to_plain(0.5));
+        assert_eq!(0.75, t.to_normalized(12.0));
+        assert_eq!(1.0, t.to_normalized(48.0));
+    }
+
+    #[test]
+    fn test_log_taper() {
+        let t = Taper::Log { min: 20.0, max: 20000.0 };
+        assert!((t.to_plain(1.0 / 3.0) - 200.0).abs() < 1e-2);
+        assert!((t.to_normalized(2000.0) - 2.0 / 3.0).abs() < 1e-6);
+    }
+}
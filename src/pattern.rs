@@ -0,0 +1,197 @@
+use std::error::Error;
+use crate::transport::Transport;
+
+/// One cell of a [`Pattern`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Step {
+    pub on: bool,
+    /// 0 to 1.
+    pub velocity: f32,
+    /// Chance of the step playing, 0 to 1.
+    pub probability: f32,
+}
+
+impl Default for Step {
+    fn default() -> Self {
+        Self { on: false, velocity: 0.8, probability: 1.0 }
+    }
+}
+
+const FORMAT_VERSION: u8 = 1;
+
+/// A grid of steps for sequencers, arpeggiators and gates, kept in the plugin state rather than
+/// as parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    rows: usize,
+    steps: usize,
+    /// Length of one step in quarter notes, e.g. 0.25 for sixteenths.
+    pub step_beats: f32,
+    cells: Vec<Step>,
+}
+
+impl Pattern {
+    pub fn new(rows: usize, steps: usize) -> Self {
+        let (rows, steps) = (rows.max(1), steps.max(1));
+        Self { rows, steps, step_beats: 0.25, cells: vec![Step::default(); rows * steps] }
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn get(&self, row: usize, step: usize) -> Step {
+        self.cells[row * self.steps + step]
+    }
+
+    pub fn get_mut(&mut self, row: usize, step: usize) -> &mut Step {
+        &mut self.cells[row * self.steps + step]
+    }
+
+    /// Changes the number of steps, keeping existing ones.
+    pub fn set_steps(&mut self, steps: usize) {
+        let steps = steps.max(1);
+        let mut cells = vec![Step::default(); self.rows * steps];
+        for row in 0..self.rows {
+            for step in 0..steps.min(self.steps) {
+                cells[row * steps + step] = self.get(row, step);
+            }
+        }
+        self.steps = steps;
+        self.cells = cells;
+    }
+
+    /// The step playing at the transport position, wrapping around the pattern.
+    pub fn step_at(&self, transport: &Transport) -> usize {
+        let step = (transport.ppq_pos / self.step_beats.max(1e-3) as f64).floor() as i64;
+        step.rem_euclid(self.steps as i64) as usize
+    }
+
+    /// Packs the pattern into a version byte, rows and steps as u16, the step length as f32
+    /// and three bytes per cell (on, velocity, probability), all little endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(9 + self.cells.len() * 3);
+        bytes.push(FORMAT_VERSION);
+        bytes.extend_from_slice(&(self.rows as u16).to_le_bytes());
+        bytes.extend_from_slice(&(self.steps as u16).to_le_bytes());
+        bytes.extend_from_slice(&self.step_beats.to_le_bytes());
+        let quantize = |v: f32| (v.clamp(0.0, 1.0) * 255.0).round() as u8;
+        for c in &self.cells {
+            bytes.extend_from_slice(&[c.on as u8, quantize(c.velocity), quantize(c.probability)]);
+        }
+        bytes
+    }
+
+    /// Reads a pattern written by [`Pattern::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Box<dyn Error>> {
+        match bytes.first() {
+            Some(&FORMAT_VERSION) => {}
+            Some(v) => return Err(format!("unknown pattern format version {}", v).into()),
+            None => return Err("empty pattern data".into()),
+        }
+        if bytes.len() < 9 {
+            return Err("truncated pattern header".into());
+        }
+        let rows = u16::from_le_bytes([bytes[1], bytes[2]]) as usize;
+        let steps = u16::from_le_bytes([bytes[3], bytes[4]]) as usize;
+        let step_beats = f32::from_le_bytes([bytes[5], bytes[6], bytes[7], bytes[8]]);
+        let body = &bytes[9..];
+        if rows == 0 || steps == 0 || body.len() != rows * steps * 3 {
+            return Err(format!("expected {} x {} cells, got {} bytes", rows, steps, body.len()).into());
+        }
+        let cells = body
+            .chunks(3)
+            .map(|c| Step { on: c[0] != 0, velocity: c[1] as f32 / 255.0, probability: c[2] as f32 / 255.0 })
+            .collect();
+        Ok(Self { rows, steps, step_beats, cells })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resize_keeps_steps() {
+        let mut p = Pattern::new(2, 4);
+        p.get_mut(1, 3).on = true;
+        p.set_steps(8);
+        assert!(p.get(1, 3).on && !p.get(1, 4).on);
+        p.set_steps(2);
+        assert_eq!(2 * 2, p.cells.len());
+    }
+
+    #[test]
+    fn test_step_at() {
+        let p = Pattern::new(1, 16);
+        let mut t = Transport { sample_rate: 48000.0, tempo: 120.0, ppq_pos: 1.3, playing: true };
+        assert_eq!(5, p.step_at(&t));
+        t.ppq_pos = 4.0 + 0.1;
+        assert_eq!(0, p.step_at(&t));
+        t.ppq_pos = -0.1;
+        assert_eq!(15, p.step_at(&t));
+    }
+
+    #[test]
+    fn test_step_at_loop_and_bar_boundaries() {
+        let at = |ppq_pos: f64| Transport { sample_rate: 44100.0, tempo: 90.0, ppq_pos, playing: true };
+        // one bar of sixteenths in 4/4 loops exactly on the bar line
+        let bar = Pattern::new(1, 16);
+        assert_eq!(15, bar.step_at(&at(3.99)));
+        assert_eq!(0, bar.step_at(&at(4.0)));
+        assert_eq!(0, bar.step_at(&at(8.0)));
+        assert_eq!(1, bar.step_at(&at(8.25)));
+        // twelve sixteenths loop every three beats, drifting against the bar
+        let mut odd = Pattern::new(1, 12);
+        assert_eq!(11, odd.step_at(&at(2.99)));
+        assert_eq!(0, odd.step_at(&at(3.0)));
+        assert_eq!(4, odd.step_at(&at(4.0)));
+        assert_eq!(8, odd.step_at(&at(8.0)));
+        // eighth notes
+        odd.step_beats = 0.5;
+        assert_eq!(2, odd.step_at(&at(7.0)));
+        assert_eq!(0, odd.step_at(&at(12.0)));
+    }
+
+    #[test]
+    fn test_bytes_round_trip_every_cell() {
+        let mut p = Pattern::new(4, 16);
+        for row in 0..4 {
+            for step in 0..16 {
+                let v = (row * 16 + step) as f32 / 63.0;
+                *p.get_mut(row, step) = Step { on: (row + step) % 3 == 0, velocity: v, probability: 1.0 - v };
+            }
+        }
+        p.step_beats = 1.0 / 3.0;
+        let read = Pattern::from_bytes(&p.to_bytes()).unwrap();
+        assert_eq!((p.rows(), p.steps(), p.step_beats), (read.rows(), read.steps(), read.step_beats));
+        for (a, b) in p.cells.iter().zip(&read.cells) {
+            assert_eq!(a.on, b.on);
+            assert!((a.velocity - b.velocity).abs() <= 0.5 / 255.0);
+            assert!((a.probability - b.probability).abs() <= 0.5 / 255.0);
+        }
+        // a second round trip is lossless
+        assert_eq!(read, Pattern::from_bytes(&read.to_bytes()).unwrap());
+
+        let mut bad = p.to_bytes();
+        bad[0] = 9;
+        assert!(Pattern::from_bytes(&bad).is_err());
+        assert!(Pattern::from_bytes(&[]).is_err());
+    }
+
+    #[test]
+    fn test_bytes_round_trip() {
+        let mut p = Pattern::new(3, 5);
+        *p.get_mut(2, 4) = Step { on: true, velocity: 0.5, probability: 0.25 };
+        p.step_beats = 0.5;
+        let read = Pattern::from_bytes(&p.to_bytes()).unwrap();
+        assert_eq!((3, 5, 0.5), (read.rows(), read.steps(), read.step_beats));
+        let s = read.get(2, 4);
+        assert!(s.on && (s.velocity - 0.5).abs() < 0.01 && (s.probability - 0.25).abs() < 0.01);
+        assert!(Pattern::from_bytes(&p.to_bytes()[..20]).is_err());
+    }
+}
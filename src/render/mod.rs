@@ -0,0 +1,312 @@
+//! Drawing of the editor's contents, kept apart from the window so it can run in `idle` on the
+//! host's thread or on a dedicated [`thread`].
+
+pub mod thread;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use glfw::WindowEvent;
+use crate::animation::{Clock, FrameClock};
+use crate::gui_elements::console::UILogConsole;
+use crate::gui_elements::theme::Theme;
+use crate::gui_elements::UIElement;
+use crate::layout::Node;
+
+pub use thread::{FramePacing, FrameStats};
+
+/// Scenes between `open` and `close` in the process. Leaks can only be told apart from live
+/// objects once the last one has closed.
+static OPEN_SCENES: AtomicUsize = AtomicUsize::new(0);
+
+/// The widgets, their layout and everything else that goes into a frame. Methods that touch
+/// widgets or the console need the GL context current.
+pub(crate) struct Scene {
+    pub size: (u32, u32),
+    pub theme: Theme,
+    pub widgets: Vec<Box<dyn UIElement>>,
+    pub layout: Option<Node>,
+    console: Option<UILogConsole>,
+    frame_clock: FrameClock,
+    animating: bool,
+    /// Set by changes the widgets cannot see themselves, like a resize or input.
+    needs_redraw: bool,
+}
+
+impl Scene {
+    pub fn new(size: (u32, u32), theme: Theme) -> Self {
+        Self {
+            size,
+            theme,
+            widgets: Vec::new(),
+            layout: None,
+            console: None,
+            frame_clock: FrameClock::default(),
+            animating: false,
+            needs_redraw: true,
+        }
+    }
+
+    pub fn set_clock(&mut self, clock: Box<dyn Clock>) {
+        self.frame_clock = FrameClock::new(clock);
+    }
+
+    /// Sets up GL state and the log console after the context was made current.
+    pub fn open(&mut self) {
+        OPEN_SCENES.fetch_add(1, Ordering::Relaxed);
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+        }
+        self.console = match UILogConsole::new() {
+            Ok(c) => Some(c),
+            Err(e) => {
+                vst_log::log(format!("failed to create log console: {}", e));
+                None
+            }
+        };
+        self.apply_theme();
+    }
+
+    /// Drops the widgets and the console, deleting their GL objects. The context has to be
+    /// current, the objects cannot be deleted once it is destroyed. In debug builds objects
+    /// still alive after the last scene closed are logged as leaks.
+    pub fn close(&mut self) {
+        self.console = None;
+        self.widgets.clear();
+        self.animating = false;
+        self.frame_clock.reset();
+        if OPEN_SCENES.fetch_sub(1, Ordering::Relaxed) == 1 && cfg!(debug_assertions) {
+            let leaked = crate::gui_elements::utils::gl::live_objects();
+            if !leaked.is_empty() {
+                vst_log::log(format!("GL objects leaked at close: {:?}", leaked));
+            }
+        }
+    }
+
+    /// Replaces the widgets, styling and placing the new ones.
+    pub fn set_widgets(&mut self, widgets: Vec<Box<dyn UIElement>>) {
+        self.widgets = widgets;
+        self.apply_theme();
+        self.relayout();
+    }
+
+    pub fn set_layout(&mut self, layout: Node) {
+        self.layout = Some(layout);
+        self.relayout();
+    }
+
+    pub fn set_size(&mut self, size: (u32, u32)) {
+        self.size = size;
+        self.relayout();
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.apply_theme();
+    }
+
+    pub fn invalidate(&mut self) {
+        self.needs_redraw = true;
+    }
+
+    /// True if a widget or the log console reported a running animation last frame.
+    pub fn is_animating(&self) -> bool {
+        self.animating
+    }
+
+    fn apply_theme(&mut self) {
+        self.needs_redraw = true;
+        if let Some(console) = self.console.as_mut() {
+            console.set_theme(&self.theme);
+        }
+        for widget in self.widgets.iter_mut() {
+            widget.set_theme(&self.theme);
+        }
+    }
+
+    fn relayout(&mut self) {
+        self.needs_redraw = true;
+        if let Some(layout) = self.layout.as_mut() {
+            layout.layout((self.size.0 as f32, self.size.1 as f32));
+            layout.apply(&mut self.widgets);
+        }
+    }
+
+    /// Hands a window event to the console, then to the widgets until one consumes it.
+    pub fn handle_event(&mut self, event: &WindowEvent) {
+        match *event {
+            WindowEvent::Size(w, h) => self.set_size((w.max(0) as u32, h.max(0) as u32)),
+            // the window was uncovered or restored, and what it showed may be gone
+            WindowEvent::Refresh | WindowEvent::Focus(_) | WindowEvent::Iconify(false) => self.needs_redraw = true,
+            _ => {}
+        }
+        if let Some(console) = self.console.as_mut() {
+            if console.handle_event(event) {
+                self.needs_redraw = true;
+                return;
+            }
+        }
+        for widget in self.widgets.iter_mut() {
+            if widget.handle_event(event) {
+                self.needs_redraw = true;
+                break;
+            }
+        }
+    }
+
+    /// Advances animations and draws a frame if anything changed. Returns true if it drew,
+    /// in which case the buffers have to be swapped.
+    pub fn draw(&mut self) -> bool {
+        let dt = self.frame_clock.tick();
+        let mut animating = false;
+        for widget in self.widgets.iter_mut() {
+            animating |= widget.animate(dt);
+        }
+        if let Some(console) = self.console.as_mut() {
+            animating |= console.animate(dt);
+        }
+        self.animating = animating;
+
+        // skip the frame when nothing changed. Frames are always redrawn whole: the back
+        // buffer is undefined after a swap, so redrawing only dirty regions would need a
+        // copy of the last frame.
+        let mut dirty = std::mem::take(&mut self.needs_redraw) || animating;
+        if let Some(console) = self.console.as_mut() {
+            dirty |= console.is_dirty();
+        }
+        if !dirty && !self.widgets.iter().any(|w| w.is_dirty()) {
+            return false;
+        }
+
+        unsafe {
+            let [r, g, b, a] = self.theme.background;
+            gl::ClearColor(r, g, b, a);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+
+        let mut text = match crate::gui_elements::text::UIText::new(
+            "fortnite battle pass",
+            50.0,
+            [1.0, 1.0, 1.0, 1.0],
+            [100.0,100.0],
+            glyph_brush::ab_glyph::FontRef::try_from_slice(include_bytes!("../gui_elements/text/fonts/source-code-pro.regular.ttf")).unwrap()
+        ) {
+            Ok(v) => v,
+            Err(e) => {
+                vst_log::log(e.to_string());
+                panic!("bruh");
+            }
+        };
+        text.render((640, 320));
+        let window_size = (self.size.0 as i32, self.size.1 as i32);
+        for widget in self.widgets.iter_mut() {
+            widget.render(window_size);
+        }
+        if let Some(console) = self.console.as_mut() {
+            console.render(window_size);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use crate::gui_elements::theme::Themed;
+    use crate::gui_elements::utils::gl::{live_objects, GlObject};
+    use crate::gui_elements::utils::t_matrix::TMatrix;
+
+    /// Reports the color in effect after every theme change.
+    struct Swatch {
+        color: Themed<[f32; 4]>,
+        shown: Rc<Cell<[f32; 4]>>,
+    }
+
+    impl UIElement for Swatch {
+        fn render(&mut self, _window_size: (i32, i32)) {}
+
+        fn get_window_transform() -> TMatrix {
+            TMatrix::default()
+        }
+
+        fn set_theme(&mut self, theme: &Theme) {
+            self.color.set_theme(theme.accent.normal);
+            self.shown.set(*self.color);
+        }
+    }
+
+    /// Remakes its framebuffer on every theme change, like widgets that draw offscreen do.
+    struct Offscreen {
+        made: bool,
+    }
+
+    impl UIElement for Offscreen {
+        fn render(&mut self, _window_size: (i32, i32)) {}
+
+        fn get_window_transform() -> TMatrix {
+            TMatrix::default()
+        }
+
+        fn set_theme(&mut self, _theme: &Theme) {
+            if self.made {
+                GlObject::Framebuffer.track(false);
+            }
+            GlObject::Framebuffer.track(true);
+            self.made = true;
+        }
+    }
+
+    impl Drop for Offscreen {
+        fn drop(&mut self) {
+            if self.made {
+                GlObject::Framebuffer.track(false);
+            }
+        }
+    }
+
+    #[test]
+    fn test_theme_switch_keeps_live_objects() {
+        // only this test counts framebuffers
+        let live = |kind| live_objects().into_iter().find(|&(k, _)| k == kind).map(|(_, n)| n);
+        let mut scene = Scene::new((100, 100), Theme::default());
+        scene.set_widgets(vec![Box::new(Offscreen { made: false })]);
+        assert_eq!(Some(1), live(GlObject::Framebuffer));
+        scene.set_theme(Theme::default());
+        scene.set_theme(Theme::default());
+        assert_eq!(Some(1), live(GlObject::Framebuffer));
+        drop(scene);
+        assert_eq!(None, live(GlObject::Framebuffer));
+    }
+
+    #[test]
+    fn test_refresh_redraws() {
+        let mut scene = Scene::new((100, 100), Theme::default());
+        for event in [WindowEvent::Refresh, WindowEvent::Focus(true), WindowEvent::Iconify(false)] {
+            scene.needs_redraw = false;
+            scene.handle_event(&event);
+            assert!(scene.needs_redraw, "{:?}", event);
+        }
+        scene.needs_redraw = false;
+        scene.handle_event(&WindowEvent::Iconify(true));
+        assert!(!scene.needs_redraw);
+    }
+
+    #[test]
+    fn test_custom_style_survives_theme() {
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let (custom, themed) = (Rc::new(Cell::new([0.0; 4])), Rc::new(Cell::new([0.0; 4])));
+        let mut swatch = Swatch { color: Themed::new([0.0; 4]), shown: custom.clone() };
+        swatch.color.set(red);
+        let plain = Swatch { color: Themed::new([0.0; 4]), shown: themed.clone() };
+
+        let mut scene = Scene::new((100, 100), Theme::default());
+        scene.set_widgets(vec![Box::new(swatch), Box::new(plain)]);
+        assert_eq!((red, Theme::default().accent.normal), (custom.get(), themed.get()));
+
+        let mut theme = Theme::default();
+        theme.accent.normal = [0.0, 0.0, 1.0, 1.0];
+        scene.set_theme(theme);
+        assert_eq!((red, [0.0, 0.0, 1.0, 1.0]), (custom.get(), themed.get()));
+    }
+}
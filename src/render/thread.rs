@@ -0,0 +1,196 @@
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread::{spawn, JoinHandle};
+use std::time::{Duration, Instant};
+
+use glfw::{Context, RenderContext, SwapInterval, WindowEvent};
+use crate::channel::{triple_buffer, TripleReader, TripleWriter};
+use crate::gui_elements::theme::Theme;
+use crate::layout::Node;
+use crate::SendWidgetBuilder;
+use super::Scene;
+
+/// How long a vsync paced thread waits when a frame had nothing to draw, since there was no
+/// swap to block on.
+const IDLE_WAIT: Duration = Duration::from_micros(16_667);
+
+/// How the render thread times its frames.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FramePacing {
+    /// Swaps wait for the display's refresh.
+    VSync,
+    /// Frames are timed to reach this many per second, with vsync off.
+    Fps(f32),
+}
+
+impl FramePacing {
+    /// The swap interval to set while the context is current on the editor's thread.
+    pub(crate) fn swap_interval(&self) -> SwapInterval {
+        match self {
+            FramePacing::VSync => SwapInterval::Sync(1),
+            FramePacing::Fps(_) => SwapInterval::None,
+        }
+    }
+
+    /// When to start the frame after one that started at `start` and ended now.
+    fn next_frame(&self, start: Instant, drawn: bool) -> Instant {
+        let now = Instant::now();
+        match *self {
+            FramePacing::VSync if drawn => now,
+            FramePacing::VSync => now + IDLE_WAIT,
+            // a late frame starts the next at once instead of trying to catch up
+            FramePacing::Fps(fps) => (start + Duration::from_secs_f32(1.0 / fps.max(1.0))).max(now),
+        }
+    }
+}
+
+/// What the render thread reports after every frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameStats {
+    /// Frames drawn since the editor opened.
+    pub frames: u64,
+    /// Drawn frames per second, smoothed. Zero while nothing changes.
+    pub fps: f32,
+    /// True if a widget or the log console reported a running animation last frame.
+    pub animating: bool,
+}
+
+impl FrameStats {
+    fn drawn(&mut self, interval: f32) {
+        self.frames += 1;
+        if interval > 0.0 {
+            let fps = 1.0 / interval;
+            self.fps = if self.fps == 0.0 { fps } else { self.fps + (fps - self.fps) * 0.1 };
+        }
+    }
+}
+
+/// What the editor tells the render thread.
+pub(crate) enum RenderMessage {
+    Event(WindowEvent),
+    Size((u32, u32)),
+    Theme(Theme),
+    Layout(Node),
+    Invalidate,
+    Close,
+}
+
+/// A thread drawing a [`Scene`] with the window's GL context current.
+pub(crate) struct RenderThread {
+    sender: Sender<RenderMessage>,
+    stats: TripleReader<FrameStats>,
+    handle: JoinHandle<SendWidgetBuilder>,
+}
+
+impl RenderThread {
+    /// Starts drawing widgets made by `builder`. The context must not be current on the
+    /// calling thread. The scene is made on the thread since widgets are not `Send`.
+    pub fn spawn(
+        mut context: RenderContext,
+        pacing: FramePacing,
+        (size, theme, layout): ((u32, u32), Theme, Option<Node>),
+        mut builder: SendWidgetBuilder,
+    ) -> Self {
+        let (sender, receiver) = channel();
+        let (mut writer, stats) = triple_buffer(FrameStats::default());
+        let handle = spawn(move || {
+            context.make_current();
+            let mut scene = Scene::new(size, theme);
+            scene.layout = layout;
+            scene.open();
+            scene.set_widgets(builder());
+            run(&mut context, &mut scene, pacing, receiver, &mut writer);
+            scene.close();
+            glfw::make_context_current(None);
+            builder
+        });
+        Self { sender, stats, handle }
+    }
+
+    pub fn send(&self, message: RenderMessage) {
+        // a thread that is gone has logged its panic already
+        let _ = self.sender.send(message);
+    }
+
+    pub fn stats(&mut self) -> FrameStats {
+        *self.stats.read()
+    }
+
+    /// Stops the thread once it dropped its widgets. Returns the builder unless the thread
+    /// panicked.
+    pub fn stop(self) -> Option<SendWidgetBuilder> {
+        self.send(RenderMessage::Close);
+        match self.handle.join() {
+            Ok(builder) => Some(builder),
+            Err(_) => {
+                vst_log::log("render thread panicked".to_string());
+                None
+            }
+        }
+    }
+}
+
+fn run(
+    context: &mut RenderContext,
+    scene: &mut Scene,
+    pacing: FramePacing,
+    receiver: Receiver<RenderMessage>,
+    stats: &mut TripleWriter<FrameStats>,
+) {
+    let mut next = Instant::now();
+    let mut last_drawn: Option<Instant> = None;
+    let mut frame = FrameStats::default();
+    loop {
+        // handle messages as they come in until the next frame is due
+        loop {
+            let message = match receiver.recv_timeout(next.saturating_duration_since(Instant::now())) {
+                Ok(message) => message,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            };
+            match message {
+                RenderMessage::Event(event) => scene.handle_event(&event),
+                RenderMessage::Size(size) => scene.set_size(size),
+                RenderMessage::Theme(theme) => scene.set_theme(theme),
+                RenderMessage::Layout(layout) => scene.set_layout(layout),
+                RenderMessage::Invalidate => scene.invalidate(),
+                RenderMessage::Close => return,
+            }
+        }
+
+        let start = Instant::now();
+        let drawn = scene.draw();
+        frame.animating = scene.is_animating();
+        if drawn {
+            context.swap_buffers();
+            let now = Instant::now();
+            frame.drawn(last_drawn.map_or(0.0, |t| (now - t).as_secs_f32()));
+            last_drawn = Some(now);
+        } else {
+            frame.fps = 0.0;
+            last_drawn = None;
+        }
+        stats.write(frame);
+        next = pacing.next_frame(start, drawn);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_pacing() {
+        let start = Instant::now();
+        let next = FramePacing::Fps(50.0).next_frame(start, true);
+        assert!(((next - start).as_secs_f32() - 0.02).abs() < 1e-6);
+        // a frame that took longer than its slot is not made up for
+        let late = start - Duration::from_secs(1);
+        assert!(FramePacing::Fps(50.0).next_frame(late, true) >= start);
+        assert!(FramePacing::VSync.next_frame(start, false) - start >= IDLE_WAIT);
+
+        let mut stats = FrameStats::default();
+        stats.drawn(0.0);
+        stats.drawn(0.5);
+        assert_eq!((2, 2.0), (stats.frames, stats.fps));
+    }
+}
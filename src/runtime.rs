@@ -0,0 +1,90 @@
+//! GLFW is process global: there is one library state, one event queue, one set of window
+//! hints and one current context per thread, no matter how many editors are open. Editors
+//! share it through [`GlfwRuntime`] handles, which are clones of one `Glfw` handle. glfw-rs
+//! counts those clones, so GLFW is initialized for the first editor and terminated after the
+//! last one is gone.
+//!
+//! Polling is safe to share. GLFW hands each event to the window it belongs to, and every
+//! window has its own queue, so an editor polling only fills the other editors' queues for
+//! their next `idle` and never takes their events.
+
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::{Rc, Weak as RcWeak};
+use std::sync::{Arc, Weak};
+use std::thread::{self, ThreadId};
+use glfw::Glfw;
+use parking_lot::{const_mutex, Mutex};
+
+/// The thread GLFW lives on, alive while any handle is.
+static OWNER: Mutex<Weak<ThreadId>> = const_mutex(Weak::new());
+
+thread_local! {
+    /// The handle every runtime on the owning thread shares.
+    static SHARED: RefCell<RcWeak<Glfw>> = const { RefCell::new(RcWeak::new()) };
+}
+
+/// Joins the thread that owns GLFW, or makes `thread` the owner if nobody holds it.
+fn claim(owner: &Mutex<Weak<ThreadId>>, thread: ThreadId) -> Result<Arc<ThreadId>, Box<dyn Error>> {
+    let mut owner = owner.lock();
+    match owner.upgrade() {
+        Some(t) if *t != thread => Err("GLFW is in use on another thread".into()),
+        Some(t) => Ok(t),
+        None => {
+            let t = Arc::new(thread);
+            *owner = Arc::downgrade(&t);
+            Ok(t)
+        }
+    }
+}
+
+/// A reference to the shared GLFW runtime. It is neither `Send` nor `Sync`: GLFW must only
+/// be called from the thread it was initialized on.
+pub struct GlfwRuntime {
+    glfw: Rc<Glfw>,
+    _owner: Arc<ThreadId>,
+}
+
+impl GlfwRuntime {
+    /// Initializes GLFW unless another handle already did. Fails if GLFW failed to initialize
+    /// or is held by another thread.
+    pub fn acquire() -> Result<Self, Box<dyn Error>> {
+        let owner = claim(&OWNER, thread::current().id())?;
+        let glfw = SHARED.with(|shared| -> Result<_, Box<dyn Error>> {
+            let mut shared = shared.borrow_mut();
+            if let Some(glfw) = shared.upgrade() {
+                return Ok(glfw);
+            }
+            let glfw = Rc::new(glfw::init(glfw::FAIL_ON_ERRORS)
+                .map_err(|e| format!("failed to initialize GLFW: {:?}", e))?);
+            *shared = Rc::downgrade(&glfw);
+            Ok(glfw)
+        })?;
+        Ok(Self { glfw, _owner: owner })
+    }
+
+    pub fn glfw(&self) -> Glfw {
+        (*self.glfw).clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_thread_check() {
+        let owner = const_mutex(Weak::new());
+        let here = thread::current().id();
+        let other = thread::spawn(|| thread::current().id()).join().unwrap();
+
+        let first = claim(&owner, here).unwrap();
+        let second = claim(&owner, here).unwrap();
+        assert!(claim(&owner, other).is_err());
+        drop(first);
+        assert!(claim(&owner, other).is_err());
+        drop(second);
+        // once all handles are gone another thread may take over
+        assert!(claim(&owner, other).is_ok());
+    }
+}
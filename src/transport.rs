@@ -0,0 +1,101 @@
+use std::time::Instant;
+use vst::api::{TimeInfo, TimeInfoFlags};
+use crate::channel::{triple_buffer, GuiEnd, TripleReader, TripleWriter};
+
+/// The host's transport state at a given sample, as far as widgets care about it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transport {
+    pub sample_rate: f64,
+    /// Beats per minute.
+    pub tempo: f64,
+    /// Position in quarter notes.
+    pub ppq_pos: f64,
+    pub playing: bool,
+}
+
+impl Transport {
+    /// Converts the host's time info. Returns `None` if the host did not report both tempo
+    /// and musical position.
+    pub fn from_time_info(info: &TimeInfo) -> Option<Self> {
+        let needed = TimeInfoFlags::TEMPO_VALID.bits() | TimeInfoFlags::PPQ_POS_VALID.bits();
+        if info.flags & needed != needed || info.tempo <= 0.0 || info.sample_rate <= 0.0 {
+            return None;
+        }
+        Some(Self {
+            sample_rate: info.sample_rate,
+            tempo: info.tempo,
+            ppq_pos: info.ppq_pos,
+            playing: info.flags & TimeInfoFlags::TRANSPORT_PLAYING.bits() != 0,
+        })
+    }
+
+    pub fn samples_per_beat(&self) -> f64 {
+        60.0 / self.tempo * self.sample_rate
+    }
+
+    /// The position `samples` later, assuming the tempo stays the same.
+    pub fn advanced(&self, samples: f64) -> Self {
+        Self {
+            ppq_pos: self.ppq_pos + samples / self.samples_per_beat(),
+            ..*self
+        }
+    }
+}
+
+/// A transport and when it was reported.
+type Stamped = Option<(Transport, Instant)>;
+
+/// Creates the audio thread and GUI ends of a transport feed for widgets that follow the host
+/// position, e.g. a sequencer playhead.
+pub fn transport_channel() -> (TransportSender, TransportSource) {
+    let (writer, reader) = triple_buffer(None);
+    (TransportSender { writer }, TransportSource { reader: GuiEnd::new(reader) })
+}
+
+/// Publishes the transport from `process`. Never blocks or allocates.
+pub struct TransportSender {
+    writer: TripleWriter<Stamped>,
+}
+
+impl TransportSender {
+    /// Call once per block with `Transport::from_time_info`.
+    pub fn set(&mut self, transport: Option<Transport>) {
+        self.writer.write(transport.map(|t| (t, Instant::now())));
+    }
+}
+
+/// The GUI end, cloned into every widget that needs it.
+#[derive(Clone)]
+pub struct TransportSource {
+    reader: GuiEnd<TripleReader<Stamped>>,
+}
+
+impl TransportSource {
+    /// The last reported transport. While playing, the position is moved forward by the time
+    /// passed since it was reported so a playhead keeps moving smoothly between blocks.
+    pub fn now(&self) -> Option<Transport> {
+        let (transport, at) = (*self.reader.lock().read())?;
+        if transport.playing {
+            Some(transport.advanced(at.elapsed().as_secs_f64() * transport.sample_rate))
+        } else {
+            Some(transport)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_extrapolates_while_playing() {
+        let (mut tx, rx) = transport_channel();
+        assert!(rx.now().is_none());
+        let t = Transport { sample_rate: 48000.0, tempo: 120.0, ppq_pos: 8.0, playing: true };
+        tx.set(Some(t));
+        let ppq_pos = rx.now().unwrap().ppq_pos;
+        assert!((8.0..8.5).contains(&ppq_pos), "{}", ppq_pos);
+        tx.set(Some(Transport { playing: false, ..t }));
+        assert_eq!(8.0, rx.now().unwrap().ppq_pos);
+    }
+}
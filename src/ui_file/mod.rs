@@ -0,0 +1,407 @@
+//! Describes the widget tree, its layout and parameter bindings in a RON file instead of code,
+//! so layouts can change without recompiling:
+//!
+//! ```text
+//! Column(
+//!     padding: 10,
+//!     gap: 8,
+//!     children: [
+//!         Widget(kind: "title", height: 24),
+//!         Grid(
+//!             columns: [Fr(1), Fr(1), Fr(1)],
+//!             rows: [Px(80), Auto],
+//!             grow: 1,
+//!             children: [
+//!                 Widget(kind: "knob", label: "Cutoff", binding: (param: 0, range: (20, 20000), taper: Log)),
+//!                 Widget(kind: "knob", label: "Reso", binding: (param: 1), cell: (1, 0)),
+//!                 Widget(kind: "scope", cell: (0, 1, 3, 1), props: {"range": 0.5}),
+//!             ],
+//!         ),
+//!     ],
+//! )
+//! ```
+//!
+//! Files are plain RON read with the extensions described at [`from_ron`]. Containers are
+//! `Row`, `Column`, `Grid` and `Anchor` and take the fields of
+//! [`Style`](crate::layout::Style): `width`, `height`, `min_width`, `max_width`, `min_height`,
+//! `max_height`, `grow`, `shrink`, `basis`, `margin`, `padding`, `align_self`, `cell`,
+//! `anchor`, `offset`, `justify`, `align` and `gap`, plus `columns`, `rows`, `column_gap` and
+//! `row_gap` on grids. `Widget` leaves take the same item fields, `kind` picks the
+//! constructor registered in a [`WidgetFactory`], and `binding`, `label`, `color` and `props`
+//! are handed to it in a [`WidgetSpec`]. Colors are written as `"#rrggbb"` strings or
+//! `(r, g, b, a)` tuples, see [`Color`].
+
+pub mod watch;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use ron::extensions::Extensions;
+use ron::Value;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use crate::gui_elements::color::Color;
+use crate::gui_elements::text::text_utils::Res;
+use crate::gui_elements::UIElement;
+use crate::layout::{Align, Edges, Grid, GridCell, Justify, Node, Style, Track};
+use crate::params::{Binding, Taper};
+
+/// Where the UI description is read from.
+#[derive(Debug, Clone)]
+pub enum UiSource {
+    /// Compiled in, e.g. with `include_str!` for release builds.
+    Embedded(&'static str),
+    /// Read on every open. Debug builds also rebuild the UI when the file changes.
+    File(PathBuf),
+}
+
+impl UiSource {
+    pub fn read(&self) -> Res<String> {
+        match self {
+            UiSource::Embedded(text) => Ok(text.to_string()),
+            UiSource::File(path) => std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e).into()),
+        }
+    }
+}
+
+/// A `Widget` entry of a UI file.
+#[derive(Debug, Clone)]
+pub struct WidgetSpec {
+    pub kind: String,
+    /// Size from `width` and `height`, zero where missing. The layout sets the final rect.
+    pub size: [f32; 2],
+    /// The parameter the widget controls, see [`BindingSpec`].
+    pub binding: Option<Binding>,
+    pub label: Option<String>,
+    pub color: Option<Color>,
+    /// Settings only some kinds read, from `props: {"name": value}`.
+    pub props: HashMap<String, Value>,
+}
+
+impl WidgetSpec {
+    /// The prop `name` read as a `T`, none if missing.
+    pub fn prop<T: DeserializeOwned>(&self, name: &str) -> Res<Option<T>> {
+        match self.props.get(name) {
+            Some(v) => v.clone().into_rust().map(Some).map_err(|e| format!("props.{}: {}", name, e).into()),
+            None => Ok(None),
+        }
+    }
+
+    /// `[0, 0, width, height]`, for constructors that want a rect.
+    pub fn rect(&self) -> [f32; 4] {
+        [0.0, 0.0, self.size[0], self.size[1]]
+    }
+}
+
+/// See [`WidgetFactory::register`].
+pub type WidgetConstructor = Box<dyn FnMut(&WidgetSpec) -> Res<Box<dyn UIElement>>>;
+
+/// Creates widgets for the `kind`s used in a UI file. Constructors are called with the GL
+/// context current, every time the UI is built.
+#[derive(Default)]
+pub struct WidgetFactory {
+    constructors: HashMap<String, WidgetConstructor>,
+}
+
+impl WidgetFactory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<S, F>(&mut self, kind: S, f: F)
+    where
+        S: Into<String>,
+        F: FnMut(&WidgetSpec) -> Res<Box<dyn UIElement>> + 'static,
+    {
+        self.constructors.insert(kind.into(), Box::new(f));
+    }
+
+    pub fn create(&mut self, spec: &WidgetSpec) -> Res<Box<dyn UIElement>> {
+        let constructor = self
+            .constructors
+            .get_mut(&spec.kind)
+            .ok_or_else(|| format!("unknown widget kind \"{}\"", spec.kind))?;
+        constructor(spec).map_err(|e| format!("widget \"{}\": {}", spec.kind, e).into())
+    }
+}
+
+/// Reads RON with the extensions UI and theme files are written with: `Row(gap: 4)` stands
+/// for `Row((gap: 4))` and optional fields take their value without `Some(..)`.
+pub fn from_ron<T: DeserializeOwned>(text: &str) -> Res<T> {
+    let options = ron::Options::default()
+        .with_default_extension(Extensions::UNWRAP_VARIANT_NEWTYPES | Extensions::IMPLICIT_SOME);
+    Ok(options.from_str(text)?)
+}
+
+/// Parses a UI description and creates its widgets. The layout refers to the widgets by
+/// their index in the returned list.
+pub fn build(text: &str, factory: &mut WidgetFactory) -> Res<(Vec<Box<dyn UIElement>>, Node)> {
+    let root: NodeSpec = from_ron(text)?;
+    let mut widgets = Vec::new();
+    let root = build_node(root, factory, &mut widgets)?;
+    Ok((widgets, root))
+}
+
+#[derive(Debug, Deserialize)]
+enum NodeSpec {
+    Row(NodeFields),
+    Column(NodeFields),
+    Grid(NodeFields),
+    Anchor(NodeFields),
+    Widget(NodeFields),
+}
+
+/// The fields of every node kind. Which ones a kind takes is checked by
+/// [`NodeFields::misplaced`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct NodeFields {
+    width: Option<f32>,
+    height: Option<f32>,
+    min_width: Option<f32>,
+    min_height: Option<f32>,
+    max_width: Option<f32>,
+    max_height: Option<f32>,
+    grow: Option<f32>,
+    shrink: Option<f32>,
+    basis: Option<f32>,
+    margin: Option<Edges>,
+    padding: Option<Edges>,
+    gap: Option<f32>,
+    align: Option<Align>,
+    align_self: Option<Align>,
+    justify: Option<Justify>,
+    cell: Option<GridCell>,
+    anchor: Option<[Align; 2]>,
+    offset: Option<[f32; 2]>,
+
+    children: Option<Vec<NodeSpec>>,
+
+    columns: Option<Vec<Track>>,
+    rows: Option<Vec<Track>>,
+    column_gap: Option<f32>,
+    row_gap: Option<f32>,
+
+    kind: Option<String>,
+    binding: Option<BindingSpec>,
+    label: Option<String>,
+    color: Option<Color>,
+    props: Option<HashMap<String, Value>>,
+}
+
+impl NodeFields {
+    /// The first field given that a `node` does not take.
+    fn misplaced(&self, node: &str) -> Option<&'static str> {
+        let container = [("children", self.children.is_some())];
+        let grid = [
+            ("columns", self.columns.is_some()),
+            ("rows", self.rows.is_some()),
+            ("column_gap", self.column_gap.is_some()),
+            ("row_gap", self.row_gap.is_some()),
+        ];
+        let widget = [
+            ("kind", self.kind.is_some()),
+            ("binding", self.binding.is_some()),
+            ("label", self.label.is_some()),
+            ("color", self.color.is_some()),
+            ("props", self.props.is_some()),
+        ];
+        let wrong: Vec<(&'static str, bool)> = match node {
+            "Widget" => container.into_iter().chain(grid).collect(),
+            "Grid" => widget.to_vec(),
+            _ => grid.into_iter().chain(widget).collect(),
+        };
+        wrong.into_iter().find(|(_, given)| *given).map(|(name, _)| name)
+    }
+
+    fn style(&self) -> Style {
+        let d = Style::default();
+        Style {
+            size: [self.width, self.height],
+            min: [self.min_width.unwrap_or(d.min[0]), self.min_height.unwrap_or(d.min[1])],
+            max: [self.max_width.unwrap_or(d.max[0]), self.max_height.unwrap_or(d.max[1])],
+            grow: self.grow.unwrap_or(d.grow),
+            shrink: self.shrink.unwrap_or(d.shrink),
+            basis: self.basis,
+            margin: self.margin.unwrap_or(d.margin),
+            padding: self.padding.unwrap_or(d.padding),
+            gap: self.gap.unwrap_or(d.gap),
+            align_items: self.align.unwrap_or(d.align_items),
+            align_self: self.align_self,
+            justify: self.justify.unwrap_or(d.justify),
+            cell: self.cell,
+            anchor: self.anchor.unwrap_or(d.anchor),
+            offset: self.offset.unwrap_or(d.offset),
+            ..d
+        }
+    }
+}
+
+/// A widget's parameter, written `binding: (param: 0, range: (20, 20000), taper: Log)`. The
+/// range defaults to 0 to 1 and the taper to `Linear`.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BindingSpec {
+    pub param: i32,
+    #[serde(default = "unit_range")]
+    pub range: (f32, f32),
+    #[serde(default)]
+    pub taper: TaperKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum TaperKind {
+    #[default]
+    Linear,
+    Log,
+}
+
+fn unit_range() -> (f32, f32) {
+    (0.0, 1.0)
+}
+
+impl BindingSpec {
+    pub fn to_binding(&self) -> Res<Binding> {
+        let (min, max) = self.range;
+        let taper = match self.taper {
+            TaperKind::Linear => Taper::Linear { min, max },
+            TaperKind::Log if min > 0.0 && max > 0.0 => Taper::Log { min, max },
+            TaperKind::Log => return Err(format!("a Log taper needs a positive range, got ({}, {})", min, max).into()),
+        };
+        Ok(Binding::new(self.param, taper))
+    }
+}
+
+fn build_node(spec: NodeSpec, factory: &mut WidgetFactory, widgets: &mut Vec<Box<dyn UIElement>>) -> Res<Node> {
+    let (name, fields) = match spec {
+        NodeSpec::Row(f) => ("Row", f),
+        NodeSpec::Column(f) => ("Column", f),
+        NodeSpec::Grid(f) => ("Grid", f),
+        NodeSpec::Anchor(f) => ("Anchor", f),
+        NodeSpec::Widget(f) => ("Widget", f),
+    };
+    if let Some(field) = fields.misplaced(name) {
+        return Err(format!("{}: unknown field \"{}\"", name, field).into());
+    }
+    let style = fields.style();
+    if name == "Widget" {
+        let kind = fields.kind.ok_or("Widget: missing kind")?;
+        let binding = match fields.binding {
+            Some(b) => Some(b.to_binding().map_err(|e| format!("Widget.binding: {}", e))?),
+            None => None,
+        };
+        let size = [style.size[0].unwrap_or(0.0), style.size[1].unwrap_or(0.0)];
+        let props = fields.props.unwrap_or_default();
+        widgets.push(factory.create(&WidgetSpec { kind, size, binding, label: fields.label, color: fields.color, props })?);
+        return Ok(Node::widget(widgets.len() - 1, style));
+    }
+
+    let mut children = Vec::new();
+    for child in fields.children.unwrap_or_default() {
+        children.push(build_node(child, factory, widgets)?);
+    }
+    let node = match name {
+        "Row" => Node::row(style, children),
+        "Column" => Node::column(style, children),
+        "Anchor" => Node::anchor(style, children),
+        _ => {
+            let grid = Grid {
+                columns: fields.columns.unwrap_or_default(),
+                rows: fields.rows.unwrap_or_default(),
+                column_gap: fields.column_gap.unwrap_or(0.0),
+                row_gap: fields.row_gap.unwrap_or(0.0),
+            };
+            Node::grid(style, grid, children)
+        }
+    };
+    Ok(node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glfw::WindowEvent;
+    use crate::gui_elements::utils::t_matrix::TMatrix;
+
+    struct Dummy;
+
+    impl UIElement for Dummy {
+        fn render(&mut self, _window_size: (i32, i32)) {}
+
+        fn get_window_transform() -> TMatrix {
+            TMatrix::default()
+        }
+
+        fn handle_event(&mut self, _event: &WindowEvent) -> bool {
+            false
+        }
+    }
+
+    fn factory() -> WidgetFactory {
+        let mut factory = WidgetFactory::new();
+        factory.register("knob", |spec| {
+            let binding = spec.binding.ok_or("missing binding")?;
+            assert_eq!(Binding::new(3, Taper::Log { min: 20.0, max: 20000.0 }), binding);
+            assert_eq!(Some("Cutoff"), spec.label.as_deref());
+            assert_eq!(Some(Color::rgba8(255, 128, 0, 255)), spec.color);
+            assert_eq!(Some(24), spec.prop::<u32>("steps")?);
+            assert_eq!(None, spec.prop::<u32>("detents")?);
+            Ok(Box::new(Dummy) as Box<dyn UIElement>)
+        });
+        factory.register("scope", |spec| {
+            assert_eq!(None, spec.binding);
+            Ok(Box::new(Dummy) as Box<dyn UIElement>)
+        });
+        factory
+    }
+
+    #[test]
+    fn test_build() {
+        let text = r##"
+            // comments and trailing commas are fine
+            Grid(
+                columns: [Fr(1), Px(100)],
+                rows: [Fr(1)],
+                padding: (10, 0),
+                column_gap: 10,
+                children: [
+                    Widget(kind: "scope"),
+                    Widget(
+                        kind: "knob",
+                        binding: (param: 3, range: (20, 20000), taper: Log),
+                        label: "Cutoff",
+                        color: "#ff8000",
+                        props: {"steps": 24},
+                        height: 40,
+                        align_self: Center,
+                    ),
+                ],
+            )
+        "##;
+        let (widgets, mut root) = build(text, &mut factory()).unwrap();
+        assert_eq!(2, widgets.len());
+        root.layout((300.0, 100.0));
+        assert_eq!(Some([10.0, 0.0, 170.0, 100.0]), root.widget_rect(0));
+        assert_eq!(Some([240.0, 30.0, 0.0, 40.0]), root.widget_rect(1));
+    }
+
+    #[test]
+    fn test_binding_defaults() {
+        let spec = |text: &str| from_ron::<BindingSpec>(text).unwrap().to_binding();
+        assert_eq!(Binding::new(2, Taper::Linear { min: 0.0, max: 1.0 }), spec("(param: 2)").unwrap());
+        assert_eq!(Binding::new(0, Taper::Linear { min: -24.0, max: 24.0 }), spec("(param: 0, range: (-24, 24))").unwrap());
+        assert!(spec("(param: 0, range: (0, 1), taper: Log)").is_err());
+    }
+
+    #[test]
+    fn test_build_errors() {
+        let err = |text: &str| build(text, &mut factory()).err().unwrap().to_string();
+        assert_eq!("unknown widget kind \"fader\"", err("Row(children: [Widget(kind: \"fader\")])"));
+        assert!(err("Row(colour: 1)").contains("`colour`"));
+        assert!(err("Fader(gap: 1)").contains("`Fader`"));
+        assert_eq!("Row: unknown field \"columns\"", err("Row(columns: [Auto])"));
+        assert_eq!("Widget: unknown field \"children\"", err("Widget(kind: \"scope\", children: [])"));
+        assert!(err("Column(padding: (1, 2, 3))").ends_with("expected 1, 2 or 4 numbers"));
+        assert!(err("Row(children: [Widget(kind: \"knob\", binding: (param: 1, taper: Log))])").starts_with("Widget.binding:"));
+        assert!(err("Row(gap: 4").starts_with("1:11:"));
+    }
+}
@@ -0,0 +1,39 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Notices when a file is modified by polling its modification time, which is cheap enough
+/// to do from `idle`.
+pub struct FileWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+    /// Minimum time between two looks at the file.
+    pub interval: Duration,
+}
+
+impl FileWatcher {
+    pub fn new(path: PathBuf) -> Self {
+        let modified = Self::modified(&path);
+        Self { path, modified, last_check: Instant::now(), interval: Duration::from_millis(250) }
+    }
+
+    fn modified(path: &PathBuf) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    /// True once after every change. A file that is missing, e.g. while an editor replaces
+    /// it, does not count as changed.
+    pub fn changed(&mut self) -> bool {
+        if self.last_check.elapsed() < self.interval {
+            return false;
+        }
+        self.last_check = Instant::now();
+        match Self::modified(&self.path) {
+            Some(modified) if Some(modified) != self.modified => {
+                self.modified = Some(modified);
+                true
+            }
+            _ => false,
+        }
+    }
+}
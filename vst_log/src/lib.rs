@@ -1,10 +1,87 @@
 use lazy_static::lazy_static;
+use std::collections::VecDeque;
 use std::fs::File;
 use std::io::Write;
 use std::sync::Mutex;
 
+pub mod rt;
+
+lazy_static! {
+    // created on first use so every instance in the process appends to the same file
+    static ref LOG_FILE: Mutex<Option<File>> = Mutex::new(None);
+    static ref HISTORY: Mutex<History> = Mutex::new(History {
+        next_id: 0,
+        entries: VecDeque::with_capacity(HISTORY_LEN),
+    });
+}
+
+/// Number of entries kept in memory for in-editor display.
+pub const HISTORY_LEN: usize = 1024;
+
+struct History {
+    next_id: u64,
+    entries: VecDeque<Entry>,
+}
+
+/// A log entry kept in the in-memory history.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    /// Increases by one for every logged entry, including ones that have since been evicted.
+    pub id: u64,
+    pub level: Level,
+    pub text: String,
+}
+
+/// Severity attached to a log entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+}
+
 // this will be made better in the future
 pub fn log(input: String) {
-    let mut f = File::create("vst_out.txt").unwrap();
-    write!(f, "{}\n", input).unwrap();
-}
\ No newline at end of file
+    log_level(Level::Info, &input);
+}
+
+/// Writes `input` to the log sink tagged with `level`.
+pub fn log_level(level: Level, input: &str) {
+    let mut file = LOG_FILE.lock().unwrap_or_else(|e| e.into_inner());
+    if file.is_none() {
+        *file = File::create("vst_out.txt").ok();
+    }
+    if let Some(f) = file.as_mut() {
+        let _ = writeln!(f, "[{}] {}", level.as_str(), input);
+    }
+    drop(file);
+
+    let mut history = HISTORY.lock().unwrap_or_else(|e| e.into_inner());
+    if history.entries.len() == HISTORY_LEN {
+        history.entries.pop_front();
+    }
+    let id = history.next_id;
+    history.next_id += 1;
+    history.entries.push_back(Entry { id, level, text: input.to_string() });
+}
+
+/// Returns the entries still in the history whose id is at least `id`, oldest first.
+/// Pass the id after the last entry seen to only fetch new ones.
+pub fn history_since(id: u64) -> Vec<Entry> {
+    let history = HISTORY.lock().unwrap_or_else(|e| e.into_inner());
+    history.entries.iter().filter(|e| e.id >= id).cloned().collect()
+}
@@ -0,0 +1,261 @@
+//! Real-time safe logging.
+//!
+//! The audio thread must never block or allocate, so [`RtLogProducer`] formats messages
+//! straight into a slot of a pre-allocated ring buffer. An [`RtLogDrainer`] on another
+//! thread (a background thread or the GUI idle loop) pulls the records out and writes
+//! them to the regular log sink.
+
+use std::cell::UnsafeCell;
+use std::fmt::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::Level;
+
+/// Maximum number of bytes of formatted text a single record can hold.
+/// Longer messages are truncated at a char boundary.
+pub const RT_RECORD_LEN: usize = 240;
+
+/// A fixed-size log record.
+#[derive(Clone, Copy)]
+pub struct RtRecord {
+    level: Level,
+    len: usize,
+    bytes: [u8; RT_RECORD_LEN],
+}
+
+impl RtRecord {
+    const EMPTY: Self = Self {
+        level: Level::Info,
+        len: 0,
+        bytes: [0; RT_RECORD_LEN],
+    };
+
+    pub fn level(&self) -> Level {
+        self.level
+    }
+
+    pub fn as_str(&self) -> &str {
+        // only ever filled through fmt::Write::write_str, which keeps the bytes valid utf8
+        std::str::from_utf8(&self.bytes[..self.len]).unwrap_or("")
+    }
+}
+
+impl fmt::Debug for RtRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RtRecord")
+         .field("level", &self.level)
+         .field("text", &self.as_str())
+         .finish()
+    }
+}
+
+impl Write for RtRecord {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let free = RT_RECORD_LEN - self.len;
+        let mut n = s.len().min(free);
+        while !s.is_char_boundary(n) {
+            n -= 1;
+        }
+        self.bytes[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
+
+struct Shared {
+    slots: Box<[UnsafeCell<RtRecord>]>,
+    // index of the next slot the producer writes
+    head: AtomicUsize,
+    // index of the next slot the drainer reads
+    tail: AtomicUsize,
+    dropped: AtomicUsize,
+    producer_alive: AtomicBool,
+}
+
+// Slots are only written by the single producer before `head` is published and only read
+// by the single drainer after observing `head`, so access never overlaps.
+unsafe impl Send for Shared {}
+unsafe impl Sync for Shared {}
+
+/// Creates a ring buffer holding up to `capacity` records and returns its two ends.
+pub fn rt_channel(capacity: usize) -> (RtLogProducer, RtLogDrainer) {
+    // one slot is kept empty to tell a full ring from an empty one
+    let slots = (0..capacity.max(1) + 1)
+        .map(|_| UnsafeCell::new(RtRecord::EMPTY))
+        .collect::<Vec<_>>()
+        .into_boxed_slice();
+    let shared = Arc::new(Shared {
+        slots,
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+        dropped: AtomicUsize::new(0),
+        producer_alive: AtomicBool::new(true),
+    });
+    (
+        RtLogProducer { shared: shared.clone() },
+        RtLogDrainer { shared },
+    )
+}
+
+/// The audio thread end of the ring. Never blocks and never allocates.
+pub struct RtLogProducer {
+    shared: Arc<Shared>,
+}
+
+impl RtLogProducer {
+    /// Formats `args` into the next free record. Returns false and bumps the dropped
+    /// counter if the ring is full.
+    pub fn log(&mut self, level: Level, args: fmt::Arguments) -> bool {
+        let shared = &*self.shared;
+        let head = shared.head.load(Ordering::Relaxed);
+        let next = (head + 1) % shared.slots.len();
+        if next == shared.tail.load(Ordering::Acquire) {
+            shared.dropped.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        let record = unsafe { &mut *shared.slots[head].get() };
+        record.level = level;
+        record.len = 0;
+        let _ = record.write_fmt(args);
+        shared.head.store(next, Ordering::Release);
+        true
+    }
+
+    /// Number of messages lost because the ring was full.
+    pub fn dropped(&self) -> usize {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for RtLogProducer {
+    fn drop(&mut self) {
+        self.shared.producer_alive.store(false, Ordering::Release);
+    }
+}
+
+/// The consuming end of the ring.
+pub struct RtLogDrainer {
+    shared: Arc<Shared>,
+}
+
+impl RtLogDrainer {
+    /// Hands every pending record to `f` and returns how many there were.
+    pub fn drain_with<F: FnMut(&RtRecord)>(&mut self, mut f: F) -> usize {
+        let shared = &*self.shared;
+        let head = shared.head.load(Ordering::Acquire);
+        let mut tail = shared.tail.load(Ordering::Relaxed);
+        let mut count = 0;
+        while tail != head {
+            f(unsafe { &*shared.slots[tail].get() });
+            tail = (tail + 1) % shared.slots.len();
+            shared.tail.store(tail, Ordering::Release);
+            count += 1;
+        }
+        count
+    }
+
+    /// Writes every pending record to the log sink. Meant to be called from the GUI idle
+    /// loop or a background thread. A note is logged whenever messages were dropped since
+    /// the last call.
+    pub fn drain(&mut self) -> usize {
+        let count = self.drain_with(|r| crate::log_level(r.level(), r.as_str()));
+        let dropped = self.take_dropped();
+        if dropped > 0 {
+            crate::log_level(Level::Warn, &format!("rt log overflowed, dropped {} messages", dropped));
+        }
+        count
+    }
+
+    /// Number of messages lost because the ring was full.
+    pub fn dropped(&self) -> usize {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Returns the dropped counter and resets it to zero.
+    pub fn take_dropped(&self) -> usize {
+        self.shared.dropped.swap(0, Ordering::Relaxed)
+    }
+
+    /// Moves the drainer onto a background thread that drains every `period`.
+    /// The thread exits after a final drain once the producer is dropped.
+    pub fn spawn(mut self, period: Duration) -> JoinHandle<()> {
+        thread::spawn(move || loop {
+            let alive = self.shared.producer_alive.load(Ordering::Acquire);
+            self.drain();
+            if !alive {
+                break;
+            }
+            thread::sleep(period);
+        })
+    }
+}
+
+/// Logs through an [`RtLogProducer`] using `format!` syntax without allocating.
+///
+/// `rt_log!(producer, Level::Warn, "voice {} stole", idx)`
+#[macro_export]
+macro_rules! rt_log {
+    ($producer:expr, $level:expr, $($arg:tt)+) => {
+        $producer.log($level, format_args!($($arg)+))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let (mut p, mut d) = rt_channel(4);
+        assert!(rt_log!(p, Level::Warn, "value {}", 3));
+        assert!(p.log(Level::Info, format_args!("second")));
+        let mut out = Vec::new();
+        assert_eq!(2, d.drain_with(|r| out.push((r.level(), r.as_str().to_string()))));
+        assert_eq!(vec![(Level::Warn, "value 3".to_string()), (Level::Info, "second".to_string())], out);
+        assert_eq!(0, d.drain_with(|_| {}));
+    }
+
+    #[test]
+    fn test_overflow_counts_dropped() {
+        let (mut p, mut d) = rt_channel(2);
+        assert!(p.log(Level::Info, format_args!("a")));
+        assert!(p.log(Level::Info, format_args!("b")));
+        assert!(!p.log(Level::Info, format_args!("c")));
+        assert_eq!(1, p.dropped());
+        assert_eq!(2, d.drain_with(|_| {}));
+        assert_eq!(1, d.take_dropped());
+        assert_eq!(0, d.dropped());
+        assert!(p.log(Level::Info, format_args!("d")));
+    }
+
+    #[test]
+    fn test_truncates_at_char_boundary() {
+        let (mut p, mut d) = rt_channel(1);
+        let long = "é".repeat(RT_RECORD_LEN);
+        p.log(Level::Info, format_args!("{}", long));
+        d.drain_with(|r| {
+            assert!(r.as_str().len() <= RT_RECORD_LEN);
+            assert!(r.as_str().chars().all(|c| c == 'é'));
+        });
+    }
+
+    #[test]
+    fn test_across_threads() {
+        let (mut p, mut d) = rt_channel(1024);
+        let t = thread::spawn(move || {
+            for i in 0..1000 {
+                rt_log!(p, Level::Debug, "{}", i);
+            }
+        });
+        t.join().unwrap();
+        let mut next = 0;
+        d.drain_with(|r| {
+            assert_eq!(next.to_string(), r.as_str());
+            next += 1;
+        });
+        assert_eq!(1000, next);
+    }
+}